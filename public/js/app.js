@@ -14,6 +14,8 @@ const state = {
   selectedDepots: new Set(),
   jobId: null,
   unlistenProgress: null,
+  lastDownloadConfig: null,
+  pausedDir: null,
   gameName: null,
   headerImage: null,
   notificationsEnabled: false,
@@ -22,6 +24,8 @@ const state = {
   // Search mode state
   searchRepos: [],
   selectedRepo: null,
+  recommendedRepo: null,
+  languageDepots: null,
   searchAppId: null,
   searchRepo: null,
   searchSha: null,
@@ -73,10 +77,12 @@ const els = {
   depotCount: $('#depot-count'),
   depotList: $('#depot-list'),
   btnSelectAll: $('#btn-select-all'),
+  depotLanguage: $('#depot-language'),
   btnDeselectAll: $('#btn-deselect-all'),
   btnBack: $('#btn-back'),
   btnDownload: $('#btn-download'),
   btnExportBat: $('#btn-export-bat'),
+  btnExportDocker: $('#btn-export-docker'),
   // Progress (depot download)
   depotProgressFill: $('#depot-progress-fill'),
   depotProgressText: $('#depot-progress-text'),
@@ -90,6 +96,7 @@ const els = {
   btnNew: $('#btn-new'),
   btnCancel: $('#btn-cancel'),
   btnStartOver: $('#btn-start-over'),
+  btnResume: $('#btn-resume'),
   mhApiKey: $('#mh-apikey'),
   downloadDirInput: $('#download-dir'),
   // Disk Space
@@ -315,6 +322,7 @@ async function performSearch() {
   els.searchGameBanner.classList.add('hidden');
   state.selectedRepo = null;
   state.searchRepos = [];
+  state.recommendedRepo = null;
   state.searchAppId = appId;
 
   // Show loading
@@ -340,9 +348,11 @@ async function performSearch() {
       date: r.date,
       sha: r.sha,
       type: r.type || 'unknown',
-      source: r.source || r.type || 'unknown'
+      source: r.source || r.type || 'unknown',
+      score: (raw.repo_scores || []).find(s => s.repo === r.repo) || null
     }));
     state.searchRepos = repos;
+    state.recommendedRepo = raw.recommended_repo || null;
 
     const githubRateLimited = raw.github_rate_limited;
 
@@ -421,8 +431,10 @@ function renderRepoList(repos) {
   autoCard.innerHTML = `
     <div class="repo-card__radio"></div>
     <div class="repo-card__info">
-      <div class="repo-card__name">⚡ Auto (newest)</div>
-      <div class="repo-card__date">Automatically selects the most recently updated repository</div>
+      <div class="repo-card__name">⚡ ${state.recommendedRepo ? 'Auto (best keys)' : 'Auto (newest)'}</div>
+      <div class="repo-card__date">${state.recommendedRepo
+        ? `Selects ${escapeHtml(state.recommendedRepo)}, which has keys for the most depots`
+        : 'Automatically selects the most recently updated repository'}</div>
     </div>
   `;
   autoCard.addEventListener('click', () => selectRepo('auto'));
@@ -442,6 +454,7 @@ function renderRepoList(repos) {
       <div class="repo-card__info">
         <div class="repo-card__name">${escapeHtml(repo.name)}</div>
         <div class="repo-card__date">Updated: ${dateStr}</div>
+        ${repo.score ? `<div class="repo-card__date">Keys: ${repo.score.keyed_depots}/${repo.score.depot_count} depots</div>` : ''}
       </div>
       <span class="repo-card__badge ${badgeClass}">${escapeHtml(repo.source || repo.type || 'unknown')}</span>
     `;
@@ -483,8 +496,9 @@ function selectRepo(indexOrAuto) {
       showSearchError('No repositories found');
       return;
     }
-    // Select the newest repo (first one, assuming sorted by date)
-    state.selectedRepo = { ...state.searchRepos[0], _auto: true };
+    // Select the recommended repo if repos were scored, else the newest (first, sorted by date)
+    const best = state.searchRepos.find(r => r.name === state.recommendedRepo) || state.searchRepos[0];
+    state.selectedRepo = { ...best, _auto: true };
   } else {
     state.selectedRepo = state.searchRepos[indexOrAuto];
   }
@@ -598,12 +612,18 @@ async function proceedFromSearch() {
       depots = (mRaw.manifests || []).map(m => ({
         depotId: String(m.depot_id),
         manifestId: m.manifest_id || 'N/A',
-        depotKey: m.depot_key || null
+        depotKey: m.depot_key || null,
+        repoPath: m.path && m.path !== m.filename ? m.path : null
       }));
 
       state.searchRepo = repo.name;
       state.searchSha = repo.sha;
       state.searchKeyVdfKeys = mRaw.depot_keys || null;
+
+      if (mRaw.dangling_manifests && mRaw.dangling_manifests.length > 0) {
+        const list = mRaw.dangling_manifests.map(d => `${d.depot_id}_${d.manifest_id}`).join(', ');
+        console.warn(`[Search] ${repo.name}'s lua names manifests missing from the branch: ${list}`);
+      }
     }
 
     els.manifestLoading.classList.add('hidden');
@@ -785,6 +805,53 @@ function showSelectionStep() {
 
   updateDownloadButton();
   goToStep(2);
+  loadLanguageDepots(data.mainAppId);
+}
+
+// Group the app's depots by language from the public app info, offer a language picker,
+// and pre-select the preferred languages' depots if the user hasn't picked any yet
+async function loadLanguageDepots(appId) {
+  state.languageDepots = null;
+  els.depotLanguage.classList.add('hidden');
+
+  try {
+    const result = await invoke('get_language_depots', { appId: String(appId), languages: null });
+    if (!state.parsedData || String(state.parsedData.mainAppId) !== String(appId)) return;
+
+    const languages = Object.keys(result.languages);
+    if (languages.length < 2) return;
+
+    state.languageDepots = result;
+    els.depotLanguage.innerHTML = '<option value="">Language…</option>' + languages
+      .map(l => `<option value="${escapeHtml(l)}">${escapeHtml(l)} (${result.languages[l].length})</option>`)
+      .join('');
+    els.depotLanguage.classList.remove('hidden');
+
+    if (result.autoSelect.length > 0 && state.selectedDepots.size === 0) {
+      selectDepotIds(result.autoSelect);
+    }
+  } catch (e) {
+    // Public app info is optional (disabled in settings or unknown app)
+  }
+}
+
+function selectLanguage(language) {
+  if (!state.languageDepots || !language) return;
+  selectDepotIds([...state.languageDepots.neutral, ...(state.languageDepots.languages[language] || [])]);
+}
+
+// Select exactly the listed depots among those shown
+function selectDepotIds(depotIds) {
+  const wanted = new Set(depotIds.map(String));
+  const matching = state.parsedData.depots.filter(d => wanted.has(String(d.depotId)));
+  if (matching.length === 0) return;
+
+  state.selectedDepots.clear();
+  matching.forEach(d => state.selectedDepots.add(d.depotId));
+  $$('.depot-item').forEach((el) => {
+    el.classList.toggle('selected', wanted.has(el.dataset.depotId));
+  });
+  updateDownloadButton();
 }
 
 function toggleDepot(depotId, element) {
@@ -818,6 +885,7 @@ function updateDownloadButton() {
   const count = state.selectedDepots.size;
   els.btnDownload.disabled = count === 0;
   els.btnExportBat.disabled = count === 0;
+  els.btnExportDocker.disabled = count === 0;
   els.btnDownload.innerHTML = `
     Download${count > 0 ? ` (${count})` : ''}
     <svg class="btn__icon" viewBox="0 0 24 24" fill="none" stroke="currentColor" stroke-width="2">
@@ -829,7 +897,7 @@ function updateDownloadButton() {
 }
 
 // ============ Export Batch Script ============
-async function exportBatScript() {
+async function exportBatScript(scriptType = 'native') {
   const data = state.parsedData;
   const selectedDepots = data.depots.filter(d => state.selectedDepots.has(d.depotId));
   if (selectedDepots.length === 0) return;
@@ -851,23 +919,34 @@ async function exportBatScript() {
   // Determine folder name
   const folderName = safeGameName ? `${data.mainAppId} - ${safeGameName}` : String(data.mainAppId);
 
+  const exportConfig = {
+    appId: String(data.mainAppId),
+    depots: depotsWithCustomManifests,
+    folderName,
+    downloadDir: getDownloadDir() || null,
+    gameName,
+    scriptType
+  };
+  // Docker scripts are shell scripts on every platform
+  const ext = scriptType === 'docker' ? 'sh' : 'bat';
+
   try {
-    const script = await invoke('export_batch_script', {
-      config: {
-        appId: String(data.mainAppId),
-        depots: depotsWithCustomManifests,
-        folderName,
-        downloadDir: getDownloadDir() || null,
-        gameName
-      }
-    });
+    const missing = await invoke('validate_export', { config: exportConfig, dir: null });
+    if (missing.length > 0 && !confirm(
+      'The script references files that don\'t exist yet. Run the download first, or these depots will fail:\n\n'
+      + missing.join('\n') + '\n\nExport anyway?'
+    )) {
+      return;
+    }
+
+    const script = await invoke('export_batch_script', { config: exportConfig });
 
     // Use Tauri save dialog to pick where to save the .bat file
     try {
       const { save } = window.__TAURI__.dialog;
       const savePath = await save({
-        filters: [{ name: 'Batch Script', extensions: ['bat'] }],
-        defaultPath: `${data.mainAppId}_${safeGameName}_download.bat`
+        filters: [{ name: scriptType === 'docker' ? 'Shell Script' : 'Batch Script', extensions: [ext] }],
+        defaultPath: `${data.mainAppId}_${safeGameName}_download.${ext}`
       });
       if (savePath) {
         // Write using Tauri fs plugin
@@ -880,7 +959,7 @@ async function exportBatScript() {
       const url = URL.createObjectURL(blob);
       const a = document.createElement('a');
       a.href = url;
-      a.download = `${data.mainAppId}_${safeGameName}_download.bat`;
+      a.download = `${data.mainAppId}_${safeGameName}_download.${ext}`;
       document.body.appendChild(a);
       a.click();
       document.body.removeChild(a);
@@ -906,6 +985,40 @@ async function startDownload() {
   if (mhApiKey) localStorage.setItem(MH_APIKEY_STORAGE_KEY, mhApiKey);
   saveDownloadDir();
 
+  // Ask before downloading an app that's already in the download folder
+  try {
+    const existing = await invoke('find_existing_download', {
+      appId: String(data.mainAppId),
+      downloadDir: getDownloadDir() || null
+    });
+    if (existing.length > 0) {
+      const found = existing[0];
+      const kind = found.complete ? 'a complete download' : 'a partial download';
+      if (!confirm(`You already have ${kind} of this app in:\n${found.path}\n\nDownload it again?`)) return;
+    }
+  } catch (e) {
+    // The check is only a convenience; never block a download on it
+  }
+
+  // Warn before writing into a folder of the same name that holds something else
+  try {
+    const preview = await invoke('preview_download_path', {
+      config: {
+        mainAppId: String(data.mainAppId),
+        selectedDepots: [],
+        downloadDir: getDownloadDir() || null,
+        gameName: state.gameName || null
+      }
+    });
+    const collision = preview.collision;
+    if (collision && collision.conflict) {
+      const hint = collision.suggestion ? `\n\nRename or move it (e.g. to "${collision.suggestion}") to keep it separate.` : '';
+      if (!confirm(`The download folder "${collision.existingName}" already exists and holds other files:\n${preview.path}${hint}\n\nDownload into it anyway?`)) return;
+    }
+  } catch (e) {
+    // Same as above: a failed check never blocks the download
+  }
+
   // Collect custom manifest IDs and uploaded manifest files from inputs
   const depotsWithCustomManifests = selectedDepots.map(depot => {
     const input = document.querySelector(`.custom-manifest-input[data-depot-id="${depot.depotId}"]`);
@@ -952,6 +1065,7 @@ async function startDownload() {
     }
 
     // Start download via Tauri invoke
+    state.lastDownloadConfig = downloadConfig;
     const result = await invoke('start_download', { config: downloadConfig });
 
     state.jobId = result.jobId;
@@ -975,6 +1089,7 @@ function initProgressUI(depots) {
   els.btnCancel.disabled = false;
   els.btnCancel.innerHTML = '✕ Cancel Download';
   els.btnStartOver.classList.add('hidden');
+  els.btnResume.classList.add('hidden');
   els.diskSpaceInfo.classList.add('hidden');
   // Reset depot download progress bar
   if (els.depotProgressFill) els.depotProgressFill.style.width = '0%';
@@ -1049,6 +1164,10 @@ function handleProgressMessage(msg) {
     case 'cancelled':
       handleCancelled(msg);
       break;
+
+    case 'paused':
+      handlePaused(msg);
+      break;
   }
 }
 
@@ -1056,7 +1175,7 @@ function handleStatusUpdate(msg) {
   switch (msg.step) {
     case 'checking_branch':
       els.progressStatus.textContent = `Checking GitHub branch for App ${msg.appId}...`;
-      appendTerminalLine(`Checking branch for App ${msg.appId}...`, 'info');
+      appendTerminalLine(`Checking branch for App ${msg.appId} in ${msg.repo}...`, 'info');
       break;
 
     case 'branch_found':
@@ -1084,11 +1203,44 @@ function handleStatusUpdate(msg) {
       appendTerminalLine(`Downloading custom manifest for depot ${msg.depotId} (ID: ${msg.manifestId}) via ManifestHub API...`, 'info');
       break;
 
+    case 'key_invalid':
+      updateDepotStatus(msg.depotId, 'error', 'Key mismatch');
+      appendTerminalLine(`⚠ ${msg.message}`, 'error');
+      break;
+
+    case 'manifest_hub_served':
+      appendTerminalLine(msg.message, 'info');
+      break;
+
+    case 'manifest_from_ddm':
+      updateDepotStatus(msg.depotId, 'active', 'Latest manifest (resolved by DepotDownloader)');
+      appendTerminalLine(msg.message, 'info');
+      break;
+
+    case 'manifests_complete':
+      appendTerminalLine(`✓ ${msg.succeeded}/${msg.total} manifests ready`, msg.failed ? 'error' : 'success');
+      break;
+
     case 'generating_keys':
       els.progressStatus.textContent = 'Generating depot keys file...';
       appendTerminalLine('Generating steam.keys file...', 'info');
       break;
 
+    case 'key_source': {
+      const sourceNames = { config: 'depot list', key_vdf: 'Key.vdf', repo_key_vdf: 'repo Key.vdf' };
+      appendTerminalLine(`Keys from ${sourceNames[msg.source] || msg.source}: ${msg.depotCount}/${msg.total} depots`, 'info');
+      break;
+    }
+
+    case 'downloading_keyvdf':
+      els.progressStatus.textContent = `Downloading Key.vdf from ${msg.repo}...`;
+      appendTerminalLine(`Downloading Key.vdf from ${msg.repo}...`, 'info');
+      break;
+
+    case 'key_source_failed':
+      appendTerminalLine(msg.message, 'error');
+      break;
+
     case 'keys_generated':
       appendTerminalLine(`✓ Generated keys for ${msg.depotCount} depots`, 'success');
       break;
@@ -1108,6 +1260,10 @@ function handleStatusUpdate(msg) {
         appendTerminalLine(`> ${msg.command}`, 'info');
       }
       break;
+
+    case 'cleanup_failed':
+      appendTerminalLine(`⚠ ${msg.message}`, 'error');
+      break;
   }
 }
 
@@ -1117,9 +1273,12 @@ function handleOutput(msg) {
   const text = msg.output || msg.line;
   if (text) {
     appendTerminalLine(text, cls);
-    // Parse depot download percentage from output (e.g. "01.83% depots\...")
+    // The backend sends the parsed percentage (bytes, or chunk counts as a fallback);
+    // otherwise parse it from the output (e.g. "01.83% depots\...")
     const percentMatch = text.match(/^\s*(\d{1,3}(?:\.\d{1,2})?)%/);
-    if (percentMatch) {
+    if (typeof msg.percent === 'number') {
+      updateDepotDownloadProgress(msg.percent);
+    } else if (percentMatch) {
       const percent = parseFloat(percentMatch[1]);
       updateDepotDownloadProgress(percent);
     }
@@ -1147,6 +1306,11 @@ function handleComplete(msg) {
     results.forEach((r) => {
       updateDepotStatus(r.depotId, r.success ? 'done' : 'error', r.success ? 'Complete' : 'Failed');
     });
+
+    const anonymous = results.filter((r) => r.usedKey === false).map((r) => r.depotId);
+    if (anonymous.length > 0 && anonymous.length < results.length) {
+      appendTerminalLine(`Downloaded without a depot key (anonymous): ${anonymous.join(', ')}`, 'info');
+    }
   }
 
   appendTerminalLine(`\n${msg.message}`, 'success');
@@ -1182,6 +1346,40 @@ function handleCancelled(msg) {
   cleanupProgressListener();
 }
 
+function handlePaused(msg) {
+  els.progressStatus.textContent = '⏸ Paused';
+  appendTerminalLine(`\n${msg.message}`, 'info');
+  showCompletion(false, msg.message);
+  cleanupProgressListener();
+  state.jobId = null;
+
+  // Resuming needs the folder and the config the job was started with
+  state.pausedDir = msg.downloadDir || null;
+  if (state.pausedDir && state.lastDownloadConfig) {
+    els.btnResume.classList.remove('hidden');
+  }
+  showBrowserNotification('Download Paused', msg.message, state.headerImage);
+}
+
+async function resumeDownload() {
+  const config = state.lastDownloadConfig;
+  const folderPath = state.pausedDir;
+  if (!config || !folderPath) return;
+
+  initProgressUI(config.selectedDepots);
+  state.pausedDir = null;
+
+  try {
+    const result = await invoke('resume_into_folder', { config, folderPath });
+    state.jobId = result.jobId;
+    appendTerminalLine(`Resuming in ${result.downloadDir}`, 'info');
+    connectProgressListener();
+  } catch (error) {
+    appendTerminalLine(`Error: ${error}`, 'error');
+    showCompletion(false, String(error));
+  }
+}
+
 // ============ UI Helpers ============
 function updateDepotStatus(depotId, status, text) {
   const item = document.getElementById(`depot-progress-${depotId}`);
@@ -1241,10 +1439,13 @@ function resetApp() {
   state.parsedData = null;
   state.selectedDepots.clear();
   state.jobId = null;
+  state.lastDownloadConfig = null;
+  state.pausedDir = null;
   state.gameName = null;
   state.headerImage = null;
   state.depotManifests = {};
   state.searchRepos = [];
+  state.recommendedRepo = null;
   state.selectedRepo = null;
   state.searchAppId = null;
   state.searchSha = null;
@@ -1441,7 +1642,10 @@ async function checkDotNet() {
     const result = await invoke('check_dotnet');
     if (!result.installed) {
       console.warn('.NET 9 runtime not found. DepotDownloader requires .NET 9.');
-      // Could show a UI warning banner here
+      const install = await invoke('get_dotnet_install_url');
+      if (install.url && confirm(`DepotDownloader needs the .NET 9 runtime, which isn't installed.\n\nDownload the ${install.arch} installer now?`)) {
+        await window.__TAURI__.shell.open(install.url);
+      }
     }
   } catch (e) {
     console.error('Failed to check .NET:', e);
@@ -1463,12 +1667,15 @@ function initEvents() {
 
   // Select
   els.btnSelectAll.addEventListener('click', selectAll);
+  els.depotLanguage.addEventListener('change', () => selectLanguage(els.depotLanguage.value));
   els.btnDeselectAll.addEventListener('click', deselectAll);
   els.btnBack.addEventListener('click', () => goToStep(1));
   els.btnDownload.addEventListener('click', startDownload);
-  els.btnExportBat.addEventListener('click', exportBatScript);
+  els.btnExportBat.addEventListener('click', () => exportBatScript('native'));
+  els.btnExportDocker.addEventListener('click', () => exportBatScript('docker'));
   els.btnNew.addEventListener('click', resetApp);
   els.btnStartOver.addEventListener('click', resetApp);
+  els.btnResume.addEventListener('click', resumeDownload);
   els.btnCancel.addEventListener('click', showCancelModal);
   els.btnCancelYes.addEventListener('click', cancelDownload);
   els.btnCancelNo.addEventListener('click', hideCancelModal);
@@ -1523,7 +1730,7 @@ function initTauri() {
 
   btnCloseYes.addEventListener('click', () => {
     closeModal.classList.add('hidden');
-    invoke('close_window');
+    invoke('confirm_close_window');
   });
 
   // Close modal on backdrop click
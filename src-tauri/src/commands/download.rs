@@ -2,19 +2,28 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use serde::Deserialize;
 use tauri::{command, AppHandle, Manager};
-
-#[cfg(target_os = "windows")]
-use std::os::windows::process::CommandExt;
 use uuid::Uuid;
 
-use crate::services::{AppState, JobInfo};
+use crate::services::{AppState, JobInfo, CANCEL_REASONS, OUTPUT_TAIL_LINES};
+use crate::services::alternative_sources;
+use crate::services::github_api;
 use crate::services::depot_runner::{self, DepotRunConfig, ProgressEvent, emit_progress};
-use crate::services::manifest_downloader;
+use crate::services::manifest_downloader::{self, ManifestFetchOptions};
 use crate::services::manifest_hub_api;
 use crate::services::steam_store_api;
 use crate::services::vdf_parser;
 use crate::services::lua_parser::DepotInfo;
 use crate::services::depot_keys_generator;
+use crate::services::key_backup;
+use crate::services::staging;
+use crate::services::depot_state;
+use crate::services::disk_space;
+use crate::services::file_manager;
+use crate::services::folder_collision;
+use crate::services::trash;
+use crate::services::manifest_parser;
+use crate::services::output_layout::{self, OutputLayout};
+use crate::services::settings::{self as settings_service, Settings};
 
 #[derive(Debug, Deserialize)]
 pub struct DownloadConfig {
@@ -36,13 +45,40 @@ pub struct DownloadConfig {
     pub download_location: Option<String>,
     #[serde(rename = "manifestHubApiKey")]
     pub manifest_hub_api_key: Option<String>,
+    /// Settings to use for this job only, merged over the saved settings.
+    #[serde(rename = "settingsOverrides", alias = "settings_overrides")]
+    pub settings_overrides: Option<serde_json::Value>,
+    /// Platform to download for ("windows", "macos", "linux"); overrides the `target_os` setting
+    #[serde(rename = "targetOs", alias = "target_os", default)]
+    pub target_os: Option<String>,
+    /// Architecture to download for ("32", "64"); overrides the `target_arch` setting
+    #[serde(rename = "targetArch", alias = "target_arch", default)]
+    pub target_arch: Option<String>,
+}
+
+impl DownloadConfig {
+    /// `settingsOverrides` with `targetOs`/`targetArch` folded in, so they are validated
+    /// like any other override. The dedicated fields win over the same keys in the object.
+    fn job_overrides(&self) -> Option<serde_json::Value> {
+        let mut overrides = self.settings_overrides.clone();
+        for (key, value) in [("target_os", &self.target_os), ("target_arch", &self.target_arch)] {
+            if let Some(value) = value {
+                let object = overrides.get_or_insert_with(|| serde_json::json!({}));
+                if let Some(fields) = object.as_object_mut() {
+                    fields.insert(key.to_string(), serde_json::Value::String(value.clone()));
+                }
+            }
+        }
+        overrides
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct DepotConfig {
     #[serde(rename = "depotId", alias = "depot_id")]
     pub depot_id: String,
-    #[serde(rename = "manifestId", alias = "manifest_id")]
+    /// Empty (or "N/A"/"latest" as sent) when no manifest is known; see `uses_latest_manifest`
+    #[serde(rename = "manifestId", alias = "manifest_id", default)]
     pub manifest_id: String,
     #[serde(rename = "customManifestId", alias = "custom_manifest_id")]
     pub custom_manifest_id: Option<String>,
@@ -50,84 +86,84 @@ pub struct DepotConfig {
     pub depot_key: Option<String>,
     #[serde(rename = "uploadedManifestPath")]
     pub uploaded_manifest_path: Option<String>,
+    /// Manifest's path in the repo branch when it isn't at the root (`RepoManifests` `path`)
+    #[serde(rename = "repoPath", alias = "repo_path", default)]
+    pub repo_path: Option<String>,
+    /// Download only these files from the depot (passed to DDM via `-filelist`)
+    #[serde(rename = "fileList", alias = "file_list", default)]
+    pub file_list: Option<Vec<String>>,
 }
 
-/// Start a download job. Returns { jobId, downloadDir } immediately,
+impl DepotConfig {
+    /// No manifest file or id to fetch: DDM is run without `-manifest`/`-manifestfile`
+    /// and downloads the depot's current manifest itself (needs the depot key).
+    fn uses_latest_manifest(&self) -> bool {
+        self.uploaded_manifest_path.is_none() && self.custom_manifest_id.is_none() && self.manifest_id.is_empty()
+    }
+}
+
+/// Start a download job. Returns { jobId, downloadDir, folderName, plan } immediately,
 /// then runs the download pipeline asynchronously emitting progress events.
 #[command]
 pub async fn start_download(
     app: AppHandle,
     state: tauri::State<'_, AppState>,
-    config: DownloadConfig,
+    mut config: DownloadConfig,
 ) -> Result<serde_json::Value, String> {
-    let job_id = Uuid::new_v4().to_string();
+    // Normalize pasted manifest ids before anything touches the filesystem
+    for depot in &mut config.depots {
+        normalize_depot(depot)?;
+    }
 
-    // Determine base download directory
-    let base_dir = resolve_download_dir(config.download_location.as_deref())
-        .unwrap_or_else(|| {
-            let home = std::env::var("USERPROFILE")
-                .or_else(|_| std::env::var("HOME"))
-                .unwrap_or_else(|_| ".".to_string());
-            PathBuf::from(home).join("Documents").join("SteamDownloads")
-        });
+    // Resolve the settings for this job, rejecting bad overrides before a job exists
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let mut settings = settings_service::load_settings(&app_data_dir).await;
+    if let Some(ref overrides) = config.job_overrides() {
+        settings = settings_service::apply_overrides(&settings, overrides)?;
+    }
+
+    let resolved = resolve_download_path(&state, &config).await;
 
     // Create base dir
-    tokio::fs::create_dir_all(&base_dir)
+    tokio::fs::create_dir_all(&resolved.base_dir)
         .await
         .map_err(|e| format!("Cannot create download directory: {}", e))?;
 
-    // Fetch game info for folder naming
-    let mut folder_name = config.app_id.clone();
-    let mut game_name = config.game_name.clone();
-    let mut header_image: Option<String> = None;
+    let download_dir = resolved.base_dir.join(&resolved.folder_name);
+    let mut response = serde_json::json!({
+        "downloadDir": download_dir.to_string_lossy(),
+        "folderName": resolved.folder_name,
+        "plan": build_download_plan(&config),
+    });
 
-    if game_name.is_none() {
-        match steam_store_api::get_game_info(
-            &state.http_client,
-            &state.steam_cache,
-            &config.app_id,
-        ).await {
-            Ok(Some(info)) => {
-                game_name = info.name.clone();
-                header_image = info.header_image.clone();
-                if let Some(ref name) = info.name {
-                    let sanitized = steam_store_api::sanitize_game_name(name);
-                    if !sanitized.is_empty() {
-                        folder_name = format!("{} - {}", config.app_id, sanitized);
-                    }
-                }
-            }
-            _ => {}
-        }
-    } else if let Some(ref name) = game_name {
-        let sanitized = steam_store_api::sanitize_game_name(name);
-        if !sanitized.is_empty() {
-            folder_name = format!("{} - {}", config.app_id, sanitized);
-        }
-    }
+    let mut job = JobInfo::new(Some(download_dir.to_string_lossy().to_string()));
+    job.app_id = Some(config.app_id.clone());
+    response["jobId"] = serde_json::json!(spawn_download_job(&app, &state, config, settings, resolved, job).await);
 
-    let download_dir = base_dir.join(&folder_name);
+    Ok(response)
+}
 
-    // Register job
-    {
-        let mut jobs = state.active_jobs.lock().await;
-        jobs.insert(
-            job_id.clone(),
-            JobInfo {
-                status: "running".to_string(),
-                child_pid: None,
-                download_dir: Some(download_dir.to_string_lossy().to_string()),
-                #[cfg(target_os = "windows")]
-                job_object: None,
-            },
-        );
-    }
+/// Register `job` and run the download pipeline for `config` into
+/// `{base_dir}/{folder_name}` in the background. Returns the job id.
+async fn spawn_download_job(
+    app: &AppHandle,
+    state: &AppState,
+    config: DownloadConfig,
+    settings: Settings,
+    resolved: ResolvedDownloadPath,
+    job: JobInfo,
+) -> String {
+    let job_id = Uuid::new_v4().to_string();
+    let ResolvedDownloadPath {
+        base_dir,
+        folder_name,
+        game_name,
+        header_image,
+    } = resolved;
 
-    let response = serde_json::json!({
-        "jobId": job_id,
-        "downloadDir": download_dir.to_string_lossy(),
-        "folderName": folder_name,
-    });
+    // Register job
+    state.active_jobs.lock().await.insert(job_id.clone(), job);
+    let running = state.running_jobs.start();
 
     // Clone what we need for the async task
     let job_id_clone = job_id.clone();
@@ -135,7 +171,10 @@ pub async fn start_download(
     let http_client = state.http_client.clone();
     let active_jobs = state.active_jobs.clone();
     let steam_cache = state.steam_cache.clone();
-    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let progress_logs = state.progress_logs.clone();
+    let close_confirmed = state.close_confirmed.clone();
+    let job_progress = state.job_progress.clone();
+    let running_jobs = state.running_jobs.clone();
 
     // Spawn the download pipeline
     tokio::spawn(async move {
@@ -144,6 +183,10 @@ pub async fn start_download(
             active_jobs: active_jobs.clone(),
             http_client: http_client.clone(),
             steam_cache: steam_cache.clone(),
+            progress_logs: progress_logs.clone(),
+            close_confirmed: close_confirmed.clone(),
+            job_progress: job_progress.clone(),
+            running_jobs,
         };
 
         let result = run_download_pipeline(
@@ -155,19 +198,24 @@ pub async fn start_download(
             &folder_name,
             game_name.as_deref(),
             header_image.as_deref(),
-            &app_data_dir,
+            &settings,
         )
         .await;
 
         match result {
             Ok(_) => {}
             Err(e) => {
-                // Check if cancelled
+                // Check if cancelled, otherwise record the failure for `list_jobs`
                 let is_cancelled = {
-                    let jobs = active_jobs.lock().await;
-                    jobs.get(&job_id_clone)
-                        .map(|j| j.status == "cancelled")
-                        .unwrap_or(false)
+                    let mut jobs = active_jobs.lock().await;
+                    match jobs.get_mut(&job_id_clone) {
+                        Some(job) if job.status == "cancelled" => true,
+                        Some(job) => {
+                            job.status = "failed".to_string();
+                            false
+                        }
+                        None => false,
+                    }
                 };
 
                 if !is_cancelled {
@@ -178,19 +226,500 @@ pub async fn start_download(
             }
         }
 
-        // Schedule cleanup after 30 min
-        let active_jobs_cleanup = active_jobs.clone();
-        let job_id_cleanup = job_id_clone.clone();
-        tokio::spawn(async move {
-            tokio::time::sleep(tokio::time::Duration::from_secs(30 * 60)).await;
-            let mut jobs = active_jobs_cleanup.lock().await;
-            jobs.remove(&job_id_cleanup);
-        });
+        depot_runner::clear_progress_log(&state_ref, &job_id_clone);
+        drop(running);
+
+        // Drop the finished job after the retention period (0 = keep until exit)
+        if settings.job_retention_minutes > 0 {
+            let retention = tokio::time::Duration::from_secs(settings.job_retention_minutes.saturating_mul(60));
+            let active_jobs_cleanup = active_jobs.clone();
+            let job_id_cleanup = job_id_clone.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(retention).await;
+                active_jobs_cleanup.lock().await.remove(&job_id_cleanup);
+                depot_runner::forget_job_progress(&state_ref.job_progress, &job_id_cleanup);
+            });
+        }
+    });
+
+    job_id
+}
+
+/// Resume a download into an existing folder the user picked, e.g. one they moved or renamed,
+/// instead of the folder name the app would compute. The folder must be writable and hold
+/// DDM state or manifests for at least one of the selected depots; keys and manifests are
+/// fetched again by the pipeline as usual, and DDM resumes from what is on disk.
+/// The folder is never deleted on cancel, and staging is skipped so DDM works in it directly.
+/// Returns { jobId, downloadDir, folderName, plan, resumableDepots }.
+#[command]
+pub async fn resume_into_folder(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    mut config: DownloadConfig,
+    folder_path: String,
+) -> Result<serde_json::Value, String> {
+    for depot in &mut config.depots {
+        normalize_depot(depot)?;
+    }
+
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let mut settings = settings_service::load_settings(&app_data_dir).await;
+    if let Some(ref overrides) = config.job_overrides() {
+        settings = settings_service::apply_overrides(&settings, overrides)?;
+    }
+    settings.use_staging_dir = false;
+
+    let folder = PathBuf::from(&folder_path);
+    if !folder.is_dir() {
+        return Err(format!("Folder does not exist: {}", folder_path));
+    }
+    let (Some(base_dir), Some(folder_name)) = (folder.parent(), folder.file_name()) else {
+        return Err(format!("Can't resume into {}", folder_path));
+    };
+
+    // Writable: DDM has to update its state and files in place
+    let probe = folder.join(format!(".write_test_{}", Uuid::new_v4()));
+    tokio::fs::write(&probe, b"")
+        .await
+        .map_err(|e| format!("Folder is not writable: {}", e))?;
+    let _ = tokio::fs::remove_file(&probe).await;
+
+    // Compatible: something on disk belongs to the selected depots
+    let scan_dir = folder.clone();
+    let pattern = settings.manifest_filename_pattern.clone();
+    let on_disk = tokio::task::spawn_blocking(move || depot_state::list_downloaded_depots(&scan_dir, &pattern))
+        .await
+        .map_err(|e| format!("Failed to scan folder: {}", e))?;
+    let resumable: Vec<&str> = config
+        .depots
+        .iter()
+        .map(|d| d.depot_id.as_str())
+        .filter(|id| on_disk.iter().any(|d| d.depot_id == *id))
+        .collect();
+    if resumable.is_empty() {
+        return Err(format!(
+            "{} has no downloaded manifests for the selected depots; pick the folder of an earlier download of this app",
+            folder_path
+        ));
+    }
+    let resumable_depots = serde_json::json!(resumable);
+
+    let mut resolved = resolve_download_path(&state, &config).await;
+    resolved.base_dir = base_dir.to_path_buf();
+    resolved.folder_name = folder_name.to_string_lossy().to_string();
+
+    let mut response = serde_json::json!({
+        "downloadDir": folder.to_string_lossy(),
+        "folderName": resolved.folder_name,
+        "plan": build_download_plan(&config),
+        "resumableDepots": resumable_depots,
     });
 
+    let mut job = JobInfo::new(Some(folder.to_string_lossy().to_string()));
+    job.app_id = Some(config.app_id.clone());
+    job.keep_files_on_cancel = true;
+    response["jobId"] = serde_json::json!(spawn_download_job(&app, &state, config, settings, resolved, job).await);
+
     Ok(response)
 }
 
+/// Run every pre-flight check for a download config without starting a job: ids, keys,
+/// settings overrides, download folder, disk space, repo branch and the DDM binary.
+/// Returns { ok, errors: [...], warnings: [...] }; `ok` is false when anything in `errors`.
+#[command]
+pub async fn validate_download_config(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    mut config: DownloadConfig,
+) -> Result<serde_json::Value, String> {
+    let mut errors: Vec<String> = Vec::new();
+    let mut warnings: Vec<String> = Vec::new();
+
+    let app_id_valid = steam_store_api::is_valid_app_id(&config.app_id);
+    if !app_id_valid {
+        errors.push(format!("Invalid App ID: {}", config.app_id));
+    }
+    if config.depots.is_empty() {
+        errors.push("No depots selected".to_string());
+    }
+
+    for depot in &mut config.depots {
+        if let Err(e) = normalize_depot(depot) {
+            errors.push(e);
+            continue;
+        }
+
+        let has_key = depot.depot_key.as_deref().is_some_and(|k| !k.trim().is_empty())
+            || config
+                .key_vdf_keys
+                .as_ref()
+                .is_some_and(|keys| keys.contains_key(&depot.depot_id));
+        if depot.uses_latest_manifest() && !has_key {
+            errors.push(format!("Depot {} has neither a manifest ID nor a depot key", depot.depot_id));
+        } else if !has_key {
+            warnings.push(format!("Depot {} has no depot key yet; it must come from the repo's Key.vdf", depot.depot_id));
+        }
+
+        if let Some(ref path) = depot.uploaded_manifest_path {
+            if !Path::new(path).is_file() {
+                errors.push(format!("Uploaded manifest for depot {} not found: {}", depot.depot_id, path));
+            }
+        } else if depot.custom_manifest_id.is_some()
+            && config.manifest_hub_api_key.as_deref().unwrap_or_default().trim().is_empty()
+        {
+            errors.push(format!("Depot {} uses a custom manifest ID, which needs a ManifestHub API key", depot.depot_id));
+        }
+    }
+
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let mut settings = settings_service::load_settings(&app_data_dir).await;
+    if let Some(ref overrides) = config.job_overrides() {
+        match settings_service::apply_overrides(&settings, overrides) {
+            Ok(merged) => settings = merged,
+            Err(e) => errors.push(e),
+        }
+    }
+
+    // Download folder: where it will be created must be writable
+    let base_dir = match config.download_location.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        Some(dir) => resolve_download_dir(Some(dir)).unwrap_or_else(|| {
+            warnings.push(format!("Download location '{}' is not a full path; the default folder will be used", dir));
+            default_download_base()
+        }),
+        None => default_download_base(),
+    };
+    match base_dir.ancestors().find(|p| p.is_dir()) {
+        Some(existing) => {
+            let probe = existing.join(format!(".write-test-{}", Uuid::new_v4()));
+            match tokio::fs::write(&probe, b"").await {
+                Ok(()) => {
+                    let _ = tokio::fs::remove_file(&probe).await;
+                }
+                Err(e) => errors.push(format!("Download folder is not writable ({}): {}", existing.display(), e)),
+            }
+        }
+        None => errors.push(format!("Download folder cannot be created: {}", base_dir.display())),
+    }
+
+    match get_disk_space_info(&base_dir) {
+        Some((free_gb, drive)) if settings.low_disk_threshold_gb > 0.0 && free_gb < settings.low_disk_threshold_gb => {
+            warnings.push(format!("Only {:.2} GB free on {}", free_gb, drive));
+        }
+        Some(_) => {}
+        None => warnings.push("Could not determine free disk space".to_string()),
+    }
+
+    // Repo branch, when manifests will be fetched from GitHub
+    let needs_repo = config
+        .depots
+        .iter()
+        .any(|d| d.uploaded_manifest_path.is_none() && d.custom_manifest_id.is_none() && !d.uses_latest_manifest());
+    if needs_repo && app_id_valid {
+        let repo = config.repo.as_deref().unwrap_or(github_api::DEFAULT_REPO);
+        match github_api::get_branch_info(&state.http_client, repo, &config.app_id, config.github_token.as_deref()).await {
+            Ok(branch) if branch.rate_limited => {
+                warnings.push("GitHub rate limit reached; the repo branch could not be checked".to_string());
+            }
+            Ok(branch) if !branch.exists => {
+                errors.push(format!("{} has no branch for App ID {}", repo, config.app_id));
+            }
+            Ok(_) => {}
+            Err(e) => warnings.push(format!("Could not reach {}: {}", repo, e)),
+        }
+    }
+
+    match depot_runner::get_exe_path_async().await {
+        Ok(exe) if exe.exists() => {}
+        Ok(exe) => errors.push(format!("DepotDownloaderMod not found at {}", exe.display())),
+        Err(e) => errors.push(e),
+    }
+    if let Some(dotnet) = settings.resolve_dotnet_path() {
+        if !dotnet.is_file() {
+            errors.push(format!("Configured dotnet not found: {}", dotnet.display()));
+        }
+    }
+
+    Ok(serde_json::json!({
+        "ok": errors.is_empty(),
+        "errors": errors,
+        "warnings": warnings,
+    }))
+}
+
+/// Project the free space left on the download volume once the selected depots are
+/// downloaded, so the UI can show "12 GB left after this". Sizes come from each manifest's
+/// metadata (repo branch or uploaded file); custom and latest-manifest depots can't be sized
+/// up front and are listed in `unsizedDepots`. Depots with a file list are counted in full.
+/// Returns { freeBefore, estimatedSize, freeAfter, sufficient, unsizedDepots }, in bytes;
+/// `freeAfter` is negative when the download doesn't fit, and `sufficient` means at least
+/// `low_disk_threshold_gb` would be left.
+#[command]
+pub async fn project_disk_space(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    mut config: DownloadConfig,
+) -> Result<serde_json::Value, String> {
+    for depot in &mut config.depots {
+        normalize_depot(depot)?;
+    }
+
+    let base_dir = resolve_download_dir(config.download_location.as_deref())
+        .unwrap_or_else(default_download_base);
+    let (space, _) = tokio::task::spawn_blocking(move || disk_space::query(&base_dir))
+        .await
+        .map_err(|e| format!("Disk space task failed: {}", e))?
+        .ok_or_else(|| "Failed to check disk space".to_string())?;
+
+    let repo = config.repo.clone().unwrap_or_else(|| github_api::DEFAULT_REPO.to_string());
+    let mut handles = Vec::new();
+    let mut unsized_depots: Vec<String> = Vec::new();
+
+    for depot in &config.depots {
+        if let Some(ref path) = depot.uploaded_manifest_path {
+            let path = PathBuf::from(path);
+            let depot_id = depot.depot_id.clone();
+            handles.push(tokio::spawn(async move {
+                let size = tokio::task::spawn_blocking(move || {
+                    let data = std::fs::read(&path).map_err(|e| format!("Failed to read manifest: {}", e))?;
+                    manifest_parser::parse_manifest(&data).map(|m| m.cb_disk_original)
+                })
+                .await
+                .map_err(|e| format!("Manifest task failed: {}", e))
+                .and_then(|size| size);
+                (depot_id, size)
+            }));
+        } else if depot.custom_manifest_id.is_some() || depot.uses_latest_manifest() {
+            unsized_depots.push(depot.depot_id.clone());
+        } else {
+            let client = state.http_client.clone();
+            let app_id = config.app_id.clone();
+            let depot_id = depot.depot_id.clone();
+            let manifest_id = depot.manifest_id.clone();
+            let repo = repo.clone();
+            let token = config.github_token.clone();
+            handles.push(tokio::spawn(async move {
+                let size = manifest_downloader::fetch_manifest_size_cached(
+                    &client,
+                    &app_id,
+                    &depot_id,
+                    &manifest_id,
+                    &repo,
+                    token.as_deref(),
+                )
+                .await;
+                (depot_id, size)
+            }));
+        }
+    }
+
+    let mut estimated_size: u64 = 0;
+    for handle in handles {
+        let (depot_id, size) = handle
+            .await
+            .map_err(|e| format!("Size lookup task failed: {}", e))?;
+        match size {
+            Ok(size) => estimated_size += size,
+            Err(e) => {
+                eprintln!("[Download] Could not size depot {}: {}", depot_id, e);
+                unsized_depots.push(depot_id);
+            }
+        }
+    }
+
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let settings = settings_service::load_settings(&app_data_dir).await;
+    let threshold = (settings.low_disk_threshold_gb * 1024.0 * 1024.0 * 1024.0) as i64;
+    let free_after = space.free as i64 - estimated_size as i64;
+
+    Ok(serde_json::json!({
+        "freeBefore": space.free,
+        "estimatedSize": estimated_size,
+        "freeAfter": free_after,
+        "sufficient": free_after >= threshold,
+        "unsizedDepots": unsized_depots,
+    }))
+}
+
+/// Normalize one depot's pasted ids and file list, as `start_download` expects them.
+/// An empty, "N/A" or "latest" manifest id becomes empty (see `uses_latest_manifest`).
+fn normalize_depot(depot: &mut DepotConfig) -> Result<(), String> {
+    let manifest_id = depot.manifest_id.trim();
+    depot.manifest_id = if manifest_id.is_empty()
+        || manifest_id.eq_ignore_ascii_case("n/a")
+        || manifest_id.eq_ignore_ascii_case("latest")
+    {
+        String::new()
+    } else {
+        manifest_downloader::normalize_manifest_id(manifest_id)
+            .map_err(|e| format!("Depot {}: {}", depot.depot_id, e))?
+    };
+    depot.custom_manifest_id = match depot.custom_manifest_id.take() {
+        Some(id) if !id.trim().is_empty() => Some(
+            manifest_downloader::normalize_manifest_id(&id)
+                .map_err(|e| format!("Depot {}: {}", depot.depot_id, e))?,
+        ),
+        _ => None,
+    };
+    if let Some(ref mut files) = depot.file_list {
+        for file in files.iter_mut() {
+            *file = file.trim().to_string();
+        }
+        if files.is_empty() || files.iter().any(|f| f.is_empty()) {
+            return Err(format!("Depot {}: file list entries cannot be empty", depot.depot_id));
+        }
+    }
+    Ok(())
+}
+
+/// `~/Documents/SteamDownloads`, used when no usable download location is configured.
+fn default_download_base() -> PathBuf {
+    let home = std::env::var("USERPROFILE")
+        .or_else(|_| std::env::var("HOME"))
+        .unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(home).join("Documents").join("SteamDownloads")
+}
+
+/// Where a download will land, plus the game info looked up to name the folder.
+struct ResolvedDownloadPath {
+    base_dir: PathBuf,
+    folder_name: String,
+    game_name: Option<String>,
+    header_image: Option<String>,
+}
+
+/// Work out the base directory and the `{app_id} - {name}` folder for a download.
+/// Falls back to the bare app id when no game name is known.
+async fn resolve_download_path(state: &AppState, config: &DownloadConfig) -> ResolvedDownloadPath {
+    // Determine base download directory
+    let base_dir = resolve_download_dir(config.download_location.as_deref())
+        .unwrap_or_else(default_download_base);
+
+    // Fetch game info for folder naming
+    let mut game_name = config.game_name.clone();
+    let mut header_image: Option<String> = None;
+
+    if game_name.is_none() {
+        if let Ok(Some(info)) = steam_store_api::get_game_info(
+            &state.http_client,
+            &state.steam_cache,
+            &config.app_id,
+        ).await {
+            game_name = info.name.clone();
+            header_image = info.header_image.clone();
+        }
+    }
+
+    let folder_name = game_name
+        .as_deref()
+        .map(steam_store_api::sanitize_game_name)
+        .filter(|sanitized| !sanitized.is_empty())
+        .map(|sanitized| format!("{} - {}", config.app_id, sanitized))
+        .unwrap_or_else(|| config.app_id.clone());
+
+    ResolvedDownloadPath {
+        base_dir,
+        folder_name,
+        game_name,
+        header_image,
+    }
+}
+
+/// Preview where a download would land without starting it.
+/// Returns { path, folderName, exists, fileCount, totalBytes, collision } for the final folder,
+/// `collision` being what `check_folder_collision` reports for the folder name.
+#[command]
+pub async fn preview_download_path(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    config: DownloadConfig,
+) -> Result<serde_json::Value, String> {
+    let resolved = resolve_download_path(&state, &config).await;
+    let path = resolved.base_dir.join(&resolved.folder_name);
+
+    let exists = path.exists();
+    let (file_count, total_bytes) = if exists {
+        let dir = path.clone();
+        tokio::task::spawn_blocking(move || dir_usage(&dir))
+            .await
+            .map_err(|e| format!("Failed to scan download folder: {}", e))?
+    } else {
+        (0, 0)
+    };
+
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let pattern = settings_service::load_settings(&app_data_dir).await.manifest_filename_pattern;
+    let base_dir = resolved.base_dir.clone();
+    let folder_name = resolved.folder_name.clone();
+    let collision = tokio::task::spawn_blocking(move || {
+        let entries = folder_collision::scan_entries(&base_dir, &folder_name, &pattern);
+        folder_collision::check_collision(&entries, &folder_name)
+    })
+    .await
+    .map_err(|e| format!("Failed to check download folder: {}", e))?;
+
+    Ok(serde_json::json!({
+        "path": path.to_string_lossy(),
+        "folderName": resolved.folder_name,
+        "exists": exists,
+        "fileCount": file_count,
+        "totalBytes": total_bytes,
+        "collision": collision,
+    }))
+}
+
+/// Summarize what a job will download so the UI can show it before any events arrive.
+/// Uses the same depot categories as the pipeline. Sizes aren't known until manifests
+/// are fetched, so `estimatedBytes` is null for now.
+fn build_download_plan(config: &DownloadConfig) -> serde_json::Value {
+    let uploaded = config.depots.iter().filter(|d| d.uploaded_manifest_path.is_some()).count();
+    let custom = config.depots.iter().filter(|d| d.uploaded_manifest_path.is_none() && d.custom_manifest_id.is_some()).count();
+    let latest = config.depots.iter().filter(|d| d.uses_latest_manifest()).count();
+    let standard = config.depots.len() - uploaded - custom - latest;
+
+    let depots: Vec<serde_json::Value> = config
+        .depots
+        .iter()
+        .map(|d| {
+            let has_key = d.depot_key.as_deref().is_some_and(|k| !k.trim().is_empty())
+                || config
+                    .key_vdf_keys
+                    .as_ref()
+                    .is_some_and(|keys| keys.contains_key(&d.depot_id));
+            let category = if d.uploaded_manifest_path.is_some() {
+                "uploaded"
+            } else if d.custom_manifest_id.is_some() {
+                "custom"
+            } else if d.uses_latest_manifest() {
+                "latest"
+            } else {
+                "standard"
+            };
+            serde_json::json!({
+                "depotId": d.depot_id,
+                "manifestId": d.custom_manifest_id.as_deref().unwrap_or(&d.manifest_id),
+                "category": category,
+                "hasKey": has_key,
+            })
+        })
+        .collect();
+    let with_keys = depots.iter().filter(|d| d["hasKey"].as_bool().unwrap_or(false)).count();
+
+    serde_json::json!({
+        "appId": config.app_id,
+        "repo": config.repo,
+        "sha": config.sha,
+        "depotCounts": {
+            "total": config.depots.len(),
+            "standard": standard,
+            "custom": custom,
+            "uploaded": uploaded,
+            "withKeys": with_keys,
+        },
+        "depots": depots,
+        "estimatedBytes": serde_json::Value::Null,
+    })
+}
+
 /// The main download pipeline logic.
 async fn run_download_pipeline(
     app: &AppHandle,
@@ -201,16 +730,32 @@ async fn run_download_pipeline(
     folder_name: &str,
     _game_name: Option<&str>,
     _header_image: Option<&str>,
-    app_data_dir: &Path,
+    settings: &Settings,
 ) -> Result<(), String> {
     let _started_at = chrono::Utc::now();
-    let work_dir = base_dir.join(folder_name);
+    let final_dir = base_dir.join(folder_name);
+
+    // With staging enabled, everything lands in `.staging/{job_id}` until the job succeeds
+    let work_dir = if settings.use_staging_dir {
+        let staging_dir = staging::staging_dir(base_dir, job_id);
+        let mut jobs = state.active_jobs.lock().await;
+        if let Some(job) = jobs.get_mut(job_id) {
+            job.download_dir = Some(staging_dir.to_string_lossy().to_string());
+        }
+        staging_dir
+    } else {
+        final_dir.clone()
+    };
 
     // Create work directory
     tokio::fs::create_dir_all(&work_dir)
         .await
         .map_err(|e| format!("Failed to create download directory: {}", e))?;
 
+    if settings.write_progress_log {
+        depot_runner::set_progress_log(state, job_id, work_dir.join("progress.ndjson"));
+    }
+
     // Check for disk space
     if let Some(disk_info) = get_disk_space_info(base_dir) {
         let mut event = ProgressEvent::new("status", job_id);
@@ -227,20 +772,22 @@ async fn run_download_pipeline(
     // Categorize depots
     let uploaded_depots: Vec<&DepotConfig> = config.depots.iter().filter(|d| d.uploaded_manifest_path.is_some()).collect();
     let custom_depots: Vec<&DepotConfig> = config.depots.iter().filter(|d| d.uploaded_manifest_path.is_none() && d.custom_manifest_id.is_some()).collect();
-    let standard_depots: Vec<&DepotConfig> = config.depots.iter().filter(|d| d.uploaded_manifest_path.is_none() && d.custom_manifest_id.is_none()).collect();
+    let standard_depots: Vec<&DepotConfig> = config.depots.iter().filter(|d| d.uploaded_manifest_path.is_none() && d.custom_manifest_id.is_none() && !d.uses_latest_manifest()).collect();
+    let latest_depots: Vec<&DepotConfig> = config.depots.iter().filter(|d| d.uses_latest_manifest()).collect();
 
     // Step 1: Branch check (only for standard depots when no repo provided)
     if !standard_depots.is_empty() && config.repo.is_none() {
         let mut event = ProgressEvent::new("status", job_id);
         event.step = Some("checking_branch".to_string());
         event.app_id = Some(config.app_id.clone());
+        event.repo = Some(github_api::DEFAULT_REPO.to_string());
         emit_progress(app, &event);
 
         if check_cancelled(state, job_id).await {
             return Ok(());
         }
 
-        let branch_result = crate::services::github_api::check_branch(
+        let branch_result = github_api::check_branch(
             &state.http_client,
             &config.app_id,
             config.github_token.as_deref(),
@@ -258,6 +805,7 @@ async fn run_download_pipeline(
         let mut event = ProgressEvent::new("status", job_id);
         event.step = Some("branch_found".to_string());
         event.app_id = Some(config.app_id.clone());
+        event.repo = Some(github_api::DEFAULT_REPO.to_string());
         event.last_updated = branch_result.last_updated;
         emit_progress(app, &event);
     } else if config.repo.is_some() {
@@ -273,19 +821,24 @@ async fn run_download_pipeline(
     }
 
     // Step 2: Download manifest files
-    let total_manifests = config.depots.len();
+    let total_manifests = config.depots.len() - latest_depots.len();
     let mut event = ProgressEvent::new("status", job_id);
     event.step = Some("downloading_manifests".to_string());
     event.total = Some(total_manifests);
     emit_progress(app, &event);
 
-    let mut manifest_results: Vec<(String, bool)> = Vec::new(); // (depot_id, success)
+    // (depot_id, path of the manifest file on success)
+    let mut manifest_results: Vec<(String, Option<PathBuf>)> = Vec::new();
 
     // Handle uploaded manifests - copy to work dir
     for depot in &uploaded_depots {
         if let Some(ref uploaded_path) = depot.uploaded_manifest_path {
             let manifest_id = depot.custom_manifest_id.as_deref().unwrap_or(&depot.manifest_id);
-            let filename = format!("{}_{}.manifest", depot.depot_id, manifest_id);
+            let filename = manifest_downloader::manifest_filename(
+                &settings.manifest_filename_pattern,
+                &depot.depot_id,
+                manifest_id,
+            );
             let dest_path = work_dir.join(&filename);
 
             match tokio::fs::copy(uploaded_path, &dest_path).await {
@@ -295,26 +848,41 @@ async fn run_download_pipeline(
                     let mut event = ProgressEvent::new("status", job_id);
                     event.step = Some("downloading_manifest".to_string());
                     event.depot_id = Some(depot.depot_id.clone());
+                    event.current = Some(manifest_results.len() + 1);
+                    event.total = Some(total_manifests);
                     event.manifest_id = Some(manifest_id.to_string());
                     event.filename = Some(filename);
                     event.message = Some("Using uploaded manifest file".to_string());
                     emit_progress(app, &event);
-                    manifest_results.push((depot.depot_id.clone(), true));
+                    manifest_results.push((depot.depot_id.clone(), Some(dest_path.clone())));
                 }
                 Err(e) => {
                     let mut event = ProgressEvent::new("error", job_id);
                     event.message = Some(format!("Failed to use uploaded manifest for depot {}: {}", depot.depot_id, e));
                     emit_progress(app, &event);
-                    manifest_results.push((depot.depot_id.clone(), false));
+                    manifest_results.push((depot.depot_id.clone(), None));
                 }
             }
         }
     }
 
     // Download standard manifests from GitHub
-    let repo = config.repo.as_deref().unwrap_or("SteamAutoCracks/ManifestHub");
+    let repo = config.repo.as_deref().unwrap_or(github_api::DEFAULT_REPO);
     let sha = config.sha.as_deref().unwrap_or(&config.app_id);
 
+    // Path -> git blob SHA, to catch manifests the raw CDN serves stale
+    let blob_shas = if settings.verify_manifest_sha && !standard_depots.is_empty() {
+        match github_api::get_blob_shas(&state.http_client, repo, sha, config.github_token.as_deref()).await {
+            Ok(shas) => Some(shas),
+            Err(e) => {
+                eprintln!("[Download] Skipping manifest SHA verification, tree lookup failed: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
     for depot in &standard_depots {
         if check_cancelled(state, job_id).await {
             return Ok(());
@@ -323,29 +891,42 @@ async fn run_download_pipeline(
         let mut event = ProgressEvent::new("status", job_id);
         event.step = Some("downloading_manifest".to_string());
         event.depot_id = Some(depot.depot_id.clone());
+        event.current = Some(manifest_results.len() + 1);
+        event.total = Some(total_manifests);
         event.manifest_id = Some(depot.manifest_id.clone());
         emit_progress(app, &event);
 
+        let options = ManifestFetchOptions {
+            filename_pattern: &settings.manifest_filename_pattern,
+            repo_path: depot.repo_path.as_deref(),
+            expected_blob_sha: blob_shas.as_ref().and_then(|shas| {
+                let path = depot.repo_path.clone()
+                    .unwrap_or_else(|| format!("{}_{}.manifest", depot.depot_id, depot.manifest_id));
+                shas.get(&path).map(String::as_str)
+            }),
+            github_token: config.github_token.as_deref(),
+            ..Default::default()
+        };
+
         match manifest_downloader::download_manifest(
             &state.http_client,
             &config.app_id,
             &depot.depot_id,
             &depot.manifest_id,
             repo,
-            sha,
             &work_dir,
-            config.github_token.as_deref(),
+            &options,
         )
         .await
         {
-            Ok(_) => {
-                manifest_results.push((depot.depot_id.clone(), true));
+            Ok(manifest_path) => {
+                manifest_results.push((depot.depot_id.clone(), Some(manifest_path)));
             }
             Err(e) => {
                 let mut event = ProgressEvent::new("error", job_id);
                 event.message = Some(format!("Failed to download manifest for depot {}: {}", depot.depot_id, e));
                 emit_progress(app, &event);
-                manifest_results.push((depot.depot_id.clone(), false));
+                manifest_results.push((depot.depot_id.clone(), None));
             }
         }
     }
@@ -361,6 +942,8 @@ async fn run_download_pipeline(
         let mut event = ProgressEvent::new("status", job_id);
         event.step = Some("downloading_manifest_hub".to_string());
         event.depot_id = Some(depot.depot_id.clone());
+        event.current = Some(manifest_results.len() + 1);
+        event.total = Some(total_manifests);
         event.manifest_id = Some(manifest_id.to_string());
         emit_progress(app, &event);
 
@@ -373,17 +956,28 @@ async fn run_download_pipeline(
             manifest_id,
             &work_dir,
             api_key,
+            &ManifestFetchOptions {
+                filename_pattern: &settings.manifest_filename_pattern,
+                hosts: &settings.manifest_hub_hosts,
+                ..Default::default()
+            },
         )
         .await
         {
-            Ok(_) => {
-                manifest_results.push((depot.depot_id.clone(), true));
+            Ok((manifest_path, host)) => {
+                let mut event = ProgressEvent::new("status", job_id);
+                event.step = Some("manifest_hub_served".to_string());
+                event.depot_id = Some(depot.depot_id.clone());
+                event.source = Some(host.clone());
+                event.message = Some(format!("Custom manifest for depot {} served by {}", depot.depot_id, host));
+                emit_progress(app, &event);
+                manifest_results.push((depot.depot_id.clone(), Some(manifest_path)));
             }
             Err(e) => {
                 let mut event = ProgressEvent::new("error", job_id);
                 event.message = Some(format!("Failed to download custom manifest for depot {}: {}", depot.depot_id, e));
                 emit_progress(app, &event);
-                manifest_results.push((depot.depot_id.clone(), false));
+                manifest_results.push((depot.depot_id.clone(), None));
             }
         }
     }
@@ -392,13 +986,44 @@ async fn run_download_pipeline(
         return Ok(());
     }
 
-    // Check if all manifests failed
-    let success_count = manifest_results.iter().filter(|(_, s)| *s).count();
-    if success_count == 0 && !manifest_results.is_empty() {
+    let success_count = manifest_results.iter().filter(|(_, p)| p.is_some()).count();
+    let mut event = ProgressEvent::new("status", job_id);
+    event.step = Some("manifests_complete".to_string());
+    event.current = Some(manifest_results.len());
+    event.total = Some(total_manifests);
+    event.succeeded = Some(success_count);
+    event.failed = Some(manifest_results.len() - success_count);
+    event.message = Some(format!(
+        "{}/{} manifests ready",
+        success_count,
+        manifest_results.len()
+    ));
+    emit_progress(app, &event);
+
+    // All or nothing: don't download some depots when others have no manifest
+    let failed_depots: Vec<&str> = manifest_results
+        .iter()
+        .filter(|(_, path)| path.is_none())
+        .map(|(depot_id, _)| depot_id.as_str())
+        .collect();
+    if settings.manifest_failure_policy == "abort" && !failed_depots.is_empty() {
+        let mut event = ProgressEvent::new("error", job_id);
+        event.message = Some(format!(
+            "Stopped before downloading: no manifest for depot(s) {} and the manifest failure policy is 'abort'",
+            failed_depots.join(", ")
+        ));
+        emit_progress(app, &event);
+        mark_failed(state, job_id).await;
+        return Ok(());
+    }
+
+    // Check if all manifests failed (latest-manifest depots can still run)
+    if success_count == 0 && !manifest_results.is_empty() && latest_depots.is_empty() {
         let error_msg = "All manifest downloads failed".to_string();
         let mut event = ProgressEvent::new("error", job_id);
         event.message = Some(error_msg.clone());
         emit_progress(app, &event);
+        mark_failed(state, job_id).await;
         return Ok(());
     }
 
@@ -409,29 +1034,34 @@ async fn run_download_pipeline(
 
     let mut event = ProgressEvent::new("status", job_id);
     event.step = Some("generating_keys".to_string());
+    event.total = Some(config.depots.len());
     emit_progress(app, &event);
 
     // Collect depot keys from config
     let mut depot_infos: Vec<DepotInfo> = config
         .depots
         .iter()
-        .map(|d| {
-            let mut key = d.depot_key.clone();
-
-            // Merge keyVdfKeys if available
-            if key.is_none() {
-                if let Some(ref kvk) = config.key_vdf_keys {
-                    key = kvk.get(&d.depot_id).cloned();
-                }
-            }
-
-            DepotInfo {
-                depot_id: d.depot_id.parse().unwrap_or(0),
-                depot_key: key,
-                manifest_id: Some(d.custom_manifest_id.as_deref().unwrap_or(&d.manifest_id).to_string()),
-            }
+        .map(|d| DepotInfo {
+            depot_id: d.depot_id.parse().unwrap_or(0),
+            depot_key: d.depot_key.clone(),
+            manifest_id: (!d.uses_latest_manifest())
+                .then(|| d.custom_manifest_id.as_deref().unwrap_or(&d.manifest_id).to_string()),
         })
         .collect();
+    let from_depots = depot_infos.iter().filter(|d| d.depot_key.is_some()).count();
+    emit_key_source(app, job_id, "config", from_depots, depot_infos.len());
+
+    // Merge keyVdfKeys if available
+    if let Some(ref kvk) = config.key_vdf_keys {
+        let mut merged = 0;
+        for depot in depot_infos.iter_mut().filter(|d| d.depot_key.is_none()) {
+            if let Some(key) = kvk.get(&depot.depot_id.to_string()) {
+                depot.depot_key = Some(key.clone());
+                merged += 1;
+            }
+        }
+        emit_key_source(app, job_id, "key_vdf", merged, depot_infos.len());
+    }
 
     // If we have a repo with Key.vdf and some depots lack keys, try downloading
     if let Some(ref repo_name) = config.repo {
@@ -439,6 +1069,7 @@ async fn run_download_pipeline(
             if let Some(ref sha_val) = config.sha {
                 let mut event = ProgressEvent::new("status", job_id);
                 event.step = Some("downloading_keyvdf".to_string());
+                event.repo = Some(repo_name.clone());
                 emit_progress(app, &event);
 
                 match manifest_downloader::download_key_vdf(
@@ -453,36 +1084,145 @@ async fn run_download_pipeline(
                 {
                     Ok(vdf_content) => {
                         let vdf_keys = vdf_parser::parse_key_vdf(&vdf_content, Some(repo_name));
+                        let mut merged = 0;
                         for depot in &mut depot_infos {
                             if depot.depot_key.is_none() {
                                 if let Some(key) = vdf_keys.get(&depot.depot_id.to_string()) {
                                     depot.depot_key = Some(key.clone());
+                                    merged += 1;
                                 }
                             }
                         }
+                        emit_key_source(app, job_id, "repo_key_vdf", merged, depot_infos.len());
                     }
                     Err(e) => {
                         eprintln!("[Download] Key.vdf download/parse skipped: {}", e);
+                        let mut event = ProgressEvent::new("status", job_id);
+                        event.step = Some("key_source_failed".to_string());
+                        event.source = Some("repo_key_vdf".to_string());
+                        event.repo = Some(repo_name.clone());
+                        event.message = Some(format!("Key.vdf download failed: {}", e));
+                        emit_progress(app, &event);
                     }
                 }
             }
         }
     }
 
-    // Generate steam.keys file
-    let keys_result = depot_keys_generator::generate_depot_keys(
-        config.app_id.parse().unwrap_or(0),
-        &depot_infos,
-        Some(folder_name),
-        base_dir,
-    )
-    .await?;
-
+    // Fill remaining gaps from PrintedWaste if enabled
+    if settings.auto_fetch_missing_keys
+        && settings.alternative_source_enabled("printedwaste")
+        && depot_infos.iter().any(|d| d.depot_key.is_none())
+    {
+        let mut event = ProgressEvent::new("status", job_id);
+        event.step = Some("fetching_missing_keys".to_string());
+        emit_progress(app, &event);
+
+        match alternative_sources::download_from_printed_waste(&state.http_client, &config.app_id).await {
+            Ok(pw_result) => {
+                let mut recovered = 0;
+                for depot in &mut depot_infos {
+                    if depot.depot_key.is_some() {
+                        continue;
+                    }
+                    let depot_id = depot.depot_id.to_string();
+                    if let Some(key) = pw_result
+                        .depots
+                        .iter()
+                        .find(|p| p.depot_id == depot_id)
+                        .and_then(|p| p.depot_key.clone())
+                    {
+                        depot.depot_key = Some(key);
+                        recovered += 1;
+                    }
+                }
+
+                let mut event = ProgressEvent::new("status", job_id);
+                event.step = Some("keys_recovered".to_string());
+                event.source = Some("printedwaste".to_string());
+                event.depot_count = Some(recovered);
+                event.message = Some(format!("Recovered {} missing depot keys from PrintedWaste", recovered));
+                emit_progress(app, &event);
+            }
+            Err(e) => {
+                eprintln!("[Download] PrintedWaste key lookup skipped: {}", e);
+            }
+        }
+    }
+
+    // Generate steam.keys file
+    let keys_result = depot_keys_generator::generate_depot_keys(
+        config.app_id.parse().unwrap_or(0),
+        &depot_infos,
+        work_dir.file_name().and_then(|n| n.to_str()),
+        work_dir.parent().unwrap_or(base_dir),
+    )
+    .await?;
+
     let mut event = ProgressEvent::new("status", job_id);
     event.step = Some("keys_generated".to_string());
     event.depot_count = Some(keys_result.depot_count);
     emit_progress(app, &event);
 
+    if settings.auto_backup_keys && keys_result.depot_count > 0 {
+        let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let keys_file = PathBuf::from(&keys_result.output_path);
+        match key_backup::backup_keys(&app_data_dir, &config.app_id, &keys_file).await {
+            Ok((_, added)) if added > 0 => {
+                eprintln!("[Download] Backed up {} new depot keys for App ID {}", added, config.app_id);
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("[Download] Key backup skipped: {}", e),
+        }
+    }
+
+    if !keys_result.rejected_keys.is_empty() {
+        let details: Vec<String> = keys_result
+            .rejected_keys
+            .iter()
+            .map(|(depot_id, reason)| format!("{} ({})", depot_id, reason))
+            .collect();
+        let mut event = ProgressEvent::new("status", job_id);
+        event.step = Some("keys_rejected".to_string());
+        event.depot_count = Some(keys_result.rejected_keys.len());
+        event.message = Some(format!("Skipped invalid depot keys: {}", details.join(", ")));
+        emit_progress(app, &event);
+    }
+
+    if settings.validate_keys {
+        check_keys_against_manifests(app, job_id, &depot_infos, &manifest_results).await;
+    }
+
+    // Depots without a manifest can only run if DDM can fetch the manifest with their key
+    let mut latest_runnable: Vec<&str> = Vec::new();
+    for depot in &latest_depots {
+        let has_key = depot_infos.iter().any(|d| {
+            d.depot_id.to_string() == depot.depot_id
+                && d.depot_key.is_some()
+                && !keys_result.rejected_keys.iter().any(|(id, _)| *id == d.depot_id)
+        });
+
+        if has_key {
+            let mut event = ProgressEvent::new("status", job_id);
+            event.step = Some("manifest_from_ddm".to_string());
+            event.depot_id = Some(depot.depot_id.clone());
+            event.message = Some(format!(
+                "No manifest for depot {}; DepotDownloaderMod will resolve the latest one",
+                depot.depot_id
+            ));
+            emit_progress(app, &event);
+            latest_runnable.push(&depot.depot_id);
+        } else {
+            let mut event = ProgressEvent::new("error", job_id);
+            event.depot_id = Some(depot.depot_id.clone());
+            event.message = Some(format!(
+                "Depot {} has neither a manifest nor a depot key and was skipped",
+                depot.depot_id
+            ));
+            emit_progress(app, &event);
+        }
+    }
+
     // Step 4: Run DepotDownloaderMod
     if check_cancelled(state, job_id).await {
         return Ok(());
@@ -490,20 +1230,38 @@ async fn run_download_pipeline(
 
     let exe_path = depot_runner::get_exe_path_async().await?;
 
-    // Filter to only depots with successful manifests
-    let successful_depot_ids: Vec<String> = manifest_results
-        .iter()
-        .filter(|(_, s)| *s)
-        .map(|(id, _)| id.clone())
+    // Filter to only depots with successful manifests, keeping the file each one was saved as
+    let manifest_paths: HashMap<String, PathBuf> = manifest_results
+        .into_iter()
+        .filter_map(|(id, path)| path.map(|p| (id, p)))
         .collect();
 
     let run_depots: Vec<DepotRunConfig> = config
         .depots
         .iter()
-        .filter(|d| successful_depot_ids.contains(&d.depot_id))
-        .map(|d| DepotRunConfig {
-            depot_id: d.depot_id.clone(),
-            manifest_id: d.custom_manifest_id.as_deref().unwrap_or(&d.manifest_id).to_string(),
+        .filter_map(|d| {
+            let (manifest_id, manifest_path) = if d.uses_latest_manifest() {
+                if !latest_runnable.contains(&d.depot_id.as_str()) {
+                    return None;
+                }
+                (None, None)
+            } else {
+                let manifest_path = manifest_paths.get(&d.depot_id)?;
+                (
+                    Some(d.custom_manifest_id.as_deref().unwrap_or(&d.manifest_id).to_string()),
+                    Some(manifest_path.clone()),
+                )
+            };
+            Some(DepotRunConfig {
+                depot_id: d.depot_id.clone(),
+                manifest_id,
+                manifest_path,
+                file_list: d.file_list.clone(),
+                used_key: d
+                    .depot_id
+                    .parse::<u64>()
+                    .is_ok_and(|id| keys_result.keyed_depots.contains(&id)),
+            })
         })
         .collect();
 
@@ -512,36 +1270,111 @@ async fn run_download_pipeline(
     event.total = Some(run_depots.len());
     emit_progress(app, &event);
 
-    // Load settings for extra args
-    let settings = crate::services::settings::load_settings(app_data_dir).await;
-    let extra_args = if settings.dd_extra_args.is_empty() {
-        vec![
-            "-max-downloads".to_string(),
-            "8".to_string(),
-            "-verify-all".to_string(),
-        ]
-    } else {
-        settings.dd_extra_args.clone()
+    let runner_options = depot_runner::RunnerOptions {
+        extra_args: settings.dd_args(),
+        dotnet_path: settings.resolve_dotnet_path(),
+        output_throttle: std::time::Duration::from_millis(settings.output_throttle_ms),
+        output_buffer_lines: settings.output_buffer_lines,
+        target_os: Some(settings.target_os.clone()).filter(|os| !os.is_empty()),
+        target_arch: Some(settings.target_arch.clone()).filter(|arch| !arch.is_empty()),
     };
 
-    let download_results = depot_runner::run_all_depots(
-        app,
-        &exe_path,
-        &config.app_id,
-        &run_depots,
-        &work_dir,
-        &extra_args,
-        job_id,
-        state,
-    )
-    .await?;
+    let mut download_results = tokio::select! {
+        results = depot_runner::run_all_depots(
+            app,
+            &exe_path,
+            &config.app_id,
+            &run_depots,
+            &work_dir,
+            &runner_options,
+            job_id,
+            state,
+        ) => results?,
+        _ = monitor_disk_space(app, state, job_id, &work_dir, settings) => {
+            unreachable!("disk space monitor never finishes")
+        }
+    };
+
+    if job_status(state, job_id).await.as_deref() == Some("paused") {
+        // resume_into_folder works in place, so staged files move to the final folder first
+        let resume_dir = if settings.use_staging_dir {
+            staging::promote(&work_dir, &final_dir).await?;
+            let mut jobs = state.active_jobs.lock().await;
+            if let Some(job) = jobs.get_mut(job_id) {
+                job.download_dir = Some(final_dir.to_string_lossy().to_string());
+            }
+            final_dir.clone()
+        } else {
+            work_dir.clone()
+        };
+
+        let mut event = ProgressEvent::new("paused", job_id);
+        event.message = Some(
+            "Download paused because the disk is almost full. Free up space, then resume the download.".to_string(),
+        );
+        event.download_dir = Some(resume_dir.to_string_lossy().to_string());
+        emit_progress(app, &event);
+        return Ok(());
+    }
 
     if check_cancelled(state, job_id).await {
         return Ok(());
     }
 
-    // Complete
+    if settings.verify_after_download
+        && !run_depots.is_empty()
+        && download_results.iter().all(|r| r["success"].as_bool().unwrap_or(false))
+    {
+        verify_downloaded_depots(
+            app,
+            job_id,
+            &work_dir,
+            &run_depots,
+            &settings.manifest_filename_pattern,
+            &mut download_results,
+        )
+        .await;
+    }
+
     let dl_success_count = download_results.iter().filter(|r| r["success"].as_bool().unwrap_or(false)).count();
+
+    // Rearranging a partial download would make DDM fetch the moved files again on retry
+    let layout = OutputLayout::parse(&settings.output_layout)?;
+    if layout != OutputLayout::Native && dl_success_count == run_depots.len() && !run_depots.is_empty() {
+        reorganize_output(app, job_id, &work_dir, &run_depots, layout).await?;
+    }
+
+    // Move staged files into the final folder only if every depot succeeded
+    if settings.use_staging_dir {
+        if dl_success_count == run_depots.len() && !run_depots.is_empty() {
+            let mut event = ProgressEvent::new("status", job_id);
+            event.step = Some("finalizing".to_string());
+            event.message = Some(format!("Moving files into {}", final_dir.to_string_lossy()));
+            emit_progress(app, &event);
+
+            staging::promote(&work_dir, &final_dir).await?;
+
+            {
+                let mut jobs = state.active_jobs.lock().await;
+                if let Some(job) = jobs.get_mut(job_id) {
+                    job.download_dir = Some(final_dir.to_string_lossy().to_string());
+                }
+            }
+            if settings.write_progress_log {
+                depot_runner::set_progress_log(state, job_id, final_dir.join("progress.ndjson"));
+            }
+        } else {
+            let mut event = ProgressEvent::new("status", job_id);
+            event.step = Some("staging_kept".to_string());
+            event.message = Some(format!(
+                "Not all depots succeeded; files were left in {} so the final folder stays untouched.",
+                work_dir.to_string_lossy()
+            ));
+            emit_progress(app, &event);
+        }
+    }
+
+    // Complete
     let mut event = ProgressEvent::new("complete", job_id);
     event.message = Some(format!(
         "Download complete. {}/{} depots downloaded successfully.",
@@ -552,73 +1385,717 @@ async fn run_download_pipeline(
     emit_progress(app, &event);
 
     // Mark job as complete
-    {
+    let output_dir = {
         let mut jobs = state.active_jobs.lock().await;
-        if let Some(job) = jobs.get_mut(job_id) {
+        jobs.get_mut(job_id).and_then(|job| {
             job.status = "complete".to_string();
+            job.download_dir.clone()
+        })
+    };
+
+    if settings.open_folder_on_complete && dl_success_count > 0 {
+        if let Some(dir) = output_dir {
+            if let Err(e) = file_manager::open_folder(Path::new(&dir)) {
+                eprintln!("[Download] {}", e);
+            }
         }
     }
 
     Ok(())
 }
 
+/// Move DDM's `depots/{depot_id}/{build}` tree into the configured layout.
+async fn reorganize_output(
+    app: &AppHandle,
+    job_id: &str,
+    work_dir: &Path,
+    depots: &[DepotRunConfig],
+    layout: OutputLayout,
+) -> Result<(), String> {
+    let mut event = ProgressEvent::new("status", job_id);
+    event.step = Some("reorganizing".to_string());
+    emit_progress(app, &event);
+
+    let dir = work_dir.to_path_buf();
+    let depot_order: Vec<String> = depots.iter().map(|d| d.depot_id.clone()).collect();
+    let (moved, collisions, renamed) = tokio::task::spawn_blocking(move || {
+        let files = output_layout::list_native_files(&dir);
+        let root_entries = output_layout::list_root_entries(&dir);
+        let plan = output_layout::plan_layout(&files, &depot_order, layout, &root_entries);
+        output_layout::apply_plan(&dir, &plan).map(|moved| (moved, plan.collisions, plan.renamed))
+    })
+    .await
+    .map_err(|e| format!("Reorganize task failed: {}", e))??;
+
+    eprintln!("[Download] Reorganized {} files ({} collisions)", moved, collisions.len());
+
+    let mut event = ProgressEvent::new("status", job_id);
+    event.step = Some("reorganized".to_string());
+    event.message = Some(if collisions.is_empty() {
+        format!("Moved {} files", moved)
+    } else {
+        let mut shown: Vec<&str> = collisions.iter().take(5).map(|c| c.as_str()).collect();
+        if collisions.len() > shown.len() {
+            shown.push("...");
+        }
+        format!(
+            "Moved {} files; {} paths existed in several depots, the later depot's copy was kept ({})",
+            moved,
+            collisions.len(),
+            shown.join(", ")
+        )
+    });
+    if !renamed.is_empty() {
+        let message = event.message.get_or_insert_with(String::new);
+        message.push_str(&format!(
+            "; {} paths were renamed so they don't replace this job's own files ({})",
+            renamed.len(),
+            renamed.join(", ")
+        ));
+    }
+    emit_progress(app, &event);
+
+    Ok(())
+}
+
+/// Check each depot key against its manifest before DDM runs, warning about keys that
+/// can't decrypt the manifest's file names; DDM would otherwise only fail after downloading.
+/// Depots without a key or manifest are skipped.
+async fn check_keys_against_manifests(
+    app: &AppHandle,
+    job_id: &str,
+    depot_infos: &[DepotInfo],
+    manifest_results: &[(String, Option<PathBuf>)],
+) {
+    for (depot_id, manifest_path) in manifest_results {
+        let Some(manifest_path) = manifest_path.clone() else {
+            continue;
+        };
+        let key = depot_infos
+            .iter()
+            .find(|d| d.depot_id.to_string() == *depot_id)
+            .and_then(|d| d.depot_key.as_deref())
+            .and_then(|k| depot_keys_generator::normalize_depot_key(k).ok());
+        let Some(key) = key else {
+            continue;
+        };
+
+        let check = tokio::task::spawn_blocking(move || {
+            manifest_parser::validate_key_against_manifest(&manifest_path, &key)
+        })
+        .await;
+
+        match check {
+            Ok(Ok(Some(false))) => {
+                let mut event = ProgressEvent::new("status", job_id);
+                event.step = Some("key_invalid".to_string());
+                event.depot_id = Some(depot_id.clone());
+                event.message = Some(format!(
+                    "The depot key for depot {} does not decrypt its manifest; the download will likely fail",
+                    depot_id
+                ));
+                emit_progress(app, &event);
+            }
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => eprintln!("[Download] Could not check key for depot {}: {}", depot_id, e),
+            Err(e) => eprintln!("[Download] Key check task failed: {}", e),
+        }
+    }
+}
+
+/// Re-check every downloaded depot against its manifest, chunk by chunk.
+/// Depots with missing or corrupt files are marked failed in `results`.
+/// Depots downloaded with a file list are skipped, as most of their files are absent by design.
+async fn verify_downloaded_depots(
+    app: &AppHandle,
+    job_id: &str,
+    work_dir: &Path,
+    depots: &[DepotRunConfig],
+    pattern: &str,
+    results: &mut [serde_json::Value],
+) {
+    let total = depots.len();
+    let mut problems = Vec::new();
+
+    for (i, depot) in depots.iter().enumerate() {
+        if depot.file_list.is_some() {
+            continue;
+        }
+
+        let mut event = ProgressEvent::new("status", job_id);
+        event.step = Some("verifying".to_string());
+        event.depot_id = Some(depot.depot_id.clone());
+        event.current = Some(i + 1);
+        event.total = Some(total);
+        emit_progress(app, &event);
+
+        let dir = work_dir.to_path_buf();
+        let depot_id = depot.depot_id.clone();
+        let pattern = pattern.to_string();
+        let report = tokio::task::spawn_blocking(move || depot_state::verify_depot(&dir, &depot_id, &pattern))
+            .await
+            .map_err(|e| format!("Verification task failed: {}", e))
+            .and_then(|r| r);
+
+        let failure = match report {
+            Ok(Some(report)) if report.is_clean() => None,
+            Ok(Some(report)) => {
+                let mut files: Vec<&String> = report.missing_files.iter().chain(&report.corrupt_files).collect();
+                let count = files.len();
+                files.truncate(5);
+                let names: Vec<&str> = files.iter().map(|f| f.as_str()).collect();
+                Some(format!(
+                    "Verification failed: {} missing, {} corrupt of {} files ({}{})",
+                    report.missing_files.len(),
+                    report.corrupt_files.len(),
+                    report.checked_files,
+                    names.join(", "),
+                    if count > names.len() { ", ..." } else { "" }
+                ))
+            }
+            Ok(None) => Some("Verification failed: no manifest or DDM state found".to_string()),
+            Err(e) => Some(format!("Verification failed: {}", e)),
+        };
+
+        if let Some(message) = failure {
+            if let Some(result) = results.iter_mut().find(|r| r["depotId"].as_str() == Some(depot.depot_id.as_str())) {
+                result["success"] = serde_json::Value::Bool(false);
+                result["error"] = serde_json::Value::String(message.clone());
+            }
+
+            let mut event = ProgressEvent::new("error", job_id);
+            event.depot_id = Some(depot.depot_id.clone());
+            event.message = Some(message);
+            emit_progress(app, &event);
+            problems.push(depot.depot_id.clone());
+        }
+    }
+
+    let mut event = ProgressEvent::new("status", job_id);
+    event.step = Some("verify_complete".to_string());
+    event.message = Some(if problems.is_empty() {
+        format!("All {} depots verified against their manifests.", total)
+    } else {
+        format!("Verification found problems in depots: {}", problems.join(", "))
+    });
+    emit_progress(app, &event);
+}
+
 /// Cancel an active download job.
 #[command]
 pub async fn cancel_download(
     app: AppHandle,
     state: tauri::State<'_, AppState>,
     job_id: String,
+    reason: Option<String>,
 ) -> Result<(), String> {
+    let reason = reason.unwrap_or_else(|| "user".to_string());
+    if !CANCEL_REASONS.contains(&reason.as_str()) {
+        return Err(format!("Unknown cancel reason: {}. Use 'user', 'shutdown' or 'error'.", reason));
+    }
+
     // Check job exists and get the download dir
-    let download_dir = {
+    let (download_dir, keep_files) = {
         let jobs = state.active_jobs.lock().await;
-        if !jobs.contains_key(&job_id) {
-            return Err("Job not found".to_string());
-        }
-        jobs.get(&job_id).and_then(|j| j.download_dir.clone())
+        let job = jobs.get(&job_id).ok_or_else(|| "Job not found".to_string())?;
+        (job.download_dir.clone(), job.keep_files_on_cancel)
     };
 
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let settings = settings_service::load_settings(&app_data_dir).await;
+    let cleanup_mode = cancel_cleanup_mode(&settings, keep_files);
+
     // Kill the process
-    depot_runner::kill_job(&state, &job_id).await;
+    depot_runner::kill_job(&state, &job_id, &reason).await;
 
     // Emit cancellation event
     let mut event = ProgressEvent::new("cancelled", &job_id);
-    event.message = Some("Download cancelled and files are being cleaned up.".to_string());
+    event.reason = Some(reason);
+    event.message = Some(match cleanup_mode {
+        "keep" => format!(
+            "Download cancelled; partial files were kept in {}.",
+            download_dir.as_deref().unwrap_or_default()
+        ),
+        "trash" => "Download cancelled and files are being moved to the trash.".to_string(),
+        _ => "Download cancelled and files are being cleaned up.".to_string(),
+    });
     emit_progress(&app, &event);
 
-    // Clean up downloaded files
-    if let Some(dir) = download_dir {
+    // Clean up downloaded files; abort_cleanup can still stop this during the delay
+    if let Some(dir) = download_dir.filter(|_| cleanup_mode != "keep") {
         let dir_path = std::path::PathBuf::from(&dir);
         if dir_path.exists() {
-            // Wait a bit for the process to fully exit
-            tokio::spawn(async move {
-                tokio::time::sleep(tokio::time::Duration::from_millis(2000)).await;
-                // Try to delete the directory with retries
-                for attempt in 0..3 {
-                    match tokio::fs::remove_dir_all(&dir_path).await {
-                        Ok(_) => {
-                            eprintln!("[Cancel] Cleaned up download directory: {:?}", dir_path);
-                            break;
-                        }
-                        Err(e) => {
-                            eprintln!("[Cancel] Attempt {} to delete {:?} failed: {}", attempt + 1, dir_path, e);
-                            if attempt < 2 {
-                                tokio::time::sleep(tokio::time::Duration::from_millis(2000)).await;
-                            }
-                        }
-                    }
+            let active_jobs = state.active_jobs.clone();
+            let app = app.clone();
+            let id = job_id.clone();
+            let to_trash = cleanup_mode == "trash";
+            let task = tokio::spawn(async move {
+                tokio::time::sleep(CLEANUP_DELAY).await;
+                // Past this point the deletion can no longer be undone
+                if let Some(job) = active_jobs.lock().await.get_mut(&id) {
+                    job.cleanup_task = None;
                 }
+                clean_up_cancelled_dir(&app, &id, &dir_path, to_trash).await;
             });
+
+            if let Some(job) = state.active_jobs.lock().await.get_mut(&job_id) {
+                job.cleanup_task = Some(task.abort_handle());
+            }
         }
     }
 
     Ok(())
 }
 
+/// Stop the pending file cleanup of a just-cancelled download so its partial files are kept.
+/// Returns false if there was nothing to stop (no cleanup scheduled, or already deleting).
+#[command]
+pub async fn abort_cleanup(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    job_id: String,
+) -> Result<bool, String> {
+    let (task, download_dir) = {
+        let mut jobs = state.active_jobs.lock().await;
+        let job = jobs.get_mut(&job_id).ok_or_else(|| "Job not found".to_string())?;
+        (job.cleanup_task.take(), job.download_dir.clone())
+    };
+
+    let Some(task) = task else {
+        return Ok(false);
+    };
+    task.abort();
+
+    let mut event = ProgressEvent::new("status", &job_id);
+    event.step = Some("cleanup_aborted".to_string());
+    event.message = Some(format!(
+        "Cleanup stopped; partial files were kept in {}",
+        download_dir.unwrap_or_default()
+    ));
+    emit_progress(&app, &event);
+
+    Ok(true)
+}
+
+/// Cancel every running or paused job, e.g. because the app is closing.
+/// With `delete_files`, waits until their download folders are cleaned up as
+/// `cancel_cleanup_mode` says; without it every folder is kept.
+/// Returns the number of jobs cancelled.
+pub(crate) async fn cancel_all_jobs(app: &AppHandle, state: &AppState, delete_files: bool) -> usize {
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let settings = settings_service::load_settings(&app_data_dir).await;
+
+    // (job id, download dir, has a process, files must be kept)
+    let jobs: Vec<(String, Option<String>, bool, bool)> = {
+        let jobs = state.active_jobs.lock().await;
+        jobs.iter()
+            .filter(|(_, j)| j.status == "running" || j.status == "paused")
+            .map(|(id, j)| {
+                let keep = !delete_files || j.keep_files_on_cancel;
+                (id.clone(), j.download_dir.clone(), j.child_pid.is_some(), keep)
+            })
+            .collect()
+    };
+
+    for (job_id, _, has_process, _) in &jobs {
+        if *has_process {
+            depot_runner::kill_job(state, job_id, "shutdown").await;
+        } else {
+            // No process to kill (between steps, or a hashing job); avoid kill_job's by-name fallback
+            depot_runner::mark_cancelled(state, job_id, "shutdown").await;
+        }
+
+        emit_progress(app, &depot_runner::cancelled_event(job_id, Some("shutdown")));
+    }
+
+    for (job_id, dir, _, keep) in &jobs {
+        let cleanup_mode = cancel_cleanup_mode(&settings, *keep);
+        if let Some(dir) = dir.as_deref().filter(|_| cleanup_mode != "keep") {
+            let dir_path = PathBuf::from(dir);
+            if dir_path.exists() {
+                tokio::time::sleep(CLEANUP_DELAY).await;
+                clean_up_cancelled_dir(app, job_id, &dir_path, cleanup_mode == "trash").await;
+            }
+        }
+    }
+
+    jobs.len()
+}
+
+/// What happens to a cancelled job's folder: the `cancel_cleanup_mode` setting
+/// ("delete", "trash" or "keep"), or always "keep" when the job's files must be kept.
+fn cancel_cleanup_mode(settings: &Settings, keep_files: bool) -> &str {
+    if keep_files {
+        "keep"
+    } else {
+        settings.cancel_cleanup_mode.as_str()
+    }
+}
+
+/// How long a cancelled job's process gets to exit before its folder is deleted.
+const CLEANUP_DELAY: std::time::Duration = std::time::Duration::from_millis(2000);
+
+/// Delete a cancelled job's folder (or move it to the trash), telling the UI when it
+/// had to be left in place.
+async fn clean_up_cancelled_dir(app: &AppHandle, job_id: &str, dir_path: &Path, to_trash: bool) {
+    if let Err(e) = delete_dir_with_retries(dir_path, to_trash).await {
+        let mut event = ProgressEvent::new("status", job_id);
+        event.step = Some("cleanup_failed".to_string());
+        event.message = Some(format!(
+            "Could not {} {}; the partial files were kept: {}",
+            if to_trash { "move to the trash" } else { "delete" },
+            dir_path.display(),
+            e
+        ));
+        emit_progress(app, &event);
+    }
+}
+
+/// Delete a folder (or move it to the trash), retrying while files are still locked.
+/// A folder the trash won't take is kept rather than deleted. Returns the last error.
+async fn delete_dir_with_retries(dir_path: &Path, to_trash: bool) -> Result<(), String> {
+    let mut last_error = String::new();
+    for attempt in 0..3 {
+        let result = if to_trash {
+            let path = dir_path.to_path_buf();
+            tokio::task::spawn_blocking(move || trash::move_to_trash(&path))
+                .await
+                .unwrap_or_else(|e| Err(format!("Trash task failed: {}", e)))
+        } else {
+            tokio::fs::remove_dir_all(dir_path).await.map_err(|e| e.to_string())
+        };
+        match result {
+            Ok(_) => {
+                eprintln!(
+                    "[Cancel] {} download directory: {:?}",
+                    if to_trash { "Moved to trash" } else { "Cleaned up" },
+                    dir_path
+                );
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("[Cancel] Attempt {} to delete {:?} failed: {}", attempt + 1, dir_path, e);
+                last_error = e;
+                if attempt < 2 {
+                    tokio::time::sleep(tokio::time::Duration::from_millis(2000)).await;
+                }
+            }
+        }
+    }
+    Err(last_error)
+}
+
+/// Report how much of a depot DDM has already downloaded into `work_dir`.
+/// Returns null when the depot has no manifest or DDM state there yet.
+#[command]
+pub async fn get_depot_state(
+    app: AppHandle,
+    work_dir: String,
+    depot_id: String,
+) -> Result<Option<depot_state::DepotState>, String> {
+    if depot_id.is_empty() || !depot_id.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Invalid Depot ID: {}", depot_id));
+    }
+
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let pattern = settings_service::load_settings(&app_data_dir).await.manifest_filename_pattern;
+
+    tokio::task::spawn_blocking(move || depot_state::read_depot_state(Path::new(&work_dir), &depot_id, &pattern))
+        .await
+        .map_err(|e| format!("Depot state task failed: {}", e))?
+}
+
+/// Check `proposed_name` against the folders in `download_dir` (the download location
+/// setting by default): whether it exists, whether downloading into it would mix with
+/// something unrelated, other folders for the same App ID, and a free name to use instead.
+/// Returns { proposedName, existingName, conflict, sameAppFolders, suggestion }.
+#[command]
+pub async fn check_folder_collision(
+    app: AppHandle,
+    proposed_name: String,
+    download_dir: Option<String>,
+) -> Result<folder_collision::FolderCollision, String> {
+    let proposed_name = proposed_name.trim().to_string();
+    if proposed_name.is_empty()
+        || proposed_name == "."
+        || proposed_name == ".."
+        || proposed_name.contains(['/', '\\'])
+    {
+        return Err(format!("Invalid folder name: {}", proposed_name));
+    }
+
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let settings = settings_service::load_settings(&app_data_dir).await;
+    let base_dir = match download_dir.filter(|d| !d.trim().is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(&settings.download_location),
+    };
+    let pattern = settings.manifest_filename_pattern;
+
+    tokio::task::spawn_blocking(move || {
+        let entries = folder_collision::scan_entries(&base_dir, &proposed_name, &pattern);
+        folder_collision::check_collision(&entries, &proposed_name)
+    })
+    .await
+    .map_err(|e| format!("Failed to check download folder: {}", e))
+}
+
+/// Look for earlier downloads of an App ID in `download_dir` (the download location setting
+/// by default): folders named `{app_id}` or `{app_id} - {name}`, as the pipeline creates them.
+/// Newest first. Returns [{ path, folderName, modifiedAt, complete, depots: [{ depotId, manifestId, installed }] }];
+/// `complete` means every depot found was fully installed by DDM.
+#[command]
+pub async fn find_existing_download(
+    app: AppHandle,
+    app_id: String,
+    download_dir: Option<String>,
+) -> Result<Vec<serde_json::Value>, String> {
+    if !steam_store_api::is_valid_app_id(&app_id) {
+        return Err(format!("Invalid App ID: {}", app_id));
+    }
+
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let settings = settings_service::load_settings(&app_data_dir).await;
+    let base_dir = match download_dir.filter(|d| !d.trim().is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(&settings.download_location),
+    };
+    let pattern = settings.manifest_filename_pattern;
+
+    tokio::task::spawn_blocking(move || {
+        let prefix = format!("{} - ", app_id);
+        let mut found: Vec<(Option<std::time::SystemTime>, serde_json::Value)> = std::fs::read_dir(&base_dir)
+            .into_iter()
+            .flat_map(|entries| entries.flatten())
+            .filter(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                (name == app_id || name.starts_with(&prefix)) && entry.path().is_dir()
+            })
+            .map(|entry| {
+                let path = entry.path();
+                let modified = entry.metadata().and_then(|m| m.modified()).ok();
+                let depots = depot_state::list_downloaded_depots(&path, &pattern);
+                let complete = !depots.is_empty() && depots.iter().all(|d| d.installed);
+                let value = serde_json::json!({
+                    "path": path.to_string_lossy(),
+                    "folderName": entry.file_name().to_string_lossy(),
+                    "modifiedAt": modified.map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339()),
+                    "complete": complete,
+                    "depots": depots,
+                });
+                (modified, value)
+            })
+            .collect();
+        found.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+        found.into_iter().map(|(_, value)| value).collect()
+    })
+    .await
+    .map_err(|e| format!("Download lookup failed: {}", e))
+}
+
+/// Delete DDM's resume state for one depot in `work_dir`, for a depot stuck failing to resume.
+/// Returns the removed paths.
+#[command]
+pub async fn clear_depot_state(
+    work_dir: String,
+    depot_id: String,
+) -> Result<Vec<String>, String> {
+    if depot_id.is_empty() || !depot_id.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Invalid Depot ID: {}", depot_id));
+    }
+
+    let removed = tokio::task::spawn_blocking(move || depot_state::clear_depot_state(Path::new(&work_dir), &depot_id))
+        .await
+        .map_err(|e| format!("Depot state task failed: {}", e))??;
+    Ok(removed.iter().map(|p| p.to_string_lossy().to_string()).collect())
+}
+
+/// Get the most recent output lines of a job (combined stdout/stderr), oldest first.
+/// Lets a reopened log panel show context that scrolled past before it subscribed.
+#[command]
+pub async fn get_job_output_tail(
+    state: tauri::State<'_, AppState>,
+    job_id: String,
+    lines: Option<usize>,
+) -> Result<Vec<String>, String> {
+    let tail = {
+        let jobs = state.active_jobs.lock().await;
+        jobs.get(&job_id).ok_or("Job not found")?.output_tail.clone()
+    };
+
+    Ok(tail.last(lines.unwrap_or(OUTPUT_TAIL_LINES)))
+}
+
+/// Every job the backend knows about: running, paused, and finished ones still within
+/// `job_retention_minutes`, oldest first, so a reloaded UI can pick up where it was.
+/// Returns [{ jobId, kind, status, appId, downloadDir, startedAt, cancelReason, progress }],
+/// `progress` being the job's latest progress event (null before the first one).
+#[command]
+pub async fn list_jobs(state: tauri::State<'_, AppState>) -> Result<Vec<serde_json::Value>, String> {
+    let mut jobs: Vec<(i64, serde_json::Value)> = {
+        let jobs = state.active_jobs.lock().await;
+        jobs.iter()
+            .map(|(id, job)| {
+                let value = serde_json::json!({
+                    "jobId": id,
+                    "kind": job.kind,
+                    "status": job.status,
+                    "appId": job.app_id,
+                    "downloadDir": job.download_dir,
+                    "startedAt": job.started_at,
+                    "cancelReason": job.cancel_reason,
+                });
+                (job.started_at, value)
+            })
+            .collect()
+    };
+
+    if let Ok(progress) = state.job_progress.lock() {
+        for (_, job) in &mut jobs {
+            let id = job["jobId"].as_str().unwrap_or_default().to_string();
+            job["progress"] = serde_json::json!(progress.get(&id));
+        }
+    }
+
+    jobs.sort_by_key(|(started_at, _)| *started_at);
+    Ok(jobs.into_iter().map(|(_, job)| job).collect())
+}
+
+/// Drop finished jobs (complete, failed or cancelled) from the job list when their download
+/// folder no longer exists or they started more than `max_age_minutes` ago (default: the
+/// `job_retention_minutes` setting; 0 means age alone never prunes). Useful when retention
+/// is 0 and finished jobs would otherwise stay listed until exit. Returns how many were removed.
+/// Jobs only live in memory, so the list starts empty and there is nothing to prune at
+/// startup; running this from the setup hook only makes sense once jobs are persisted.
+#[command]
+pub async fn prune_jobs(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    max_age_minutes: Option<u64>,
+) -> Result<usize, String> {
+    let max_age_minutes = match max_age_minutes {
+        Some(minutes) => minutes,
+        None => {
+            let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+            settings_service::load_settings(&app_data_dir).await.job_retention_minutes
+        }
+    };
+    let cutoff = chrono::Utc::now().timestamp() - (max_age_minutes as i64) * 60;
+
+    let pruned: Vec<String> = {
+        let mut jobs = state.active_jobs.lock().await;
+        let stale: Vec<String> = jobs
+            .iter()
+            .filter(|(_, job)| matches!(job.status.as_str(), "complete" | "failed" | "cancelled"))
+            .filter(|(_, job)| {
+                let folder_gone = job.download_dir.as_deref().is_some_and(|dir| !Path::new(dir).exists());
+                let too_old = max_age_minutes > 0 && job.started_at < cutoff;
+                folder_gone || too_old
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &stale {
+            jobs.remove(id);
+        }
+        stale
+    };
+
+    for id in &pruned {
+        depot_runner::forget_job_progress(&state.job_progress, id);
+    }
+    if !pruned.is_empty() {
+        eprintln!("[Download] Pruned {} finished job(s)", pruned.len());
+    }
+    Ok(pruned.len())
+}
+
+/// Try downloading one manifest from a repo into a temp dir and check it parses,
+/// without creating a job or running DDM. The temp file is removed afterwards.
+/// Returns { success, size, url, depotId, manifestId, fileCount, error }.
+#[command]
+pub async fn test_manifest_download(
+    state: tauri::State<'_, AppState>,
+    app_id: String,
+    depot_id: String,
+    manifest_id: String,
+    repo: String,
+    token: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let manifest_id = manifest_downloader::normalize_manifest_id(&manifest_id)?;
+    let url = manifest_downloader::manifest_url(&repo, &app_id, &depot_id, &manifest_id);
+    let temp_dir = std::env::temp_dir()
+        .join("SteamManifestDownloader")
+        .join("manifest-test")
+        .join(Uuid::new_v4().to_string());
+
+    let result = async {
+        let path = manifest_downloader::download_manifest(
+            &state.http_client,
+            &app_id,
+            &depot_id,
+            &manifest_id,
+            &repo,
+            &temp_dir,
+            &ManifestFetchOptions {
+                github_token: token.as_deref().filter(|t| !t.is_empty()),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let data = tokio::fs::read(&path)
+            .await
+            .map_err(|e| format!("Failed to read downloaded manifest: {}", e))?;
+        let manifest = manifest_parser::parse_manifest(&data)
+            .map_err(|e| format!("Downloaded file is not a valid manifest: {}", e))?;
+
+        if manifest.depot_id.to_string() != depot_id || manifest.manifest_id.to_string() != manifest_id {
+            return Err(format!(
+                "Manifest is for depot {} / manifest {}, not the requested one",
+                manifest.depot_id, manifest.manifest_id
+            ));
+        }
+        Ok((data.len(), manifest.files.len()))
+    }
+    .await;
+
+    let _ = tokio::fs::remove_dir_all(&temp_dir).await;
+
+    Ok(match result {
+        Ok((size, file_count)) => serde_json::json!({
+            "success": true,
+            "size": size,
+            "url": url,
+            "depotId": depot_id,
+            "manifestId": manifest_id,
+            "fileCount": file_count,
+            "error": null,
+        }),
+        Err(e) => serde_json::json!({
+            "success": false,
+            "size": null,
+            "url": url,
+            "depotId": depot_id,
+            "manifestId": manifest_id,
+            "fileCount": null,
+            "error": e,
+        }),
+    })
+}
+
+/// Validate and normalize a manifest id pasted by the user.
+/// Returns the canonical digit string or a message explaining why it was rejected.
+#[command]
+pub async fn normalize_manifest_id(input: String) -> Result<String, String> {
+    manifest_downloader::normalize_manifest_id(&input)
+}
+
 /// Export a script for manual download execution.
 /// On Windows: generates a .bat file. On Linux: generates a .sh file.
+/// With `scriptType: "docker"`, generates a shell script of `docker run` commands instead
+/// (see `docker_export_script`). Manifest paths follow the `manifest_filename_pattern` setting.
 #[command]
-pub async fn export_batch_script(config: serde_json::Value) -> Result<String, String> {
+pub async fn export_batch_script(app: AppHandle, config: serde_json::Value) -> Result<String, String> {
     let app_id = config["appId"]
         .as_str()
         .or_else(|| config["mainAppId"].as_str())
@@ -643,6 +2120,29 @@ pub async fn export_batch_script(config: serde_json::Value) -> Result<String, St
         .as_str()
         .unwrap_or(&default_game_name);
 
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let pattern = settings_service::load_settings(&app_data_dir).await.manifest_filename_pattern;
+
+    match config["scriptType"].as_str().unwrap_or("native") {
+        "native" => {}
+        "docker" => {
+            let inputs = ExportInputs {
+                app_id,
+                game_name,
+                depots,
+                folder_name: &folder_name,
+                download_dir: config["downloadDir"].as_str(),
+                pattern: &pattern,
+            };
+            return Ok(docker_export_script(
+                &inputs,
+                config["dockerImage"].as_str().filter(|i| !i.trim().is_empty()),
+                config["ddmDir"].as_str().unwrap_or(DOCKER_DEFAULT_DDM_DIR),
+            ));
+        }
+        other => return Err(format!("Unsupported script type: {}. Use 'native' or 'docker'.", other)),
+    }
+
     #[cfg(target_os = "windows")]
     {
         // Escape special batch characters
@@ -659,21 +2159,12 @@ pub async fn export_batch_script(config: serde_json::Value) -> Result<String, St
         script.push_str("\r\n");
 
         for (i, depot) in depots.iter().enumerate() {
-            let depot_id = depot["depotId"]
-                .as_str()
-                .or_else(|| depot["depot_id"].as_str())
-                .unwrap_or("0");
-
-            let manifest_id = depot["customManifestId"]
-                .as_str()
-                .or_else(|| depot["manifestId"].as_str())
-                .or_else(|| depot["manifest_id"].as_str())
-                .unwrap_or("0");
+            let (depot_id, manifest_id) = export_depot_ids(depot);
 
             script.push_str(&format!("REM Depot {}\r\n", depot_id));
             script.push_str(&format!(
-                "DepotDownloaderMod.exe -app {} -depot {} -manifest {} -depotkeys \"{}\\steam.keys\" -manifestfile \"{}\\{}_{}.manifest\"\r\n",
-                app_id, depot_id, manifest_id, folder_name, folder_name, depot_id, manifest_id
+                "DepotDownloaderMod.exe {}\r\n",
+                export_depot_args(app_id, depot_id, manifest_id, &folder_name, '\\', &pattern)
             ));
             script.push_str(&format!(
                 "if %errorlevel% neq 0 echo ERROR: Depot {} failed!\r\n",
@@ -706,21 +2197,12 @@ pub async fn export_batch_script(config: serde_json::Value) -> Result<String, St
         script.push_str("\n");
 
         for (i, depot) in depots.iter().enumerate() {
-            let depot_id = depot["depotId"]
-                .as_str()
-                .or_else(|| depot["depot_id"].as_str())
-                .unwrap_or("0");
-
-            let manifest_id = depot["customManifestId"]
-                .as_str()
-                .or_else(|| depot["manifestId"].as_str())
-                .or_else(|| depot["manifest_id"].as_str())
-                .unwrap_or("0");
+            let (depot_id, manifest_id) = export_depot_ids(depot);
 
             script.push_str(&format!("# Depot {}\n", depot_id));
             script.push_str(&format!(
-                "./DepotDownloaderMod -app {} -depot {} -manifest {} -depotkeys \"{}/steam.keys\" -manifestfile \"{}/{}_{}.manifest\"\n",
-                app_id, depot_id, manifest_id, folder_name, folder_name, depot_id, manifest_id
+                "./DepotDownloaderMod {}\n",
+                export_depot_args(app_id, depot_id, manifest_id, &folder_name, '/', &pattern)
             ));
             script.push_str(&format!(
                 "if [ $? -ne 0 ]; then echo \"ERROR: Depot {} failed!\"; fi\n",
@@ -740,15 +2222,281 @@ pub async fn export_batch_script(config: serde_json::Value) -> Result<String, St
     }
 }
 
+/// .NET runtime image the Docker export runs the DDM DLL with, matching DDM's target framework.
+const DOCKER_DOTNET_IMAGE: &str = "mcr.microsoft.com/dotnet/runtime:9.0";
+
+/// Host folder holding `DepotDownloaderMod.dll` when the export config doesn't name one.
+const DOCKER_DEFAULT_DDM_DIR: &str = "./DepotDownloaderMod";
+
+/// Proxy variables passed from the host into the container; unset ones are left out by Docker.
+const DOCKER_PROXY_VARS: &[&str] = &["HTTP_PROXY", "HTTPS_PROXY", "NO_PROXY"];
+
+/// What `export_batch_script` read from its config, for the script builders.
+struct ExportInputs<'a> {
+    app_id: &'a str,
+    game_name: &'a str,
+    depots: &'a [serde_json::Value],
+    folder_name: &'a str,
+    /// As given; None when the config has no `downloadDir`
+    download_dir: Option<&'a str>,
+    /// `manifest_filename_pattern` from the settings
+    pattern: &'a str,
+}
+
+/// Shell script with one `docker run` per depot. The download directory is mounted at
+/// `/data` and used as the working directory, so `steam.keys`, the manifests and DDM's
+/// `depots/` output sit where the native script would put them. With `image`, that image
+/// is expected to run DDM as its entrypoint; otherwise the .NET runtime image runs
+/// `DepotDownloaderMod.dll` from `ddm_dir`, mounted read-only at `/ddm`.
+fn docker_export_script(inputs: &ExportInputs, image: Option<&str>, ddm_dir: &str) -> String {
+    let mut script = String::new();
+    script.push_str("#!/bin/sh\n");
+    script.push_str("# === Steam Manifest Downloader - Docker Script ===\n");
+    script.push_str(&format!(
+        "# Game: {} (AppID: {})\n",
+        inputs.game_name.replace(['\r', '\n'], " "),
+        inputs.app_id
+    ));
+    script.push('\n');
+    // Bind mounts need an absolute path
+    match inputs.download_dir.filter(|d| !d.trim().is_empty() && *d != ".") {
+        Some(dir) => script.push_str(&format!("DOWNLOAD_DIR=\"{}\"\n", escape_shell_chars(dir))),
+        None => script.push_str("DOWNLOAD_DIR=\"$(pwd)\"\n"),
+    }
+    if image.is_none() {
+        script.push_str(&format!("DDM_DIR=\"$(cd \"{}\" && pwd)\" || exit 1\n", escape_shell_chars(ddm_dir)));
+    }
+    script.push('\n');
+
+    let proxy_env: String = DOCKER_PROXY_VARS.iter().map(|v| format!(" -e {}", v)).collect();
+    let run_prefix = match image {
+        Some(image) => format!(
+            "docker run --rm{} -v \"$DOWNLOAD_DIR:/data\" -w /data \"{}\"",
+            proxy_env,
+            escape_shell_chars(image)
+        ),
+        None => format!(
+            "docker run --rm{} -v \"$DOWNLOAD_DIR:/data\" -v \"$DDM_DIR:/ddm:ro\" -w /data {} dotnet /ddm/DepotDownloaderMod.dll",
+            proxy_env, DOCKER_DOTNET_IMAGE
+        ),
+    };
+
+    let folder_name = escape_shell_chars(inputs.folder_name);
+    for depot in inputs.depots {
+        let (depot_id, manifest_id) = export_depot_ids(depot);
+        script.push_str(&format!("# Depot {}\n", depot_id));
+        script.push_str(&format!(
+            "{} {} || echo \"ERROR: Depot {} failed!\"\n\n",
+            run_prefix,
+            export_depot_args(inputs.app_id, depot_id, manifest_id, &folder_name, '/', inputs.pattern),
+            depot_id
+        ));
+    }
+
+    script.push_str("echo \"=== All downloads complete! ===\"\n");
+    script
+}
+
+/// Check that the files an exported script references exist, relative to `dir`
+/// (defaults to the config's `downloadDir`, where the script `cd`s to).
+/// Returns one warning per missing file; an empty list means the script can run as is.
+#[command]
+pub async fn validate_export(
+    app: AppHandle,
+    config: serde_json::Value,
+    dir: Option<String>,
+) -> Result<Vec<String>, String> {
+    let app_id = config["appId"]
+        .as_str()
+        .or_else(|| config["mainAppId"].as_str())
+        .ok_or("Missing appId")?;
+
+    let depots = config["depots"]
+        .as_array()
+        .or_else(|| config["selectedDepots"].as_array())
+        .ok_or("Missing depots array")?;
+
+    let folder_name = config["folderName"].as_str().unwrap_or(app_id);
+    let base = PathBuf::from(dir.as_deref().or_else(|| config["downloadDir"].as_str()).unwrap_or("."));
+    let folder = base.join(folder_name);
+
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let pattern = settings_service::load_settings(&app_data_dir).await.manifest_filename_pattern;
+
+    let mut warnings = Vec::new();
+
+    let keys_path = folder.join("steam.keys");
+    if !keys_path.is_file() {
+        warnings.push(format!("Depot key file missing: {}", keys_path.display()));
+    }
+
+    for depot in depots {
+        let (depot_id, manifest_id) = export_depot_ids(depot);
+        let manifest_path = folder.join(manifest_downloader::manifest_filename(&pattern, depot_id, manifest_id));
+        if !manifest_path.is_file() {
+            warnings.push(format!(
+                "Manifest for depot {} missing: {}",
+                depot_id,
+                manifest_path.display()
+            ));
+        }
+    }
+
+    Ok(warnings)
+}
+
 // --- Helper functions ---
 
+/// DDM arguments for one exported depot, with `steam.keys` and the manifest (named by
+/// `pattern`) read from `folder` (joined with `sep`).
+fn export_depot_args(app_id: &str, depot_id: &str, manifest_id: &str, folder: &str, sep: char, pattern: &str) -> String {
+    format!(
+        "-app {} -depot {} -manifest {} -depotkeys \"{}{}steam.keys\" -manifestfile \"{}{}{}\"",
+        app_id,
+        depot_id,
+        manifest_id,
+        folder,
+        sep,
+        folder,
+        sep,
+        manifest_downloader::manifest_filename(pattern, depot_id, manifest_id)
+    )
+}
+
+/// Depot and manifest id of one exported depot entry, accepting both the camelCase and
+/// snake_case keys the frontend sends.
+fn export_depot_ids(depot: &serde_json::Value) -> (&str, &str) {
+    let depot_id = depot["depotId"]
+        .as_str()
+        .or_else(|| depot["depot_id"].as_str())
+        .unwrap_or("0");
+
+    let manifest_id = depot["customManifestId"]
+        .as_str()
+        .or_else(|| depot["manifestId"].as_str())
+        .or_else(|| depot["manifest_id"].as_str())
+        .unwrap_or("0");
+
+    (depot_id, manifest_id)
+}
+
+/// Report how many depot keys one source supplied during the key stage.
+fn emit_key_source(app: &AppHandle, job_id: &str, source: &str, found: usize, total: usize) {
+    let mut event = ProgressEvent::new("status", job_id);
+    event.step = Some("key_source".to_string());
+    event.source = Some(source.to_string());
+    event.depot_count = Some(found);
+    event.total = Some(total);
+    emit_progress(app, &event);
+}
+
+/// Record a job that stopped on its own after reporting why, so it no longer counts as running.
+async fn mark_failed(state: &AppState, job_id: &str) {
+    if let Some(job) = state.active_jobs.lock().await.get_mut(job_id) {
+        job.status = "failed".to_string();
+    }
+}
+
+/// The job's current status, or None once it has been dropped.
+async fn job_status(state: &AppState, job_id: &str) -> Option<String> {
+    state.active_jobs.lock().await.get(job_id).map(|j| j.status.clone())
+}
+
+/// Whether the job was cancelled or paused and the pipeline should stop.
 async fn check_cancelled(state: &AppState, job_id: &str) -> bool {
     let jobs = state.active_jobs.lock().await;
     jobs.get(job_id)
-        .map(|j| j.status == "cancelled")
+        .map(|j| j.status == "cancelled" || j.status == "paused")
         .unwrap_or(false)
 }
 
+/// How often free space is checked while DDM runs.
+const DISK_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Watch free space on the download drive while the job runs.
+///
+/// Warns once each time free space drops below `low_disk_threshold_gb`, and with
+/// `pause_on_low_disk` stops DDM before the disk fills. Never returns; the caller
+/// drops it when the download finishes.
+async fn monitor_disk_space(
+    app: &AppHandle,
+    state: &AppState,
+    job_id: &str,
+    work_dir: &Path,
+    settings: &Settings,
+) {
+    let mut warned = false;
+
+    loop {
+        tokio::time::sleep(DISK_CHECK_INTERVAL).await;
+
+        if settings.low_disk_threshold_gb <= 0.0 || check_cancelled(state, job_id).await {
+            continue;
+        }
+
+        let dir = work_dir.to_path_buf();
+        let info = tokio::task::spawn_blocking(move || get_disk_space_info(&dir))
+            .await
+            .ok()
+            .flatten();
+        let Some((free_gb, drive)) = info else {
+            continue;
+        };
+
+        if free_gb >= settings.low_disk_threshold_gb {
+            warned = false;
+            continue;
+        }
+        if warned {
+            continue;
+        }
+        warned = true;
+
+        let mut event = ProgressEvent::new("status", job_id);
+        event.step = Some("low_disk_space".to_string());
+        event.free_gb = Some(free_gb);
+        event.drive = Some(drive.clone());
+        event.message = Some(format!(
+            "Only {:.2} GB free on {} (threshold {:.2} GB).",
+            free_gb, drive, settings.low_disk_threshold_gb
+        ));
+        emit_progress(app, &event);
+
+        if settings.pause_on_low_disk {
+            depot_runner::stop_job(state, job_id, "paused").await;
+
+            let mut event = ProgressEvent::new("status", job_id);
+            event.step = Some("paused".to_string());
+            event.free_gb = Some(free_gb);
+            event.drive = Some(drive);
+            event.message = Some(format!("Stopping the download: only {:.2} GB left.", free_gb));
+            emit_progress(app, &event);
+        }
+    }
+}
+
+/// Count files and bytes under a directory, ignoring entries that can't be read.
+fn dir_usage(dir: &Path) -> (u64, u64) {
+    let mut files = 0;
+    let mut bytes = 0;
+
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                let (f, b) = dir_usage(&path);
+                files += f;
+                bytes += b;
+            } else if let Ok(meta) = entry.metadata() {
+                files += 1;
+                bytes += meta.len();
+            }
+        }
+    }
+
+    (files, bytes)
+}
+
 fn resolve_download_dir(dir_path: Option<&str>) -> Option<PathBuf> {
     let path_str = dir_path?.trim();
     if path_str.is_empty() {
@@ -756,16 +2504,45 @@ fn resolve_download_dir(dir_path: Option<&str>) -> Option<PathBuf> {
     }
 
     let resolved = PathBuf::from(path_str);
-    if !resolved.is_absolute() {
-        return None;
-    }
-    if resolved.to_string_lossy().len() < 3 {
+    if !is_usable_download_dir(&resolved) {
+        eprintln!("[Download] Ignoring download location '{}': not a full path to a folder", path_str);
         return None;
     }
 
     Some(resolved)
 }
 
+/// Whether a download location is absolute and more than a bare root (`/`, `C:`).
+/// On Windows, UNC locations (`\\server\share`, or verbatim `\\?\UNC\server\share`) are accepted
+/// once they name a share; drive-relative paths like `C:folder` are not absolute and rejected.
+fn is_usable_download_dir(path: &Path) -> bool {
+    if !path.is_absolute() {
+        return false;
+    }
+
+    #[cfg(target_os = "windows")]
+    if let Some(std::path::Component::Prefix(prefix)) = path.components().next() {
+        use std::path::Prefix;
+        if let Prefix::UNC(_, share) | Prefix::VerbatimUNC(_, share) = prefix.kind() {
+            return !share.is_empty();
+        }
+    }
+
+    path.to_string_lossy().len() >= 3
+}
+
+/// Escape a value for use inside double quotes in a POSIX shell script.
+fn escape_shell_chars(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '"' | '$' | '`' | '\\') {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}
+
 #[cfg(target_os = "windows")]
 fn escape_batch_chars(s: &str) -> String {
     let mut result = String::with_capacity(s.len());
@@ -781,51 +2558,75 @@ fn escape_batch_chars(s: &str) -> String {
     result
 }
 
-#[cfg(target_os = "windows")]
+/// Free GB on the volume holding `path`, and the path that was checked.
 fn get_disk_space_info(path: &Path) -> Option<(f64, String)> {
-    let path_str = path.to_string_lossy();
-    if path_str.len() < 2 {
-        return None;
-    }
-
-    let drive_letter = path_str.chars().next()?;
-    let drive = format!("{}:", drive_letter);
+    let (space, checked) = disk_space::query(path)?;
+    Some((space.free_gb(), checked.to_string_lossy().to_string()))
+}
 
-    let mut cmd = std::process::Command::new("powershell");
-    cmd.args([
-            "-NoProfile",
-            "-Command",
-            &format!("(Get-PSDrive {}).Free", drive_letter),
-        ]);
-    cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
-    let output = cmd.output().ok()?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let free_bytes: u64 = stdout.trim().parse().ok()?;
-    let free_gb = (free_bytes as f64) / (1024.0 * 1024.0 * 1024.0);
-    let free_gb = (free_gb * 100.0).round() / 100.0;
+    #[test]
+    fn falls_back_when_location_is_not_a_full_path() {
+        assert_eq!(resolve_download_dir(None), None);
+        assert_eq!(resolve_download_dir(Some("   ")), None);
+        assert_eq!(resolve_download_dir(Some("downloads")), None);
+        assert_eq!(resolve_download_dir(Some("./downloads")), None);
+    }
 
-    Some((free_gb, drive))
-}
+    #[test]
+    fn accepts_absolute_location() {
+        let dir = std::env::temp_dir().join("SteamDownloads");
+        let input = format!("  {}  ", dir.to_string_lossy());
+        assert_eq!(resolve_download_dir(Some(&input)), Some(dir));
+    }
 
-#[cfg(target_os = "linux")]
-fn get_disk_space_info(path: &Path) -> Option<(f64, String)> {
-    use std::ffi::CString;
+    #[test]
+    fn kept_files_override_cancel_cleanup_mode() {
+        let mut settings = Settings::default();
+        assert_eq!(cancel_cleanup_mode(&settings, false), "delete");
+        settings.cancel_cleanup_mode = "trash".to_string();
+        assert_eq!(cancel_cleanup_mode(&settings, false), "trash");
+        assert_eq!(cancel_cleanup_mode(&settings, true), "keep");
+    }
 
-    let path_str = path.to_string_lossy();
-    let c_path = CString::new(path_str.as_ref()).ok()?;
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn rejects_bare_root() {
+        assert!(!is_usable_download_dir(Path::new("/")));
+        assert!(is_usable_download_dir(Path::new("/srv/games")));
+    }
 
-    unsafe {
-        let mut stat: libc::statvfs = std::mem::zeroed();
-        let result = libc::statvfs(c_path.as_ptr(), &mut stat);
-        if result != 0 {
-            return None;
-        }
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn accepts_unc_locations() {
+        assert!(is_usable_download_dir(Path::new(r"\\server\share")));
+        assert!(is_usable_download_dir(Path::new(r"\\server\share\Games")));
+        assert!(is_usable_download_dir(Path::new(r"\\?\UNC\server\share\Games")));
+        assert_eq!(
+            resolve_download_dir(Some(r"\\nas\downloads\Steam")),
+            Some(PathBuf::from(r"\\nas\downloads\Steam"))
+        );
+    }
 
-        let free = (stat.f_bavail as u64) * (stat.f_frsize as u64);
-        let free_gb = (free as f64) / (1024.0 * 1024.0 * 1024.0);
-        let free_gb = (free_gb * 100.0).round() / 100.0;
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn accepts_mapped_drive_locations() {
+        assert!(is_usable_download_dir(Path::new(r"Z:\")));
+        assert!(is_usable_download_dir(Path::new(r"Z:\SteamDownloads")));
+        assert_eq!(
+            resolve_download_dir(Some(r"Z:\SteamDownloads")),
+            Some(PathBuf::from(r"Z:\SteamDownloads"))
+        );
+    }
 
-        Some((free_gb, path_str.to_string()))
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn rejects_drive_relative_locations() {
+        assert!(!is_usable_download_dir(Path::new("Z:")));
+        assert!(!is_usable_download_dir(Path::new(r"Z:SteamDownloads")));
+        assert_eq!(resolve_download_dir(Some(r"Z:SteamDownloads")), None);
     }
 }
@@ -1,6 +1,23 @@
-use tauri::command;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use base64::Engine;
+use tauri::{command, AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+use uuid::Uuid;
+
+use crate::services::{AppState, JobInfo};
+use crate::services::atomic_file;
+use crate::services::depot_keys_generator;
+use crate::services::depot_runner::{emit_progress, forget_job_progress, ProgressEvent};
+use crate::services::depot_table_parser;
+use crate::services::file_hasher::{self, HashAlgorithm};
+use crate::services::file_manager;
+use crate::services::key_backup;
 use crate::services::lua_parser;
+use crate::services::manifest_folder;
+use crate::services::settings as settings_service;
 use crate::services::st_parser;
+use crate::services::vdf_parser;
 
 /// Parse a .lua or .st file at the given path.
 /// Returns the parsed depot information as JSON.
@@ -37,6 +54,75 @@ pub async fn parse_lua_file(path: String) -> Result<serde_json::Value, String> {
     }
 }
 
+/// Turn a depot-key map into Key.vdf content, e.g. to contribute keys back to a repo.
+/// With a sean-who `repo` the keys are XOR encrypted the way that repo stores them.
+/// Keys must be 64 hex characters and depot ids numeric.
+#[command]
+pub async fn export_key_vdf(keys: HashMap<String, String>, repo: Option<String>) -> Result<String, String> {
+    if keys.is_empty() {
+        return Err("No depot keys to export".to_string());
+    }
+    for (depot_id, key) in &keys {
+        if depot_id.is_empty() || !depot_id.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("Invalid depot ID: {}", depot_id));
+        }
+        if key.len() != 64 || !key.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Depot {} key must be 64 hex characters", depot_id));
+        }
+    }
+
+    Ok(vdf_parser::build_key_vdf(&keys, repo.as_deref()))
+}
+
+/// Show a folder in the system file manager.
+#[command]
+pub async fn open_folder(path: String) -> Result<(), String> {
+    file_manager::open_folder(Path::new(&path))
+}
+
+/// Parse a depot table pasted from SteamDB into depot id / name / manifest id rows.
+/// Returns { rows: [...], unparsed: [...] }
+#[command]
+pub async fn parse_depot_table(text: String) -> Result<serde_json::Value, String> {
+    let result = depot_table_parser::parse_depot_table(&text);
+    serde_json::to_value(&result).map_err(|e| format!("Failed to serialize result: {}", e))
+}
+
+/// Find the manifests in a folder so the depot list can be filled from it.
+/// Returns { manifests: [{ depot_id, manifest_id, path }], unrecognized: [...] }
+#[command]
+pub async fn scan_manifest_folder(dir: String) -> Result<serde_json::Value, String> {
+    let scan = tokio::task::spawn_blocking(move || manifest_folder::scan_manifest_folder(&PathBuf::from(dir)))
+        .await
+        .map_err(|e| format!("Folder scan task failed: {}", e))??;
+    serde_json::to_value(&scan).map_err(|e| format!("Failed to serialize result: {}", e))
+}
+
+/// Open a native multi-select dialog for manifest inputs (.lua, .st, .manifest).
+/// Returns the chosen paths, or an empty list if the dialog was cancelled.
+#[command]
+pub async fn pick_manifest_files(app: AppHandle) -> Result<Vec<String>, String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title("Select manifest files")
+        .add_filter("Manifest files", &["lua", "st", "manifest"])
+        .pick_files(move |files| {
+            let _ = tx.send(files);
+        });
+
+    let files = rx
+        .await
+        .map_err(|e| format!("File dialog closed unexpectedly: {}", e))?
+        .unwrap_or_default();
+
+    Ok(files
+        .into_iter()
+        .filter_map(|f| f.into_path().ok())
+        .map(|p| p.to_string_lossy().to_string())
+        .collect())
+}
+
 /// Parse lua content string directly (for when frontend passes content).
 #[command]
 pub async fn parse_lua_content(content: String, filename: String) -> Result<serde_json::Value, String> {
@@ -52,9 +138,8 @@ pub async fn parse_lua_content(content: String, filename: String) -> Result<serd
             serde_json::to_value(&result).map_err(|e| format!("Failed to serialize result: {}", e))
         }
         "st" => {
-            // For .st files, content should be base64 encoded or raw bytes
-            // Try parsing as UTF-8 lua first, then as raw bytes
-            let result = lua_parser::parse_lua_file(&content);
+            // .st files are binary, so the content has to arrive base64 encoded
+            let result = parse_st_base64(&content)?;
             serde_json::to_value(&result).map_err(|e| format!("Failed to serialize result: {}", e))
         }
         _ => {
@@ -64,3 +149,311 @@ pub async fn parse_lua_content(content: String, filename: String) -> Result<serd
         }
     }
 }
+
+/// Check that a .st file decrypts and decompresses without using its content.
+/// Returns { valid, depotCount, error }.
+#[command]
+pub async fn validate_st_file(path: String) -> Result<serde_json::Value, String> {
+    let buffer = tokio::fs::read(&path)
+        .await
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    Ok(match st_parser::parse_st_file(&buffer) {
+        Ok(result) => serde_json::json!({
+            "valid": true,
+            "depotCount": result.depots.len(),
+            "error": null,
+        }),
+        Err(e) => serde_json::json!({
+            "valid": false,
+            "depotCount": 0,
+            "error": e,
+        }),
+    })
+}
+
+/// Parse a base64-encoded .st file held in memory (e.g. from a drag-drop).
+/// Returns the same depot result as `parse_lua_file` does for a .st path.
+#[command]
+pub async fn parse_st_content(content: String) -> Result<serde_json::Value, String> {
+    let result = parse_st_base64(&content)?;
+    serde_json::to_value(&result).map_err(|e| format!("Failed to serialize result: {}", e))
+}
+
+/// Fill depot keys into a depot selection from a Key.vdf the user supplied, the manual
+/// counterpart of the pipeline's Key.vdf merge. Depots that already have a key keep it,
+/// and keys that aren't valid AES keys are left out. Other depot fields pass through as sent.
+/// Returns { depots: [... with depotKey and keyFromVdf], keyed: [depotId], rejected: [{ depotId, reason }], vdfKeyCount }.
+#[command]
+pub async fn apply_key_vdf_to_selection(
+    vdf_content: String,
+    repo: Option<String>,
+    mut depots: Vec<serde_json::Value>,
+) -> Result<serde_json::Value, String> {
+    let vdf_keys = vdf_parser::parse_key_vdf(&vdf_content, repo.as_deref());
+    if vdf_keys.is_empty() {
+        return Err("No depot keys found in the Key.vdf".to_string());
+    }
+
+    let mut keyed = Vec::new();
+    let mut rejected = Vec::new();
+
+    for depot in &mut depots {
+        let Some(fields) = depot.as_object_mut() else {
+            return Err("Each depot must be an object".to_string());
+        };
+        let depot_id = fields
+            .get("depotId")
+            .or_else(|| fields.get("depot_id"))
+            .and_then(|v| v.as_str().map(String::from).or_else(|| v.as_u64().map(|n| n.to_string())))
+            .ok_or("Each depot needs a depotId")?;
+        let has_key = fields
+            .get("depotKey")
+            .and_then(|v| v.as_str())
+            .is_some_and(|k| !k.trim().is_empty());
+
+        let mut from_vdf = false;
+        if !has_key {
+            if let Some(key) = vdf_keys.get(&depot_id) {
+                match depot_keys_generator::normalize_depot_key(key) {
+                    Ok(key) => {
+                        fields.insert("depotKey".to_string(), serde_json::Value::String(key));
+                        keyed.push(depot_id.clone());
+                        from_vdf = true;
+                    }
+                    Err(reason) => rejected.push(serde_json::json!({ "depotId": depot_id, "reason": reason })),
+                }
+            }
+        }
+        fields.insert("keyFromVdf".to_string(), serde_json::Value::Bool(from_vdf));
+    }
+
+    Ok(serde_json::json!({
+        "depots": depots,
+        "keyed": keyed,
+        "rejected": rejected,
+        "vdfKeyCount": vdf_keys.len(),
+    }))
+}
+
+/// Back up a download's `steam.keys` to `{app_data_dir}/key_backups/{app_id}.keys` so the
+/// keys survive the folder being deleted. Without `download_dir`, looks for the app's
+/// folder in the configured download location.
+/// Returns { backupPath, source, added } where `added` counts depots new to the backup.
+#[command]
+pub async fn backup_keys(
+    app: AppHandle,
+    app_id: String,
+    download_dir: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let folder = match download_dir.filter(|d| !d.trim().is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let settings = settings_service::load_settings(&app_data_dir).await;
+            key_backup::find_keys_folder(Path::new(&settings.download_location), &app_id)
+                .ok_or_else(|| format!("No download folder with a steam.keys found for App ID {}", app_id))?
+        }
+    };
+
+    let keys_file = folder.join("steam.keys");
+    let (backup_path, added) = key_backup::backup_keys(&app_data_dir, &app_id, &keys_file).await?;
+
+    Ok(serde_json::json!({
+        "backupPath": backup_path.to_string_lossy(),
+        "source": keys_file.to_string_lossy(),
+        "added": added,
+    }))
+}
+
+/// Write the backed-up keys for an app into `{target_dir}/steam.keys`, keeping any keys
+/// already there. Returns { path, added }.
+#[command]
+pub async fn restore_keys(
+    app: AppHandle,
+    app_id: String,
+    target_dir: String,
+) -> Result<serde_json::Value, String> {
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let target = PathBuf::from(&target_dir);
+    if !target.is_dir() {
+        return Err(format!("Folder does not exist: {}", target_dir));
+    }
+
+    let (path, added) = key_backup::restore_keys(&app_data_dir, &app_id, &target).await?;
+
+    Ok(serde_json::json!({
+        "path": path.to_string_lossy(),
+        "added": added,
+    }))
+}
+
+/// Fix a folder's `steam.keys` in place: drop malformed and duplicate lines and normalize the
+/// rest to `depotId;hexKey`. The file is only rewritten when something changed.
+/// Returns { path, changed, kept, normalized: [lineNumber], dropped: [{ lineNumber, line, reason }] }.
+#[command]
+pub async fn repair_steam_keys(work_dir: String) -> Result<serde_json::Value, String> {
+    let path = Path::new(&work_dir).join("steam.keys");
+    let content = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+    let repair = depot_keys_generator::repair_key_lines(&content);
+    let changed = repair.content != content;
+    if changed {
+        atomic_file::write_atomic_async(&path, repair.content.clone().into_bytes())
+            .await
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        eprintln!(
+            "[Keys] Repaired {}: kept {}, dropped {}",
+            path.display(),
+            repair.kept,
+            repair.dropped.len()
+        );
+    }
+
+    let mut result = serde_json::to_value(&repair).map_err(|e| format!("Failed to serialize repair: {}", e))?;
+    result["path"] = serde_json::json!(path.to_string_lossy());
+    result["changed"] = serde_json::json!(changed);
+    Ok(result)
+}
+
+fn parse_st_base64(content: &str) -> Result<lua_parser::LuaParseResult, String> {
+    let buffer = base64::engine::general_purpose::STANDARD
+        .decode(content.trim())
+        .map_err(|e| format!("Invalid base64 .st content: {}", e))?;
+    st_parser::parse_st_file(&buffer)
+}
+
+/// Hash every file of a finished download and write `hashes.json` into the folder.
+/// Returns { jobId } immediately; progress arrives as `download-progress` events with
+/// step "hashing", and the job can be stopped with `cancel_hashing`.
+#[command]
+pub async fn hash_download(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    work_dir: String,
+    algorithm: String,
+) -> Result<serde_json::Value, String> {
+    let algorithm = HashAlgorithm::parse(&algorithm)?;
+    let root = PathBuf::from(&work_dir);
+    if !root.is_dir() {
+        return Err(format!("Directory not found: {}", work_dir));
+    }
+
+    let job_id = Uuid::new_v4().to_string();
+    {
+        let mut jobs = state.active_jobs.lock().await;
+        let mut job = JobInfo::new(None);
+        job.kind = "hashing".to_string();
+        jobs.insert(job_id.clone(), job);
+    }
+
+    let active_jobs = state.active_jobs.clone();
+    let job_progress = state.job_progress.clone();
+    let job_id_clone = job_id.clone();
+    let running = state.running_jobs.start();
+
+    tokio::spawn(async move {
+        let _running = running;
+        let is_cancelled = || async {
+            let jobs = active_jobs.lock().await;
+            jobs.get(&job_id_clone)
+                .map(|j| j.status == "cancelled")
+                .unwrap_or(false)
+        };
+
+        let root_clone = root.clone();
+        let files = tokio::task::spawn_blocking(move || file_hasher::list_download_files(&root_clone))
+            .await
+            .unwrap_or_default();
+        let total = files.len();
+
+        let mut hashes: BTreeMap<String, String> = BTreeMap::new();
+        let mut last_emit = tokio::time::Instant::now();
+        let mut cancelled = false;
+
+        for (i, file) in files.into_iter().enumerate() {
+            if is_cancelled().await {
+                cancelled = true;
+                break;
+            }
+
+            let relative = file_hasher::relative_path(&root, &file);
+            let now = tokio::time::Instant::now();
+            if now.duration_since(last_emit) >= tokio::time::Duration::from_millis(150) || i + 1 == total {
+                let mut event = ProgressEvent::new("status", &job_id_clone);
+                event.step = Some("hashing".to_string());
+                event.current = Some(i + 1);
+                event.total = Some(total);
+                event.filename = Some(relative.clone());
+                emit_progress(&app, &event);
+                last_emit = now;
+            }
+
+            match tokio::task::spawn_blocking(move || file_hasher::hash_file(&file, algorithm)).await {
+                Ok(Ok(hash)) => {
+                    hashes.insert(relative, hash);
+                }
+                Ok(Err(e)) => eprintln!("[FileOps] Skipping {}: {}", relative, e),
+                Err(e) => eprintln!("[FileOps] Hash task failed for {}: {}", relative, e),
+            }
+        }
+
+        if cancelled {
+            let mut event = ProgressEvent::new("cancelled", &job_id_clone);
+            event.message = Some("Hashing cancelled; hashes.json was not written.".to_string());
+            emit_progress(&app, &event);
+        } else {
+            let output_path = root.join("hashes.json");
+            let content = serde_json::json!({
+                "algorithm": algorithm.name(),
+                "generatedAt": chrono::Utc::now().to_rfc3339(),
+                "files": hashes,
+            });
+
+            let write_result = match serde_json::to_string_pretty(&content) {
+                Ok(json) => atomic_file::write_atomic_async(&output_path, json.into_bytes())
+                    .await
+                    .map_err(|e| format!("Failed to write hashes.json: {}", e)),
+                Err(e) => Err(format!("Failed to serialize hashes: {}", e)),
+            };
+
+            match write_result {
+                Ok(_) => {
+                    let mut event = ProgressEvent::new("complete", &job_id_clone);
+                    event.step = Some("hashing".to_string());
+                    event.total = Some(total);
+                    event.filename = Some(output_path.to_string_lossy().to_string());
+                    event.message = Some(format!("Hashed {} files with {}.", hashes.len(), algorithm.name()));
+                    emit_progress(&app, &event);
+                }
+                Err(e) => {
+                    let mut event = ProgressEvent::new("error", &job_id_clone);
+                    event.message = Some(e);
+                    emit_progress(&app, &event);
+                }
+            }
+        }
+
+        let mut jobs = active_jobs.lock().await;
+        jobs.remove(&job_id_clone);
+        forget_job_progress(&job_progress, &job_id_clone);
+    });
+
+    Ok(serde_json::json!({ "jobId": job_id }))
+}
+
+/// Stop a running `hash_download` job. There is no child process to kill,
+/// so this only flags the job; the hasher stops before its next file.
+#[command]
+pub async fn cancel_hashing(
+    state: tauri::State<'_, AppState>,
+    job_id: String,
+) -> Result<(), String> {
+    let mut jobs = state.active_jobs.lock().await;
+    let job = jobs.get_mut(&job_id).ok_or("Job not found")?;
+    job.status = "cancelled".to_string();
+    job.cancel_reason = Some("user".to_string());
+    Ok(())
+}
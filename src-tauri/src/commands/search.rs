@@ -1,37 +1,298 @@
-use tauri::command;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use serde::Deserialize;
+use tauri::{command, AppHandle, Emitter, Manager};
 use crate::services::AppState;
 use crate::services::multi_repo_search;
+use crate::services::manifest_downloader;
+use crate::services::manifest_history;
 use crate::services::alternative_sources;
 use crate::services::steam_store_api;
+use crate::services::public_appinfo;
+use crate::services::ddm_appinfo;
+use crate::services::depot_runner;
+use crate::services::settings as settings_service;
 
 /// Search all known repos for an App ID.
-/// Returns { repos: [...], githubRateLimited: bool }
+/// Emits a "search-progress" `repo_found` event per repo as it answers, then `search_complete`.
+/// Returns { repos: [...], githubRateLimited: bool }. With the `auto_select_best_repo` setting,
+/// also { recommended_repo, repo_scores }, the GitHub repos ranked by depot key coverage.
 #[command]
 pub async fn search_repos(
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
     app_id: String,
     github_token: Option<String>,
 ) -> Result<serde_json::Value, String> {
-    let result = multi_repo_search::search_repos(
+    let result = multi_repo_search::search_repos_streaming(
         &state.http_client,
         &app_id,
         github_token.as_deref(),
+        |repo| emit_search_progress(&app, serde_json::json!({
+            "type": "repo_found",
+            "appId": app_id,
+            "repo": repo,
+        })),
     )
     .await?;
 
-    serde_json::to_value(&result).map_err(|e| format!("Failed to serialize search result: {}", e))
+    emit_search_progress(&app, serde_json::json!({
+        "type": "search_complete",
+        "appId": app_id,
+        "count": result.repos.len(),
+        "github_rate_limited": result.github_rate_limited,
+    }));
+
+    let mut response =
+        serde_json::to_value(&result).map_err(|e| format!("Failed to serialize search result: {}", e))?;
+
+    // Opt-in, as it lists every found repo's tree
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let settings = settings_service::load_settings(&app_data_dir).await;
+    if settings.auto_select_best_repo && !result.github_rate_limited {
+        let scores =
+            multi_repo_search::rank_repos(&state.http_client, &app_id, &result.repos, github_token.as_deref()).await;
+        response["recommended_repo"] = serde_json::json!(scores.first().map(|s| s.repo.clone()));
+        response["repo_scores"] = serde_json::json!(scores);
+    }
+
+    Ok(response)
+}
+
+fn emit_search_progress(app: &AppHandle, payload: serde_json::Value) {
+    if let Err(e) = app.emit("search-progress", payload) {
+        eprintln!("[Search] Failed to emit search progress: {}", e);
+    }
 }
 
 /// Get manifest file listing from a repo's branch.
 /// Returns manifests list with depot keys.
 #[command]
 pub async fn get_repo_manifests(
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
     app_id: String,
     repo: String,
     sha: Option<String>,
     github_token: Option<String>,
 ) -> Result<serde_json::Value, String> {
+    let (_, result) = fetch_repo_manifests(&app, &state, &app_id, &repo, sha, github_token.as_deref()).await?;
+    serde_json::to_value(&result).map_err(|e| format!("Failed to serialize manifests: {}", e))
+}
+
+/// Compare a repo branch's current manifests with the tree seen before it changed.
+/// Fetches the branch head, so calling this also records it for later diffs.
+/// Returns { previous_sha, previous_seen_at, current_sha, current_seen_at, added, removed, changed };
+/// `previous_sha` is null until the branch has been seen with two different trees.
+#[command]
+pub async fn diff_repo_manifests(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    repo: String,
+    app_id: String,
+    github_token: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let (history, _) = fetch_repo_manifests(&app, &state, &app_id, &repo, None, github_token.as_deref()).await?;
+    let history = history.ok_or("Failed to record the branch's manifests, so there is nothing to compare")?;
+    let diff = manifest_history::diff_history(&history);
+    serde_json::to_value(&diff).map_err(|e| format!("Failed to serialize manifest diff: {}", e))
+}
+
+/// How fresh one repo's branch for an app is, without searching every repo.
+/// Returns { exists, last_updated, sha }; errors when GitHub rate limits the check.
+#[command]
+pub async fn get_repo_freshness(
+    state: tauri::State<'_, AppState>,
+    repo: String,
+    app_id: String,
+    github_token: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let info = crate::services::github_api::get_branch_info(
+        &state.http_client,
+        &repo,
+        &app_id,
+        github_token.as_deref(),
+    )
+    .await?;
+
+    if info.rate_limited {
+        return Err("GitHub API rate limit exceeded. Add a GitHub token in Settings to raise it.".to_string());
+    }
+
+    Ok(serde_json::json!({
+        "exists": info.exists,
+        "last_updated": info.last_updated,
+        "sha": info.sha,
+    }))
+}
+
+/// A depot/manifest pair from `get_repo_manifests` to size.
+#[derive(Debug, Deserialize)]
+pub struct DepotManifestRef {
+    #[serde(rename = "depotId", alias = "depot_id")]
+    pub depot_id: String,
+    #[serde(rename = "manifestId", alias = "manifest_id")]
+    pub manifest_id: String,
+}
+
+/// Report each depot's uncompressed size by reading only the metadata of its manifest
+/// in the repo branch. Returns [{ depot_id, manifest_id, size, error }], with `size`
+/// null (and `error` set) for manifests that couldn't be read.
+#[command]
+pub async fn get_depot_sizes(
+    state: tauri::State<'_, AppState>,
+    app_id: String,
+    repo: String,
+    manifests: Vec<DepotManifestRef>,
+    github_token: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let mut handles = Vec::new();
+
+    for entry in manifests {
+        let client = state.http_client.clone();
+        let app_id = app_id.clone();
+        let repo = repo.clone();
+        let token = github_token.clone();
+
+        handles.push(tokio::spawn(async move {
+            let size = manifest_downloader::fetch_manifest_size_cached(
+                &client,
+                &app_id,
+                &entry.depot_id,
+                &entry.manifest_id,
+                &repo,
+                token.as_deref(),
+            )
+            .await;
+            (entry, size)
+        }));
+    }
+
+    let mut sizes = Vec::new();
+    for handle in handles {
+        let (entry, size) = handle
+            .await
+            .map_err(|e| format!("Size lookup task failed: {}", e))?;
+        if let Err(ref e) = size {
+            eprintln!("[Search] Could not size depot {}: {}", entry.depot_id, e);
+        }
+        sizes.push(serde_json::json!({
+            "depot_id": entry.depot_id,
+            "manifest_id": entry.manifest_id,
+            "size": size.as_ref().ok(),
+            "error": size.err(),
+        }));
+    }
+
+    Ok(serde_json::Value::Array(sizes))
+}
+
+/// Sizes of several manifest versions of one depot, so the UI can compare them before
+/// picking one. Reads only each manifest's metadata, concurrently; sizes are cached per
+/// manifest id. Returns [{ manifest_id, size, error }] in the order given.
+#[command]
+pub async fn get_manifest_version_sizes(
+    state: tauri::State<'_, AppState>,
+    app_id: String,
+    repo: String,
+    depot_id: String,
+    manifest_ids: Vec<String>,
+    github_token: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let mut handles = Vec::new();
+
+    for manifest_id in manifest_ids {
+        let client = state.http_client.clone();
+        let app_id = app_id.clone();
+        let repo = repo.clone();
+        let depot_id = depot_id.clone();
+        let token = github_token.clone();
+
+        handles.push(tokio::spawn(async move {
+            let size = manifest_downloader::fetch_manifest_size_cached(
+                &client,
+                &app_id,
+                &depot_id,
+                &manifest_id,
+                &repo,
+                token.as_deref(),
+            )
+            .await;
+            (manifest_id, size)
+        }));
+    }
+
+    let mut sizes = Vec::new();
+    for handle in handles {
+        let (manifest_id, size) = handle
+            .await
+            .map_err(|e| format!("Size lookup task failed: {}", e))?;
+        if let Err(ref e) = size {
+            eprintln!("[Search] Could not size manifest {} of depot {}: {}", manifest_id, depot_id, e);
+        }
+        sizes.push(serde_json::json!({
+            "manifest_id": manifest_id,
+            "size": size.as_ref().ok(),
+            "error": size.err(),
+        }));
+    }
+
+    Ok(serde_json::Value::Array(sizes))
+}
+
+/// Split a game's depots (from the public app info mirror) into those the repo branch has
+/// manifests for and those it doesn't. Needs `enable_public_manifest_lookup`.
+/// Returns { covered: [...], missing: [...], extra: [...] }; `extra` are repo depots Steam doesn't list.
+#[command]
+pub async fn get_repo_depot_coverage(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    app_id: String,
+    repo: String,
+    sha: Option<String>,
+    github_token: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let settings = settings_service::load_settings(&app_data_dir).await;
+    if !settings.enable_public_manifest_lookup {
+        return Err("Depot coverage needs public manifest lookup, which is disabled in settings".to_string());
+    }
+
+    let (_, manifests) = fetch_repo_manifests(&app, &state, &app_id, &repo, sha, github_token.as_deref()).await?;
+    let app_depots = public_appinfo::list_app_depots(&state.http_client, &app_id)
+        .await?
+        .ok_or_else(|| format!("No depot list found for App ID {}", app_id))?;
+
+    let repo_depots: HashSet<&str> = manifests.manifests.iter().map(|m| m.depot_id.as_str()).collect();
+    let (covered, missing): (Vec<_>, Vec<_>) = app_depots
+        .iter()
+        .partition(|d| repo_depots.contains(d.depot_id.as_str()));
+
+    let mut extra: Vec<&str> = repo_depots
+        .iter()
+        .copied()
+        .filter(|id| !app_depots.iter().any(|d| d.depot_id == *id))
+        .collect();
+    extra.sort();
+
+    Ok(serde_json::json!({
+        "covered": covered,
+        "missing": missing,
+        "extra": extra,
+    }))
+}
+
+/// Manifests on a repo branch, looking up the branch head when no SHA is given.
+/// Each fetched tree is recorded in the manifest history; the updated history entry is
+/// returned alongside, or `None` if recording failed (which doesn't fail the fetch).
+async fn fetch_repo_manifests(
+    app: &AppHandle,
+    state: &AppState,
+    app_id: &str,
+    repo: &str,
+    sha: Option<String>,
+    github_token: Option<&str>,
+) -> Result<(Option<manifest_history::RepoHistory>, multi_repo_search::RepoManifests), String> {
     // If no SHA provided, we need to look up the branch first
     let effective_sha = match sha {
         Some(s) if !s.is_empty() => s,
@@ -39,9 +300,9 @@ pub async fn get_repo_manifests(
             // Use app_id as branch name to get the SHA
             let branch_info = crate::services::github_api::get_branch_info(
                 &state.http_client,
-                &repo,
-                &app_id,
-                github_token.as_deref(),
+                repo,
+                app_id,
+                github_token,
             )
             .await?;
 
@@ -51,25 +312,101 @@ pub async fn get_repo_manifests(
         }
     };
 
-    let result = multi_repo_search::get_repo_manifests(
+    let manifests = multi_repo_search::get_repo_manifests(
         &state.http_client,
-        &app_id,
-        &repo,
+        app_id,
+        repo,
         &effective_sha,
+        github_token,
+    )
+    .await?;
+
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let snapshot = manifest_history::snapshot_of(&effective_sha, &manifests);
+    let history = match manifest_history::record_snapshot(&app_data_dir, repo, app_id, snapshot).await {
+        Ok(history) => Some(history),
+        Err(e) => {
+            eprintln!("[Search] {}", e);
+            None
+        }
+    };
+
+    Ok((history, manifests))
+}
+
+/// GitHub API rate limit for the given token, plus how bulk searches are currently
+/// throttled because of it. Querying the limit doesn't count against it.
+/// Returns { remaining, limit, reset_time, adaptive: { remaining, limit, reset_time, concurrency, delay_ms } }
+#[command]
+pub async fn get_github_rate_limit(
+    state: tauri::State<'_, AppState>,
+    github_token: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let info = crate::services::github_api::check_rate_limit(&state.http_client, github_token.as_deref()).await?;
+    let adaptive = crate::services::github_api::adaptive_state();
+
+    Ok(serde_json::json!({
+        "remaining": info.remaining,
+        "limit": info.limit,
+        "reset_time": info.reset_time,
+        "adaptive": adaptive,
+    }))
+}
+
+/// How many GitHub API requests an operation would cost, so the UI can warn before running
+/// it. `repo_count` applies to searches; `has_sha` to manifest listings and downloads.
+/// Returns { operation, requests, breakdown: [...] }
+#[command]
+pub async fn estimate_api_cost(
+    operation: String,
+    repo_count: Option<usize>,
+    has_sha: Option<bool>,
+) -> Result<serde_json::Value, String> {
+    let estimate = multi_repo_search::estimate_api_cost(&operation, repo_count, has_sha.unwrap_or(false))?;
+    serde_json::to_value(&estimate).map_err(|e| format!("Failed to serialize API cost estimate: {}", e))
+}
+
+/// After a failed branch check, look for another repo that has the App ID so the UI can
+/// offer to switch. `exclude_repo` defaults to the default repo check_branch uses.
+/// Returns { repo: RepoResult | null, github_rate_limited: bool }
+#[command]
+pub async fn find_any_repo_with_app(
+    state: tauri::State<'_, AppState>,
+    app_id: String,
+    github_token: Option<String>,
+    exclude_repo: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let exclude = exclude_repo.unwrap_or_else(|| multi_repo_search::REPOS[0].to_string());
+    let (repo, rate_limited) = multi_repo_search::find_alternative_repo(
+        &state.http_client,
+        &app_id,
         github_token.as_deref(),
+        &exclude,
     )
     .await?;
 
-    serde_json::to_value(&result).map_err(|e| format!("Failed to serialize manifests: {}", e))
+    Ok(serde_json::json!({
+        "repo": repo,
+        "github_rate_limited": rate_limited,
+    }))
 }
 
 /// Search alternative sources (kernelos or printedwaste).
+/// Sources turned off in `enabled_alternative_sources` fail immediately without a request.
 #[command]
 pub async fn search_alternative(
+    app: AppHandle,
     state: tauri::State<'_, AppState>,
     app_id: String,
     source: String,
 ) -> Result<serde_json::Value, String> {
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let settings = settings_service::load_settings(&app_data_dir).await;
+    let known = settings_service::ALTERNATIVE_SOURCES.contains(&source.to_lowercase().as_str());
+    if known && !settings.alternative_source_enabled(&source) {
+        return Err(format!("Source disabled: {} is turned off in settings", source));
+    }
+
     match source.to_lowercase().as_str() {
         "printedwaste" => {
             let result = alternative_sources::download_from_printed_waste(
@@ -96,6 +433,30 @@ pub async fn search_alternative(
     }
 }
 
+/// Query every alternative source enabled in settings at once.
+/// Returns { depots: [{ depot_id, manifest_id, depot_key, sources, key_source }], errors: { source: message }, kernelos }
+#[command]
+pub async fn search_all_alternatives(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    app_id: String,
+) -> Result<serde_json::Value, String> {
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let settings = settings_service::load_settings(&app_data_dir).await;
+    let sources: Vec<String> = settings_service::ALTERNATIVE_SOURCES
+        .iter()
+        .filter(|s| settings.alternative_source_enabled(s))
+        .map(|s| s.to_string())
+        .collect();
+    if sources.is_empty() {
+        return Err("All alternative sources are turned off in settings".to_string());
+    }
+
+    let temp_dir = std::env::temp_dir().join("steam_manifest_downloader");
+    let result = alternative_sources::search_all_sources(&state.http_client, &app_id, &sources, &temp_dir).await;
+    serde_json::to_value(&result).map_err(|e| format!("Failed to serialize alternative sources result: {}", e))
+}
+
 /// Get Steam Store app info (name, header image, etc.).
 #[command]
 pub async fn get_steam_app_info(
@@ -115,3 +476,152 @@ pub async fn get_steam_app_info(
         None => Ok(serde_json::Value::Null),
     }
 }
+
+/// Whether an app likely needs depot keys (paid) or downloads anonymously (free).
+#[command]
+pub async fn app_requires_key(
+    state: tauri::State<'_, AppState>,
+    app_id: String,
+) -> Result<bool, String> {
+    steam_store_api::app_requires_key(&state.http_client, &state.steam_cache, &app_id).await
+}
+
+/// Clear the Steam Store info cache so names and images are fetched fresh.
+/// Returns the number of entries removed.
+#[command]
+pub async fn clear_steam_cache(
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, String> {
+    let mut cache = state.steam_cache.lock().await;
+    let count = cache.len();
+    cache.clear();
+    Ok(count)
+}
+
+/// Drop a single app from the Steam Store info cache.
+/// Returns whether the app was cached.
+#[command]
+pub async fn clear_steam_cache_entry(
+    state: tauri::State<'_, AppState>,
+    app_id: String,
+) -> Result<bool, String> {
+    let mut cache = state.steam_cache.lock().await;
+    Ok(cache.remove(&app_id).is_some())
+}
+
+/// Best-effort lookup of a depot's current public manifest id from a public app info mirror.
+/// Disabled unless `enable_public_manifest_lookup` is on. Returns null when nothing is found.
+#[command]
+pub async fn lookup_manifest_steamdb(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    app_id: String,
+    depot_id: String,
+) -> Result<serde_json::Value, String> {
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let settings = settings_service::load_settings(&app_data_dir).await;
+    if !settings.enable_public_manifest_lookup {
+        return Err("Public manifest lookup is disabled in settings".to_string());
+    }
+
+    if !steam_store_api::is_valid_app_id(&app_id) {
+        return Err(format!("Invalid App ID: {}", app_id));
+    }
+    if depot_id.is_empty() || !depot_id.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Invalid Depot ID: {}", depot_id));
+    }
+
+    match public_appinfo::lookup_public_manifest(&state.http_client, &app_id, &depot_id).await? {
+        Some(manifest) => serde_json::to_value(&manifest)
+            .map_err(|e| format!("Failed to serialize manifest info: {}", e)),
+        None => Ok(serde_json::Value::Null),
+    }
+}
+
+/// Which of an app's depots the game needs on an OS and language, from the public app info.
+/// `os` defaults to the one this app runs on and `language` to "english".
+/// Returns [{ depotId, language, oslist, dlcAppId, optional, matchesPlatform, required }].
+#[command]
+pub async fn get_depot_requirements(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    app_id: String,
+    os: Option<String>,
+    language: Option<String>,
+) -> Result<serde_json::Value, String> {
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let settings = settings_service::load_settings(&app_data_dir).await;
+    if !settings.enable_public_manifest_lookup {
+        return Err("Depot requirements need public manifest lookup, which is disabled in settings".to_string());
+    }
+
+    if !steam_store_api::is_valid_app_id(&app_id) {
+        return Err(format!("Invalid App ID: {}", app_id));
+    }
+
+    let depots = public_appinfo::list_app_depots(&state.http_client, &app_id)
+        .await?
+        .ok_or_else(|| format!("No depot list found for App ID {}", app_id))?;
+
+    let os = os.unwrap_or_else(|| {
+        if cfg!(target_os = "windows") { "windows" } else { "linux" }.to_string()
+    });
+    let language = language.unwrap_or_else(|| "english".to_string());
+
+    serde_json::to_value(public_appinfo::depot_requirements(depots, &os, &language))
+        .map_err(|e| format!("Failed to serialize depot requirements: {}", e))
+}
+
+/// An app's depots grouped by language, from the public app info, with the ones to select
+/// for `languages` (the `preferred_languages` setting by default).
+/// Returns { languages: { [language]: [depotId] }, neutral: [depotId], autoSelect: [depotId], preferredLanguages }.
+#[command]
+pub async fn get_language_depots(
+    app: AppHandle,
+    state: tauri::State<'_, AppState>,
+    app_id: String,
+    languages: Option<Vec<String>>,
+) -> Result<serde_json::Value, String> {
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let settings = settings_service::load_settings(&app_data_dir).await;
+    if !settings.enable_public_manifest_lookup {
+        return Err("Language depots need public manifest lookup, which is disabled in settings".to_string());
+    }
+
+    if !steam_store_api::is_valid_app_id(&app_id) {
+        return Err(format!("Invalid App ID: {}", app_id));
+    }
+
+    let depots = public_appinfo::list_app_depots(&state.http_client, &app_id)
+        .await?
+        .ok_or_else(|| format!("No depot list found for App ID {}", app_id))?;
+
+    let preferred = languages.unwrap_or(settings.preferred_languages);
+    let mut result = serde_json::to_value(public_appinfo::group_language_depots(&depots, &preferred))
+        .map_err(|e| format!("Failed to serialize language depots: {}", e))?;
+    result["preferredLanguages"] = serde_json::json!(preferred);
+    Ok(result)
+}
+
+/// List an app's depots through DepotDownloaderMod's anonymous Steam login, for apps the
+/// store API and appinfo mirror don't return. Slow (DDM has to log in), so results are cached
+/// unless `refresh` is set. Only depot ids are known this way.
+/// Returns [{ depotId, language, oslist, dlcAppId, optional }].
+#[command]
+pub async fn list_app_depots_via_ddm(
+    app: AppHandle,
+    app_id: String,
+    refresh: Option<bool>,
+) -> Result<Vec<public_appinfo::AppDepot>, String> {
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let settings = settings_service::load_settings(&app_data_dir).await;
+    let exe_path = depot_runner::get_exe_path_async().await?;
+
+    ddm_appinfo::list_app_depots_via_ddm(
+        &exe_path,
+        settings.resolve_dotnet_path().as_deref(),
+        &app_id,
+        refresh.unwrap_or(false),
+    )
+    .await
+}
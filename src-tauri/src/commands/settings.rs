@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 use tauri::{command, AppHandle, Manager};
 use crate::services::settings as settings_service;
+use crate::services::config_bundle;
 
 /// Get current settings.
 #[command]
@@ -18,6 +19,63 @@ pub async fn save_settings(app: AppHandle, settings: serde_json::Value) -> Resul
     // Deserialize the incoming value into Settings, merging with defaults
     let new_settings: settings_service::Settings = serde_json::from_value(settings)
         .map_err(|e| format!("Invalid settings format: {}", e))?;
+    new_settings.validate()?;
 
     settings_service::save_settings(&app_data_dir, &new_settings).await
 }
+
+/// The settings a download would run with: defaults, then `settings.json`, then the given
+/// per-job overrides. Returns { settings, sources: { field: "default" | "file" | "override" }, ddArgs },
+/// where `ddArgs` are the extra DepotDownloaderMod arguments actually passed.
+#[command]
+pub async fn get_effective_settings(
+    app: AppHandle,
+    job_overrides: Option<serde_json::Value>,
+) -> Result<serde_json::Value, String> {
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let (settings, sources) = settings_service::effective_settings(&app_data_dir, job_overrides.as_ref()).await?;
+
+    Ok(serde_json::json!({
+        "settings": settings,
+        "sources": sources,
+        "ddArgs": settings.dd_args(),
+    }))
+}
+
+/// Export all config files (settings, profiles, ...) into a zip bundle at `path`.
+/// Tokens are blanked unless `include_secrets` is set.
+#[command]
+pub async fn export_config_bundle(
+    app: AppHandle,
+    path: String,
+    include_secrets: Option<bool>,
+) -> Result<serde_json::Value, String> {
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let include_secrets = include_secrets.unwrap_or(false);
+
+    let files = tokio::task::spawn_blocking(move || {
+        config_bundle::export_bundle(&app_data_dir, &PathBuf::from(path), include_secrets)
+    })
+    .await
+    .map_err(|e| format!("Export task failed: {}", e))??;
+
+    Ok(serde_json::json!({ "files": files, "secretsIncluded": include_secrets }))
+}
+
+/// Import a config bundle. Without `overwrite`, returns the conflicting files
+/// and changes nothing so the UI can ask for confirmation.
+#[command]
+pub async fn import_config_bundle(
+    app: AppHandle,
+    path: String,
+    overwrite: Option<bool>,
+) -> Result<config_bundle::ImportResult, String> {
+    let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let overwrite = overwrite.unwrap_or(false);
+
+    tokio::task::spawn_blocking(move || {
+        config_bundle::import_bundle(&app_data_dir, &PathBuf::from(path), overwrite)
+    })
+    .await
+    .map_err(|e| format!("Import task failed: {}", e))?
+}
@@ -1,16 +1,27 @@
-use tauri::command;
+use std::path::PathBuf;
+use tauri::{command, AppHandle};
+use crate::services::disk_space;
+use crate::services::embedded_tools;
+use crate::services::steam_library;
+
+#[cfg(target_os = "windows")]
+use tauri::Manager;
+#[cfg(target_os = "windows")]
+use crate::services::settings as settings_service;
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
 /// Check if .NET 9 runtime is installed.
 /// On Linux, the DDM binary is self-contained so dotnet is not needed.
-/// Runs `dotnet --list-runtimes` and checks for "Microsoft.NETCore.App 9."
+/// Runs `dotnet --list-runtimes` (using the `dotnet_path` setting if set)
+/// and checks for "Microsoft.NETCore.App 9."
 #[command]
-pub async fn check_dotnet() -> Result<serde_json::Value, String> {
+pub async fn check_dotnet(app: AppHandle) -> Result<serde_json::Value, String> {
     // On Linux, DDM is a self-contained binary — no dotnet needed
     #[cfg(target_os = "linux")]
     {
+        let _ = app;
         return Ok(serde_json::json!({
             "installed": true,
             "version": "self-contained",
@@ -19,7 +30,13 @@ pub async fn check_dotnet() -> Result<serde_json::Value, String> {
 
     #[cfg(target_os = "windows")]
     {
-        let mut cmd = std::process::Command::new("dotnet");
+        let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let settings = settings_service::load_settings(&app_data_dir).await;
+        let dotnet = settings
+            .resolve_dotnet_path()
+            .unwrap_or_else(|| PathBuf::from("dotnet"));
+
+        let mut cmd = std::process::Command::new(dotnet);
         cmd.args(["--list-runtimes"]);
         cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
         match cmd.output() {
@@ -57,78 +74,138 @@ pub async fn check_dotnet() -> Result<serde_json::Value, String> {
     }
 }
 
-/// Get disk space information for a given path.
-/// Uses PowerShell on Windows, statvfs on Linux.
+/// Official .NET 9 runtime installer for this machine, for when `check_dotnet` finds none.
+/// The bundled `DepotDownloaderMod.exe` is an x64 app, so it needs the x64 runtime even on
+/// ARM64 Windows; the ARM64 runtime is only offered when a `dotnet_path` runs the DLL instead.
+/// Returns { url, arch, osArch }; `url` is null on Linux, where DDM is self-contained.
 #[command]
-pub async fn get_disk_space(path: String) -> Result<serde_json::Value, String> {
+pub async fn get_dotnet_install_url(app: AppHandle) -> Result<serde_json::Value, String> {
+    #[cfg(target_os = "linux")]
+    {
+        let _ = app;
+        Ok(serde_json::json!({
+            "url": null,
+            "arch": null,
+            "osArch": std::env::consts::ARCH,
+        }))
+    }
+
     #[cfg(target_os = "windows")]
     {
-        if path.len() < 2 {
-            return Err("Invalid path".to_string());
-        }
+        let os_arch = windows_os_arch();
+        let app_data_dir = app.path().app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let settings = settings_service::load_settings(&app_data_dir).await;
+
+        let arch = match os_arch {
+            "arm64" if settings.resolve_dotnet_path().is_some() => "arm64",
+            "arm64" | "x64" => "x64",
+            other => return Err(format!("DepotDownloaderMod needs 64-bit Windows, this is {}", other)),
+        };
+
+        Ok(serde_json::json!({
+            "url": format!("https://aka.ms/dotnet/9.0/dotnet-runtime-win-{}.exe", arch),
+            "arch": arch,
+            "osArch": os_arch,
+        }))
+    }
+}
 
-        let drive_letter = path.chars().next().ok_or("Empty path")?;
-
-        let mut cmd = std::process::Command::new("powershell");
-        cmd.args([
-                "-NoProfile",
-                "-Command",
-                &format!(
-                    "$d = Get-PSDrive {}; @{{ Free = $d.Free; Used = $d.Used }} | ConvertTo-Json",
-                    drive_letter
-                ),
-            ]);
-        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
-        match cmd.output() {
-            Ok(output) => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let data: serde_json::Value = serde_json::from_str(stdout.trim())
-                    .unwrap_or_else(|_| serde_json::json!({}));
+/// The machine's native architecture, which an x64 build running emulated on ARM64
+/// can't tell from `std::env::consts::ARCH`.
+#[cfg(target_os = "windows")]
+fn windows_os_arch() -> &'static str {
+    use std::ffi::c_void;
 
-                let free = data["Free"].as_u64().unwrap_or(0);
-                let used = data["Used"].as_u64().unwrap_or(0);
-                let total = free + used;
-                let free_gb = (free as f64) / (1024.0 * 1024.0 * 1024.0);
-                let free_gb = (free_gb * 100.0).round() / 100.0;
+    extern "system" {
+        fn GetCurrentProcess() -> *mut c_void;
+        fn IsWow64Process2(process: *mut c_void, process_machine: *mut u16, native_machine: *mut u16) -> i32;
+    }
 
-                Ok(serde_json::json!({
-                    "free": free,
-                    "total": total,
-                    "freeGB": free_gb,
-                    "drive": format!("{}:", drive_letter),
-                    "path": path,
-                }))
-            }
-            Err(e) => Err(format!("Failed to check disk space: {}", e)),
-        }
+    const IMAGE_FILE_MACHINE_I386: u16 = 0x014c;
+    const IMAGE_FILE_MACHINE_AMD64: u16 = 0x8664;
+    const IMAGE_FILE_MACHINE_ARM64: u16 = 0xaa64;
+
+    let mut process_machine = 0u16;
+    let mut native_machine = 0u16;
+    let ok = unsafe { IsWow64Process2(GetCurrentProcess(), &mut process_machine, &mut native_machine) };
+    if ok == 0 {
+        return if cfg!(target_arch = "aarch64") { "arm64" } else { "x64" };
     }
 
-    #[cfg(target_os = "linux")]
-    {
-        use std::ffi::CString;
+    match native_machine {
+        IMAGE_FILE_MACHINE_ARM64 => "arm64",
+        IMAGE_FILE_MACHINE_AMD64 => "x64",
+        IMAGE_FILE_MACHINE_I386 => "x86",
+        _ => "unknown",
+    }
+}
 
-        let c_path = CString::new(path.clone())
-            .map_err(|_| "Invalid path".to_string())?;
+/// Check the extracted DepotDownloaderMod files against the embedded copies,
+/// re-extracting any that are missing or have the wrong size.
+/// Returns { directory, allOk, repaired, files: [{ name, expectedSize, foundSize, status, error }] }.
+#[command]
+pub async fn verify_downloader_files() -> Result<serde_json::Value, String> {
+    let files = embedded_tools::verify_extracted().await?;
+    let all_ok = files.iter().all(|f| f.status != "failed");
+    let repaired = files.iter().filter(|f| f.status == "repaired").count();
+
+    Ok(serde_json::json!({
+        "directory": embedded_tools::extraction_dir().to_string_lossy(),
+        "allOk": all_ok,
+        "repaired": repaired,
+        "files": files,
+    }))
+}
 
-        unsafe {
-            let mut stat: libc::statvfs = std::mem::zeroed();
-            let result = libc::statvfs(c_path.as_ptr(), &mut stat);
-            if result != 0 {
-                return Err("Failed to get filesystem stats".to_string());
-            }
+/// Stop a DepotDownloaderMod extraction that is taking too long. The extraction
+/// fails with a "cancelled" error and runs again the next time DDM is needed.
+#[command]
+pub async fn cancel_tool_extraction() -> Result<(), String> {
+    embedded_tools::cancel_extraction();
+    Ok(())
+}
 
-            let free = (stat.f_bavail as u64) * (stat.f_frsize as u64);
-            let total = (stat.f_blocks as u64) * (stat.f_frsize as u64);
-            let free_gb = (free as f64) / (1024.0 * 1024.0 * 1024.0);
-            let free_gb = (free_gb * 100.0).round() / 100.0;
-
-            Ok(serde_json::json!({
-                "free": free,
-                "total": total,
-                "freeGB": free_gb,
-                "drive": path.clone(),
-                "path": path,
-            }))
-        }
-    }
+/// Describe the bundled DepotDownloaderMod build for bug reports.
+/// Returns { appVersion, targetOs, targetArch, files: [{ name, size, sha256 }] }.
+#[command]
+pub async fn get_bundled_tool_manifest() -> Result<serde_json::Value, String> {
+    let files = tokio::task::spawn_blocking(embedded_tools::bundled_files)
+        .await
+        .map_err(|e| format!("Failed to hash bundled files: {}", e))?;
+
+    Ok(serde_json::json!({
+        "appVersion": env!("CARGO_PKG_VERSION"),
+        "targetOs": std::env::consts::OS,
+        "targetArch": std::env::consts::ARCH,
+        "files": files,
+    }))
+}
+
+/// Find the Steam library folders configured on this machine so they can be offered
+/// as download destinations. Returns an empty list if Steam isn't installed.
+#[command]
+pub async fn detect_steam_libraries() -> Result<Vec<steam_library::SteamLibrary>, String> {
+    tokio::task::spawn_blocking(steam_library::detect_libraries)
+        .await
+        .map_err(|e| format!("Library detection failed: {}", e))
+}
+
+/// Get disk space information for a given path.
+/// Checks the volume the path itself is on (mount points and junctions included),
+/// or its nearest existing parent if the folder doesn't exist yet.
+#[command]
+pub async fn get_disk_space(path: String) -> Result<serde_json::Value, String> {
+    let target = PathBuf::from(&path);
+    let (space, checked) = tokio::task::spawn_blocking(move || disk_space::query(&target))
+        .await
+        .map_err(|e| format!("Disk space task failed: {}", e))?
+        .ok_or_else(|| "Failed to check disk space".to_string())?;
+
+    Ok(serde_json::json!({
+        "free": space.free,
+        "total": space.total,
+        "freeGB": space.free_gb(),
+        "drive": checked.to_string_lossy(),
+        "path": path,
+    }))
 }
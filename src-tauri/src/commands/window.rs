@@ -1,4 +1,8 @@
-use tauri::command;
+use std::sync::atomic::Ordering;
+use tauri::{command, AppHandle};
+
+use crate::services::AppState;
+use super::download::cancel_all_jobs;
 
 #[command]
 pub async fn minimize_window(window: tauri::Window) -> Result<(), String> {
@@ -18,3 +22,19 @@ pub async fn maximize_window(window: tauri::Window) -> Result<(), String> {
 pub async fn close_window(window: tauri::Window) -> Result<(), String> {
     window.destroy().map_err(|e| e.to_string())
 }
+
+/// Quit after the user confirmed closing with downloads active.
+/// Cancels every active job (cleaning up partial files per `cancel_cleanup_mode` unless
+/// `delete_files` is false, same as cancelling a single download), then destroys the window
+/// past the close guard.
+#[command]
+pub async fn confirm_close_window(
+    app: AppHandle,
+    window: tauri::Window,
+    state: tauri::State<'_, AppState>,
+    delete_files: Option<bool>,
+) -> Result<(), String> {
+    state.close_confirmed.store(true, Ordering::SeqCst);
+    cancel_all_jobs(&app, &state, delete_files.unwrap_or(true)).await;
+    window.destroy().map_err(|e| e.to_string())
+}
@@ -15,8 +15,9 @@ fn main() {
             let app_data = app.path().app_data_dir().expect("Failed to get app data dir");
             std::fs::create_dir_all(&app_data).ok();
             
-            // Initialize services state
-            let state = services::AppState::new(app.handle().clone());
+            // Initialize services state (the HTTP client depends on the DoH setting)
+            let settings = tauri::async_runtime::block_on(services::settings::load_settings(&app_data));
+            let state = services::AppState::new(app.handle().clone(), &settings);
             app.manage(state);
 
             // On Windows, remove native decorations so the custom title bar is used.
@@ -35,37 +36,97 @@ fn main() {
             // File operations
             commands::parse_lua_file,
             commands::parse_lua_content,
+            commands::parse_st_content,
+            commands::validate_st_file,
+            commands::apply_key_vdf_to_selection,
+            commands::export_key_vdf,
+            commands::backup_keys,
+            commands::restore_keys,
+            commands::repair_steam_keys,
+            commands::parse_depot_table,
+            commands::scan_manifest_folder,
+            commands::open_folder,
+            commands::pick_manifest_files,
+            commands::hash_download,
+            commands::cancel_hashing,
             // Search
             commands::search_repos,
             commands::get_repo_manifests,
+            commands::diff_repo_manifests,
+            commands::get_repo_depot_coverage,
+            commands::find_any_repo_with_app,
+            commands::get_github_rate_limit,
+            commands::estimate_api_cost,
+            commands::get_depot_sizes,
+            commands::get_manifest_version_sizes,
+            commands::get_repo_freshness,
             commands::search_alternative,
+            commands::search_all_alternatives,
             // Steam
             commands::get_steam_app_info,
+            commands::app_requires_key,
+            commands::clear_steam_cache,
+            commands::clear_steam_cache_entry,
+            commands::lookup_manifest_steamdb,
+            commands::get_depot_requirements,
+            commands::get_language_depots,
+            commands::list_app_depots_via_ddm,
             // Download
             commands::start_download,
+            commands::resume_into_folder,
+            commands::validate_download_config,
+            commands::project_disk_space,
+            commands::preview_download_path,
             commands::cancel_download,
+            commands::abort_cleanup,
+            commands::normalize_manifest_id,
+            commands::test_manifest_download,
+            commands::get_job_output_tail,
+            commands::list_jobs,
+            commands::prune_jobs,
+            commands::get_depot_state,
+            commands::find_existing_download,
+            commands::check_folder_collision,
+            commands::clear_depot_state,
             commands::export_batch_script,
+            commands::validate_export,
             // Settings
             commands::get_settings,
             commands::save_settings,
+            commands::get_effective_settings,
+            commands::export_config_bundle,
+            commands::import_config_bundle,
             // System
             commands::check_dotnet,
+            commands::get_dotnet_install_url,
+            commands::verify_downloader_files,
+            commands::get_bundled_tool_manifest,
+            commands::cancel_tool_extraction,
+            commands::detect_steam_libraries,
             commands::get_disk_space,
             // Window
             commands::minimize_window,
             commands::maximize_window,
             commands::close_window,
+            commands::confirm_close_window,
         ])
         .on_window_event(|window, event| {
             if let tauri::WindowEvent::CloseRequested { api, .. } = event {
                 let state = window.state::<services::AppState>();
-                if state.has_active_downloads() {
+                let confirmed = state.close_confirmed.load(std::sync::atomic::Ordering::SeqCst);
+                if !confirmed && state.has_active_downloads() {
                     api.prevent_close();
                     let window = window.clone();
                     window.emit("close-requested", ()).ok();
                 }
             }
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Fires once, after the last window closed and exit is no longer preventable
+            if let tauri::RunEvent::Exit = event {
+                services::shutdown::flush_on_exit(app);
+            }
+        });
 }
@@ -1,5 +1,6 @@
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 
@@ -25,6 +26,30 @@ pub struct KernelOsResult {
     pub depots: Vec<DepotInfo>,
 }
 
+/// Longest a single source may take in `search_all_sources`, KernelOS zip download included.
+const SOURCE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// A depot reported by one or more alternative sources.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergedDepot {
+    pub depot_id: String,
+    pub manifest_id: Option<String>,
+    pub depot_key: Option<String>,
+    /// Every source that listed this depot
+    pub sources: Vec<String>,
+    /// The source `depot_key` came from
+    pub key_source: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllSourcesResult {
+    pub depots: Vec<MergedDepot>,
+    /// Source name -> error, for sources that failed or timed out
+    pub errors: HashMap<String, String>,
+    /// KernelOS result, kept so its extracted files can still be used
+    pub kernelos: Option<KernelOsResult>,
+}
+
 /// Download from PrintedWaste API.
 ///
 /// API: `GET https://gcore.api.printedwaste.com/app/{app_id}/depot`
@@ -100,6 +125,119 @@ pub async fn download_from_printed_waste(
     Ok(PrintedWasteResult { depots })
 }
 
+/// Query the given sources ("printedwaste", "kernelos") concurrently and merge their depots.
+///
+/// Depots are deduplicated by id. The first source in `sources` order that has a key
+/// (or manifest id) for a depot supplies it. A source that fails or takes longer than
+/// `SOURCE_TIMEOUT` is reported in `errors` without failing the others.
+pub async fn search_all_sources(
+    client: &Client,
+    app_id: &str,
+    sources: &[String],
+    kernelos_dir: &Path,
+) -> AllSourcesResult {
+    let wants = |name: &str| sources.iter().any(|s| s.eq_ignore_ascii_case(name));
+
+    let printed_waste = async {
+        if !wants("printedwaste") {
+            return None;
+        }
+        Some(
+            tokio::time::timeout(SOURCE_TIMEOUT, download_from_printed_waste(client, app_id))
+                .await
+                .unwrap_or_else(|_| Err("PrintedWaste timed out".to_string())),
+        )
+    };
+    let kernel_os = async {
+        if !wants("kernelos") {
+            return None;
+        }
+        Some(
+            tokio::time::timeout(SOURCE_TIMEOUT, download_from_kernel_os(client, app_id, kernelos_dir))
+                .await
+                .unwrap_or_else(|_| Err("KernelOS timed out".to_string())),
+        )
+    };
+    let (printed_waste, kernel_os) = tokio::join!(printed_waste, kernel_os);
+
+    // Both sources' depots in PrintedWaste's shape, by source name
+    let mut found: HashMap<&str, Vec<PrintedWasteDepot>> = HashMap::new();
+    let mut errors = HashMap::new();
+    let mut kernelos = None;
+
+    match printed_waste {
+        Some(Ok(result)) => {
+            found.insert("printedwaste", result.depots);
+        }
+        Some(Err(e)) => {
+            errors.insert("printedwaste".to_string(), e);
+        }
+        None => {}
+    }
+    match kernel_os {
+        Some(Ok(result)) => {
+            found.insert(
+                "kernelos",
+                result
+                    .depots
+                    .iter()
+                    .map(|d| PrintedWasteDepot {
+                        depot_id: d.depot_id.to_string(),
+                        manifest_id: d.manifest_id.clone(),
+                        depot_key: d.depot_key.clone(),
+                    })
+                    .collect(),
+            );
+            kernelos = Some(result);
+        }
+        Some(Err(e)) => {
+            errors.insert("kernelos".to_string(), e);
+        }
+        None => {}
+    }
+
+    let mut depots: Vec<MergedDepot> = Vec::new();
+    for source in sources {
+        let source = source.to_lowercase();
+        let Some(entries) = found.remove(source.as_str()) else {
+            continue;
+        };
+        for entry in entries {
+            let index = match depots.iter().position(|d| d.depot_id == entry.depot_id) {
+                Some(i) => i,
+                None => {
+                    depots.push(MergedDepot {
+                        depot_id: entry.depot_id,
+                        manifest_id: None,
+                        depot_key: None,
+                        sources: Vec::new(),
+                        key_source: None,
+                    });
+                    depots.len() - 1
+                }
+            };
+            let depot = &mut depots[index];
+            if !depot.sources.contains(&source) {
+                depot.sources.push(source.clone());
+            }
+            if depot.manifest_id.is_none() {
+                depot.manifest_id = entry.manifest_id;
+            }
+            if depot.depot_key.is_none() && entry.depot_key.is_some() {
+                depot.depot_key = entry.depot_key;
+                depot.key_source = Some(source.clone());
+            }
+        }
+    }
+    depots.sort_by_key(|d| d.depot_id.parse::<u64>().unwrap_or(u64::MAX));
+
+    AllSourcesResult {
+        depots,
+        errors,
+        kernelos,
+    }
+}
+
 /// Download from KernelOS and extract .lua and .st files.
 ///
 /// Step 1: `GET https://kernelosgithub.onrender.com/get_signed_url/{app_id}` → get signed URL
@@ -156,27 +294,28 @@ pub async fn download_from_kernel_os(
         ));
     }
 
-    let zip_bytes = zip_response
-        .bytes()
-        .await
-        .map_err(|e| format!("Failed to read KernelOS zip response: {}", e))?;
-
-    // Step 3: Create temp dir and extract zip
+    // Step 3: Stream the zip to a temp file, then extract from it, so a large
+    // archive is never held in memory
     let temp_dir = output_dir.join(format!("kernelos_{}", app_id));
     tokio::fs::create_dir_all(&temp_dir)
         .await
         .map_err(|e| format!("Failed to create temp directory: {}", e))?;
 
-    // Extract zip using zip crate (blocking, use spawn_blocking)
-    let zip_bytes_clone = zip_bytes.to_vec();
-    let temp_dir_clone = temp_dir.clone();
-
-    let extracted_files = tokio::task::spawn_blocking(move || {
-        extract_zip_files(&zip_bytes_clone, &temp_dir_clone)
-    })
-    .await
-    .map_err(|e| format!("Zip extraction task failed: {}", e))?
-    .map_err(|e| format!("Zip extraction failed: {}", e))?;
+    let zip_path = output_dir.join(format!("kernelos_{}.zip", app_id));
+    let extracted = async {
+        save_zip_response(zip_response, &zip_path).await?;
+
+        // Extract zip using zip crate (blocking, use spawn_blocking)
+        let zip_path_clone = zip_path.clone();
+        let temp_dir_clone = temp_dir.clone();
+        tokio::task::spawn_blocking(move || extract_zip_files(&zip_path_clone, &temp_dir_clone))
+            .await
+            .map_err(|e| format!("Zip extraction task failed: {}", e))?
+            .map_err(|e| format!("Zip extraction failed: {}", e))
+    }
+    .await;
+    let _ = tokio::fs::remove_file(&zip_path).await;
+    let extracted_files = extracted?;
 
     // Step 4 & 5: Find and parse .lua and .st files
     let mut all_depots: Vec<DepotInfo> = Vec::new();
@@ -236,13 +375,57 @@ pub async fn download_from_kernel_os(
     })
 }
 
-/// Extract .lua, .st, and .manifest files from a zip buffer to a target directory.
-fn extract_zip_files(zip_bytes: &[u8], target_dir: &Path) -> Result<Vec<PathBuf>, String> {
-    let cursor = std::io::Cursor::new(zip_bytes);
-    let mut archive =
-        zip::ZipArchive::new(cursor).map_err(|e| format!("Failed to open zip archive: {}", e))?;
+/// Largest KernelOS zip accepted; the bundles are a few manifests and scripts
+const MAX_KERNELOS_ZIP_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Largest single file extracted from a KernelOS zip
+const MAX_EXTRACTED_FILE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Most bytes extracted from one KernelOS zip in total
+const MAX_EXTRACTED_TOTAL_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Write a zip download to `path` chunk by chunk, stopping at `MAX_KERNELOS_ZIP_BYTES`.
+async fn save_zip_response(mut response: reqwest::Response, path: &Path) -> Result<(), String> {
+    use tokio::io::AsyncWriteExt;
+
+    if response.content_length().is_some_and(|len| len > MAX_KERNELOS_ZIP_BYTES) {
+        return Err(format!("KernelOS zip is larger than {} MB", MAX_KERNELOS_ZIP_BYTES / (1024 * 1024)));
+    }
+
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| format!("Failed to create temp zip file: {}", e))?;
+    let mut written: u64 = 0;
+
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to read KernelOS zip response: {}", e))?
+    {
+        written += chunk.len() as u64;
+        if written > MAX_KERNELOS_ZIP_BYTES {
+            return Err(format!("KernelOS zip is larger than {} MB", MAX_KERNELOS_ZIP_BYTES / (1024 * 1024)));
+        }
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("Failed to write temp zip file: {}", e))?;
+    }
+
+    file.flush()
+        .await
+        .map_err(|e| format!("Failed to write temp zip file: {}", e))
+}
+
+/// Extract .lua, .st, and .manifest files from a zip file to a target directory.
+/// Entries are copied straight to disk and capped at `MAX_EXTRACTED_FILE_BYTES` each and
+/// `MAX_EXTRACTED_TOTAL_BYTES` overall, whatever sizes the archive claims.
+fn extract_zip_files(zip_path: &Path, target_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let zip_file = std::fs::File::open(zip_path).map_err(|e| format!("Failed to open zip file: {}", e))?;
+    let mut archive = zip::ZipArchive::new(std::io::BufReader::new(zip_file))
+        .map_err(|e| format!("Failed to open zip archive: {}", e))?;
 
     let mut extracted_files = Vec::new();
+    let mut extracted_total: u64 = 0;
 
     for i in 0..archive.len() {
         let mut file = archive
@@ -272,12 +455,21 @@ fn extract_zip_files(zip_bytes: &[u8], target_dir: &Path) -> Result<Vec<PathBuf>
 
         let output_path = target_dir.join(&filename);
 
-        let mut data = Vec::new();
-        file.read_to_end(&mut data)
-            .map_err(|e| format!("Failed to read zip entry data: {}", e))?;
+        if file.size() > MAX_EXTRACTED_FILE_BYTES {
+            return Err(format!("Zip entry {} is too large ({} bytes)", name, file.size()));
+        }
 
-        std::fs::write(&output_path, &data)
+        let mut output = std::fs::File::create(&output_path)
             .map_err(|e| format!("Failed to write extracted file: {}", e))?;
+        // Read one byte past the cap so an entry that lies about its size is caught
+        let copied = std::io::copy(&mut (&mut file).take(MAX_EXTRACTED_FILE_BYTES + 1), &mut output)
+            .map_err(|e| format!("Failed to extract zip entry {}: {}", name, e))?;
+        extracted_total += copied;
+        if copied > MAX_EXTRACTED_FILE_BYTES || extracted_total > MAX_EXTRACTED_TOTAL_BYTES {
+            drop(output);
+            let _ = std::fs::remove_file(&output_path);
+            return Err(format!("Zip contents exceed the size limit at {}", name));
+        }
 
         extracted_files.push(output_path);
     }
@@ -0,0 +1,126 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Async writes started but not yet renamed into place, so shutdown can wait for them.
+static PENDING_WRITES: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a write as pending until dropped, also when the write panics.
+struct PendingWrite;
+
+impl PendingWrite {
+    fn start() -> Self {
+        PENDING_WRITES.fetch_add(1, Ordering::SeqCst);
+        PendingWrite
+    }
+}
+
+impl Drop for PendingWrite {
+    fn drop(&mut self) {
+        PENDING_WRITES.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Write `content` so readers only ever see the old or the new complete file: write a
+/// sibling temp file, flush it to disk, then rename it over `path`. Blocking.
+pub fn write_atomic(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let tmp = temp_path(path);
+
+    let result = (|| {
+        let mut file = std::fs::File::create(&tmp)?;
+        file.write_all(content)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Async version of `write_atomic`.
+pub async fn write_atomic_async(path: &Path, content: Vec<u8>) -> std::io::Result<()> {
+    let path = path.to_path_buf();
+    let pending = PendingWrite::start();
+    tokio::task::spawn_blocking(move || {
+        let _pending = pending;
+        write_atomic(&path, &content)
+    })
+        .await
+        .map_err(std::io::Error::other)?
+}
+
+/// Block until every `write_atomic_async` in flight has finished, or `timeout` passes.
+/// Returns how many writes were still pending when it gave up (0 when all finished).
+pub fn wait_for_pending_writes(timeout: Duration) -> usize {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let pending = PENDING_WRITES.load(Ordering::SeqCst);
+        if pending == 0 || Instant::now() >= deadline {
+            return pending;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// `settings.json` -> `settings.json.tmp`, in the same folder so the rename stays on one volume.
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("atomic-file-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn replaces_content() {
+        let dir = test_dir();
+        let path = dir.join("settings.json");
+        std::fs::write(&path, b"old").unwrap();
+
+        write_atomic(&path, b"new").unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        assert!(!temp_path(&path).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_rename_keeps_target_and_removes_temp_file() {
+        let dir = test_dir();
+        // A non-empty folder can't be replaced by a file
+        let path = dir.join("settings.json");
+        std::fs::create_dir(&path).unwrap();
+        std::fs::write(path.join("keep.txt"), b"original").unwrap();
+
+        assert!(write_atomic(&path, b"new").is_err());
+
+        assert_eq!(std::fs::read(path.join("keep.txt")).unwrap(), b"original");
+        assert!(!temp_path(&path).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_write_keeps_original_file() {
+        let dir = test_dir();
+        let path = dir.join("settings.json");
+        std::fs::write(&path, b"original").unwrap();
+        // The temp file can't be created where a folder already sits
+        std::fs::create_dir(temp_path(&path)).unwrap();
+
+        assert!(write_atomic(&path, b"new").is_err());
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"original");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,242 @@
+use serde::Serialize;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::services::atomic_file;
+use crate::services::settings::Settings;
+
+/// Name of the manifest entry describing the bundle.
+const BUNDLE_MANIFEST: &str = "bundle.json";
+
+/// Identifies our archives so an arbitrary zip isn't imported by mistake.
+const BUNDLE_FORMAT: &str = "steam-manifest-downloader-config";
+const BUNDLE_VERSION: u64 = 1;
+
+/// Config subfolders whose `*.json` files are carried along (e.g. saved profiles).
+const BUNDLE_SUBDIRS: &[&str] = &["profiles"];
+
+/// Top-level JSON keys blanked unless secrets are explicitly included.
+const SECRET_KEYS: &[&str] = &["github_token", "manifest_hub_api_key"];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportResult {
+    /// Files written to the config directory.
+    pub imported: Vec<String>,
+    /// Existing files that differ from the bundle; nothing is written while this is non-empty.
+    pub conflicts: Vec<String>,
+}
+
+/// Write every config file in `app_data_dir` into a zip at `dest`.
+/// Returns the relative names of the files that were bundled.
+pub fn export_bundle(app_data_dir: &Path, dest: &Path, include_secrets: bool) -> Result<Vec<String>, String> {
+    let files = collect_config_files(app_data_dir)?;
+
+    let out = std::fs::File::create(dest)
+        .map_err(|e| format!("Failed to create bundle file: {}", e))?;
+    let mut zip = zip::ZipWriter::new(out);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    let mut names = Vec::new();
+    for name in &files {
+        let content = std::fs::read_to_string(app_data_dir.join(name))
+            .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+        let mut value: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", name, e))?;
+        if !include_secrets {
+            redact_secrets(&mut value);
+        }
+        let content = serde_json::to_string_pretty(&value)
+            .map_err(|e| format!("Failed to serialize {}: {}", name, e))?;
+
+        zip.start_file(name.as_str(), options)
+            .map_err(|e| format!("Failed to add {} to bundle: {}", name, e))?;
+        zip.write_all(content.as_bytes())
+            .map_err(|e| format!("Failed to write {} to bundle: {}", name, e))?;
+        names.push(name.clone());
+    }
+
+    let manifest = serde_json::json!({
+        "format": BUNDLE_FORMAT,
+        "version": BUNDLE_VERSION,
+        "createdAt": chrono::Utc::now().to_rfc3339(),
+        "secretsIncluded": include_secrets,
+        "files": names,
+    });
+    zip.start_file(BUNDLE_MANIFEST, options)
+        .map_err(|e| format!("Failed to add bundle manifest: {}", e))?;
+    zip.write_all(manifest.to_string().as_bytes())
+        .map_err(|e| format!("Failed to write bundle manifest: {}", e))?;
+
+    zip.finish()
+        .map_err(|e| format!("Failed to finalize bundle: {}", e))?;
+
+    Ok(names)
+}
+
+/// Restore a bundle created by `export_bundle` into `app_data_dir`.
+///
+/// The whole archive is validated before anything is written. Unless `overwrite`
+/// is set, existing files with different content are reported as conflicts and
+/// the import is not applied. Redacted secrets keep their current local values.
+pub fn import_bundle(app_data_dir: &Path, src: &Path, overwrite: bool) -> Result<ImportResult, String> {
+    let file = std::fs::File::open(src)
+        .map_err(|e| format!("Failed to open bundle: {}", e))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| format!("Failed to open bundle archive: {}", e))?;
+
+    let manifest: serde_json::Value = serde_json::from_str(&read_entry(&mut archive, BUNDLE_MANIFEST)?)
+        .map_err(|e| format!("Invalid bundle manifest: {}", e))?;
+    if manifest["format"].as_str() != Some(BUNDLE_FORMAT) {
+        return Err("Not a Steam Manifest Downloader config bundle".to_string());
+    }
+    if manifest["version"].as_u64().unwrap_or(0) > BUNDLE_VERSION {
+        return Err("Bundle was created by a newer version of the app".to_string());
+    }
+    let secrets_included = manifest["secretsIncluded"].as_bool().unwrap_or(false);
+
+    let names: Vec<String> = manifest["files"]
+        .as_array()
+        .ok_or_else(|| "Bundle manifest has no file list".to_string())?
+        .iter()
+        .filter_map(|v| v.as_str().map(|s| s.to_string()))
+        .collect();
+
+    // Validate every entry first so a bad bundle never leaves config half-restored
+    let mut entries: Vec<(String, String)> = Vec::new();
+    for name in names {
+        if !is_safe_entry_name(&name) {
+            return Err(format!("Bundle contains an invalid entry: {}", name));
+        }
+
+        let mut value: serde_json::Value = serde_json::from_str(&read_entry(&mut archive, &name)?)
+            .map_err(|e| format!("Bundle entry {} is not valid JSON: {}", name, e))?;
+
+        let target = app_data_dir.join(&name);
+        if !secrets_included {
+            restore_local_secrets(&mut value, &target);
+        }
+
+        if name == "settings.json" {
+            let settings: Settings = serde_json::from_value(value.clone())
+                .map_err(|e| format!("Bundled settings are invalid: {}", e))?;
+            settings.validate()?;
+        }
+
+        let content = serde_json::to_string_pretty(&value)
+            .map_err(|e| format!("Failed to serialize {}: {}", name, e))?;
+        entries.push((name, content));
+    }
+
+    let conflicts: Vec<String> = entries
+        .iter()
+        .filter(|(name, content)| {
+            std::fs::read_to_string(app_data_dir.join(name))
+                .map(|existing| existing != *content)
+                .unwrap_or(false)
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    if !conflicts.is_empty() && !overwrite {
+        return Ok(ImportResult {
+            imported: Vec::new(),
+            conflicts,
+        });
+    }
+
+    let mut imported = Vec::new();
+    for (name, content) in entries {
+        let target = app_data_dir.join(&name);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+        }
+        atomic_file::write_atomic(&target, content.as_bytes())
+            .map_err(|e| format!("Failed to write {}: {}", name, e))?;
+        imported.push(name);
+    }
+
+    Ok(ImportResult { imported, conflicts })
+}
+
+/// Relative names of all bundled config files: top-level `*.json` plus known subfolders.
+fn collect_config_files(app_data_dir: &Path) -> Result<Vec<String>, String> {
+    let mut files = json_files_in(app_data_dir)
+        .map_err(|e| format!("Failed to read config directory: {}", e))?
+        .into_iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+
+    for subdir in BUNDLE_SUBDIRS {
+        if let Ok(names) = json_files_in(&app_data_dir.join(subdir)) {
+            files.extend(names.into_iter().map(|name| format!("{}/{}", subdir, name)));
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+fn json_files_in(dir: &Path) -> std::io::Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let path: PathBuf = entry.path();
+        if path.is_file() && path.extension().and_then(|e| e.to_str()) == Some("json") {
+            names.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    Ok(names)
+}
+
+fn read_entry<R: Read + std::io::Seek>(archive: &mut zip::ZipArchive<R>, name: &str) -> Result<String, String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|_| format!("Bundle is missing {}", name))?;
+    let mut content = String::new();
+    entry
+        .read_to_string(&mut content)
+        .map_err(|e| format!("Failed to read {} from bundle: {}", name, e))?;
+    Ok(content)
+}
+
+/// Only plain `name.json` or `{known subdir}/name.json` entries may be restored.
+fn is_safe_entry_name(name: &str) -> bool {
+    if !name.ends_with(".json") || name == BUNDLE_MANIFEST {
+        return false;
+    }
+    let parts: Vec<&str> = name.split('/').collect();
+    let valid_part = |p: &str| !p.is_empty() && p != "." && p != ".." && !p.contains('\\') && !p.contains(':');
+    match parts.as_slice() {
+        [file] => valid_part(file),
+        [dir, file] => BUNDLE_SUBDIRS.contains(dir) && valid_part(file),
+        _ => false,
+    }
+}
+
+fn redact_secrets(value: &mut serde_json::Value) {
+    if let Some(obj) = value.as_object_mut() {
+        for key in SECRET_KEYS {
+            if let Some(secret) = obj.get_mut(*key) {
+                *secret = serde_json::Value::String(String::new());
+            }
+        }
+    }
+}
+
+/// Put back secrets from the existing local file in place of redacted (empty) values.
+fn restore_local_secrets(value: &mut serde_json::Value, existing_path: &Path) {
+    let existing: Option<serde_json::Value> = std::fs::read_to_string(existing_path)
+        .ok()
+        .and_then(|c| serde_json::from_str(&c).ok());
+    let (Some(obj), Some(existing)) = (value.as_object_mut(), existing) else {
+        return;
+    };
+
+    for key in SECRET_KEYS {
+        let redacted = obj.get(*key).and_then(|v| v.as_str()) == Some("");
+        if let (true, Some(local)) = (redacted, existing.get(*key)) {
+            obj.insert(key.to_string(), local.clone());
+        }
+    }
+}
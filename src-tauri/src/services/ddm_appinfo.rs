@@ -0,0 +1,181 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+use crate::services::public_appinfo::AppDepot;
+use crate::services::steam_store_api;
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+/// DDM gets app info from Steam within seconds; anything past this is spent on manifests we don't need.
+const DDM_LISTING_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// How long a depot list read through DDM is reused. Starting DDM and logging in is slow.
+const DDM_CACHE_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Maximum cached apps before the cache is cleared
+const MAX_DDM_CACHE_SIZE: usize = 100;
+
+static DDM_DEPOT_CACHE: Mutex<BTreeMap<String, (Instant, Vec<AppDepot>)>> = Mutex::const_new(BTreeMap::new());
+
+/// Read the depots DDM processes for an app from its console output.
+///
+/// DDM logs `Processing depot {id}` for each depot it picks from the app info, and
+/// `App {app}, Depot {id} has depotfromapp of {other}!` for depots shared from another app,
+/// which are left out like `public_appinfo::list_app_depots` does. Returns `None` when the
+/// output shows DDM never got the app info.
+pub fn parse_depot_listing(app_id: &str, output: &str) -> Option<Vec<AppDepot>> {
+    let mut got_app_info = false;
+    let mut processed: BTreeSet<u64> = BTreeSet::new();
+    let mut shared: BTreeSet<u64> = BTreeSet::new();
+
+    for line in output.lines().map(str::trim) {
+        if line == format!("Got AppInfo for {}", app_id) {
+            got_app_info = true;
+        } else if let Some(id) = line.strip_prefix("Processing depot ") {
+            if let Ok(id) = id.trim().parse() {
+                processed.insert(id);
+            }
+        } else if line.starts_with("App ") && line.contains("has depotfromapp of") {
+            let id = line
+                .split("Depot ")
+                .nth(1)
+                .and_then(|rest| rest.split_whitespace().next())
+                .and_then(|id| id.parse().ok());
+            if let Some(id) = id {
+                shared.insert(id);
+            }
+        }
+    }
+
+    if !got_app_info && processed.is_empty() {
+        return None;
+    }
+
+    Some(
+        processed
+            .difference(&shared)
+            .map(|id| AppDepot {
+                depot_id: id.to_string(),
+                // DDM doesn't print depot configs
+                language: None,
+                oslist: None,
+                dlc_app_id: None,
+                optional: false,
+            })
+            .collect(),
+    )
+}
+
+/// List an app's depots by running DDM anonymously in manifest-only mode, for when
+/// the store and appinfo mirrors have nothing. Only depot ids are known this way.
+/// Results are cached for `DDM_CACHE_TTL` unless `refresh` is set.
+pub async fn list_app_depots_via_ddm(
+    exe_path: &Path,
+    dotnet_path: Option<&Path>,
+    app_id: &str,
+    refresh: bool,
+) -> Result<Vec<AppDepot>, String> {
+    if !steam_store_api::is_valid_app_id(app_id) {
+        return Err(format!("Invalid App ID: {}", app_id));
+    }
+
+    if !refresh {
+        let mut cache = DDM_DEPOT_CACHE.lock().await;
+        if cache.len() > MAX_DDM_CACHE_SIZE {
+            cache.clear();
+        }
+        if let Some((at, depots)) = cache.get(app_id) {
+            if at.elapsed() < DDM_CACHE_TTL {
+                return Ok(depots.clone());
+            }
+        }
+    }
+
+    let output = run_manifest_only(exe_path, dotnet_path, app_id).await?;
+    let depots = parse_depot_listing(app_id, &output)
+        .ok_or_else(|| format!("DepotDownloaderMod could not get app info for App ID {}", app_id))?;
+
+    DDM_DEPOT_CACHE
+        .lock()
+        .await
+        .insert(app_id.to_string(), (Instant::now(), depots.clone()));
+    Ok(depots)
+}
+
+/// Run `DDM -app {id} -manifest-only` for every platform, language and architecture
+/// in a scratch directory and return its stdout. Stops DDM after `DDM_LISTING_TIMEOUT`.
+async fn run_manifest_only(exe_path: &Path, dotnet_path: Option<&Path>, app_id: &str) -> Result<String, String> {
+    let scratch = std::env::temp_dir()
+        .join("SteamManifestDownloader")
+        .join("appinfo")
+        .join(app_id);
+    tokio::fs::create_dir_all(&scratch)
+        .await
+        .map_err(|e| format!("Failed to create scratch directory: {}", e))?;
+
+    // With a custom dotnet on Windows, launch the DLL through it instead of the apphost
+    #[cfg(target_os = "windows")]
+    let (program, mut args) = match dotnet_path {
+        Some(dotnet) => (
+            dotnet.to_path_buf(),
+            vec![exe_path.with_file_name("DepotDownloaderMod.dll").to_string_lossy().to_string()],
+        ),
+        None => (exe_path.to_path_buf(), Vec::new()),
+    };
+    #[cfg(target_os = "linux")]
+    let (program, mut args) = {
+        let _ = dotnet_path;
+        (exe_path.to_path_buf(), Vec::new())
+    };
+
+    args.extend([
+        "-app".to_string(),
+        app_id.to_string(),
+        "-manifest-only".to_string(),
+        "-all-platforms".to_string(),
+        "-all-languages".to_string(),
+        "-all-archs".to_string(),
+        "-dir".to_string(),
+        scratch.to_string_lossy().to_string(),
+    ]);
+
+    let mut cmd = Command::new(&program);
+    cmd.args(&args)
+        .current_dir(&scratch)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true);
+
+    // CREATE_NO_WINDOW on Windows
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(0x08000000);
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to start DepotDownloaderMod: {}", e))?;
+
+    let mut output = String::new();
+    if let Some(stdout) = child.stdout.take() {
+        let mut lines = BufReader::new(stdout).lines();
+        let read_all = async {
+            while let Ok(Some(line)) = lines.next_line().await {
+                output.push_str(&line);
+                output.push('\n');
+            }
+        };
+        if tokio::time::timeout(DDM_LISTING_TIMEOUT, read_all).await.is_err() {
+            eprintln!("[DdmAppInfo] DepotDownloaderMod still running for App ID {} after {:?}, stopping it", app_id, DDM_LISTING_TIMEOUT);
+        }
+    }
+    let _ = child.kill().await;
+
+    let _ = tokio::fs::remove_dir_all(&scratch).await;
+    Ok(output)
+}
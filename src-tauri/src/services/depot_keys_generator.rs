@@ -8,6 +8,129 @@ use crate::services::lua_parser::DepotInfo;
 pub struct DepotKeysResult {
     pub output_path: String,
     pub depot_count: usize,
+    /// Keys left out of the file: (depot_id, reason)
+    pub rejected_keys: Vec<(u64, String)>,
+    /// Depots that got a line in the file
+    pub keyed_depots: Vec<u64>,
+}
+
+/// Normalize a depot key to lowercase and check it is exactly 64 hex characters
+/// (a 32-byte AES key). DDM fails obscurely on anything else.
+pub fn normalize_depot_key(key: &str) -> Result<String, String> {
+    let key = key.trim().to_lowercase();
+    if key.len() != 64 {
+        return Err(format!("expected 64 hex characters, got {}", key.len()));
+    }
+    if !key.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("contains non-hex characters".to_string());
+    }
+    Ok(key)
+}
+
+/// Build `depotId;hexKey` lines for every depot with a valid key,
+/// collecting the invalid ones with the reason they were skipped.
+fn build_key_lines(depots: &[DepotInfo]) -> (Vec<String>, Vec<(u64, String)>) {
+    let mut lines = Vec::new();
+    let mut rejected = Vec::new();
+
+    for depot in depots {
+        if let Some(ref key) = depot.depot_key {
+            match normalize_depot_key(key) {
+                Ok(normalized) => lines.push(format!("{};{}", depot.depot_id, normalized)),
+                Err(reason) => rejected.push((depot.depot_id, reason)),
+            }
+        }
+    }
+
+    (lines, rejected)
+}
+
+/// A `steam.keys` line `repair_key_lines` left out.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DroppedKeyLine {
+    /// 1-based line number in the original file
+    #[serde(rename = "lineNumber")]
+    pub line_number: usize,
+    pub line: String,
+    pub reason: String,
+}
+
+/// Outcome of `repair_key_lines`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct KeysRepair {
+    /// The cleaned file, `depotId;hexKey` per line
+    #[serde(skip)]
+    pub content: String,
+    pub kept: usize,
+    /// Line numbers of kept lines that had to be rewritten (case, spacing or separator)
+    pub normalized: Vec<usize>,
+    pub dropped: Vec<DroppedKeyLine>,
+}
+
+/// Clean up a `steam.keys` file: keep `depotId;hexKey` lines with a numeric depot and a valid
+/// key, accepting `,`, tabs or spaces as the separator. Malformed lines are dropped, and so is
+/// any repeat of a depot, which keeps its first key. Blank lines are removed silently.
+pub fn repair_key_lines(content: &str) -> KeysRepair {
+    let mut lines: Vec<String> = Vec::new();
+    let mut seen: Vec<(String, String)> = Vec::new();
+    let mut normalized = Vec::new();
+    let mut dropped = Vec::new();
+
+    for (index, raw) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let trimmed = raw.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let mut drop = |reason: String| {
+            dropped.push(DroppedKeyLine {
+                line_number,
+                line: trimmed.to_string(),
+                reason,
+            })
+        };
+
+        let Some((depot_id, key)) = trimmed
+            .split_once(';')
+            .or_else(|| trimmed.split_once(','))
+            .or_else(|| trimmed.split_once(char::is_whitespace))
+        else {
+            drop("no separator between depot id and key".to_string());
+            continue;
+        };
+        let depot_id = depot_id.trim();
+        if depot_id.is_empty() || !depot_id.chars().all(|c| c.is_ascii_digit()) {
+            drop(format!("invalid depot id '{}'", depot_id));
+            continue;
+        }
+        let key = match normalize_depot_key(key) {
+            Ok(key) => key,
+            Err(reason) => {
+                drop(format!("invalid key: {}", reason));
+                continue;
+            }
+        };
+
+        match seen.iter().find(|(id, _)| id == depot_id) {
+            Some((_, first)) if *first == key => drop(format!("duplicate of depot {}", depot_id)),
+            Some(_) => drop(format!("second key for depot {}; the first one is kept", depot_id)),
+            None => {
+                let line = format!("{};{}", depot_id, key);
+                if line != trimmed {
+                    normalized.push(line_number);
+                }
+                seen.push((depot_id.to_string(), key));
+                lines.push(line);
+            }
+        }
+    }
+
+    KeysRepair {
+        content: if lines.is_empty() { String::from("\n") } else { lines.join("\n") + "\n" },
+        kept: lines.len(),
+        normalized,
+        dropped,
+    }
 }
 
 /// Generate `steam.keys` file content in format: `depotId;hexKey\n`
@@ -36,15 +159,7 @@ pub async fn generate_depot_keys(
         .map_err(|e| format!("Failed to create output directory: {}", e))?;
 
     // Build file content: depotId;hexKey per line
-    let lines: Vec<String> = depots
-        .iter()
-        .filter_map(|depot| {
-            depot
-                .depot_key
-                .as_ref()
-                .map(|key| format!("{};{}", depot.depot_id, key))
-        })
-        .collect();
+    let (lines, rejected_keys) = build_key_lines(depots);
 
     let content = if lines.is_empty() {
         String::from("\n")
@@ -56,24 +171,23 @@ pub async fn generate_depot_keys(
         .await
         .map_err(|e| format!("Failed to write steam.keys: {}", e))?;
 
+    let keyed_depots = lines
+        .iter()
+        .filter_map(|line| line.split(';').next()?.parse().ok())
+        .collect();
+
     Ok(DepotKeysResult {
         output_path: output_path.to_string_lossy().to_string(),
         depot_count: lines.len(),
+        rejected_keys,
+        keyed_depots,
     })
 }
 
 /// Generate depot keys content as a string without writing to file.
 #[allow(dead_code)]
 pub fn generate_depot_keys_content(depots: &[DepotInfo]) -> String {
-    let lines: Vec<String> = depots
-        .iter()
-        .filter_map(|depot| {
-            depot
-                .depot_key
-                .as_ref()
-                .map(|key| format!("{};{}", depot.depot_id, key))
-        })
-        .collect();
+    let (lines, _) = build_key_lines(depots);
 
     if lines.is_empty() {
         String::from("\n")
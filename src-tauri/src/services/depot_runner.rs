@@ -1,11 +1,13 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Manager};
 
-#[cfg(target_os = "windows")]
-use std::sync::Arc;
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
 
@@ -185,6 +187,24 @@ pub struct ProgressEvent {
     pub filename: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none", rename = "manifestId")]
     pub manifest_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub succeeded: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failed: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    /// Where a stage got its data, e.g. the depot key source
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Why a job was cancelled (`CANCEL_REASONS`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Depot download progress (0-100) parsed from the output in this event
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub percent: Option<f64>,
+    /// Folder a paused job can be resumed in
+    #[serde(skip_serializing_if = "Option::is_none", rename = "downloadDir")]
+    pub download_dir: Option<String>,
 }
 
 impl ProgressEvent {
@@ -208,22 +228,236 @@ impl ProgressEvent {
             drive: None,
             filename: None,
             manifest_id: None,
+            succeeded: None,
+            failed: None,
+            repo: None,
+            source: None,
+            reason: None,
+            percent: None,
+            download_dir: None,
         }
     }
 }
 
 /// Emit a progress event to the frontend.
+/// Also appends it to the job's NDJSON progress log when one is registered.
 pub fn emit_progress(app: &AppHandle, event: &ProgressEvent) {
     if let Err(e) = app.emit("download-progress", event) {
         eprintln!("[DepotRunner] Failed to emit progress event: {}", e);
     }
+    append_progress_log(app, event);
+    record_job_progress(app, event);
+}
+
+/// Keep the job's latest non-output event as its progress snapshot.
+fn record_job_progress(app: &AppHandle, event: &ProgressEvent) {
+    if event.event_type == "output" {
+        return;
+    }
+    let Some(state) = app.try_state::<AppState>() else {
+        return;
+    };
+    let Ok(mut progress) = state.job_progress.lock() else {
+        return;
+    };
+    progress.insert(event.job_id.clone(), event.clone());
+}
+
+/// Drop a job's progress snapshot once the job itself is dropped.
+pub fn forget_job_progress(job_progress: &std::sync::Mutex<HashMap<String, ProgressEvent>>, job_id: &str) {
+    if let Ok(mut progress) = job_progress.lock() {
+        progress.remove(job_id);
+    }
+}
+
+/// Start mirroring a job's progress events into `log_path` (one JSON object per line).
+pub fn set_progress_log(state: &AppState, job_id: &str, log_path: PathBuf) {
+    if let Ok(mut logs) = state.progress_logs.lock() {
+        logs.insert(job_id.to_string(), log_path);
+    }
+}
+
+/// Stop mirroring a job's progress events.
+pub fn clear_progress_log(state: &AppState, job_id: &str) {
+    if let Ok(mut logs) = state.progress_logs.lock() {
+        logs.remove(job_id);
+    }
+}
+
+/// On app exit, note in every still-open progress log that its job was interrupted,
+/// then stop mirroring. Returns how many logs were closed.
+pub fn close_progress_logs(state: &AppState) -> usize {
+    let logs: Vec<(String, PathBuf)> = match state.progress_logs.lock() {
+        Ok(mut logs) => logs.drain().collect(),
+        Err(_) => return 0,
+    };
+
+    for (job_id, path) in &logs {
+        let mut event = ProgressEvent::new("app_exit", job_id);
+        event.message = Some("The app exited while this job was running".to_string());
+        write_progress_line(path, &event);
+    }
+    logs.len()
+}
+
+/// Best-effort append of an event to its job's progress log.
+/// The file is opened per write so no handle is held while the job runs.
+fn append_progress_log(app: &AppHandle, event: &ProgressEvent) {
+    let state = match app.try_state::<AppState>() {
+        Some(s) => s,
+        None => return,
+    };
+
+    let log_path = match state.progress_logs.lock() {
+        Ok(logs) => logs.get(&event.job_id).cloned(),
+        Err(_) => None,
+    };
+
+    if let Some(path) = log_path {
+        write_progress_line(&path, event);
+    }
+}
+
+fn write_progress_line(path: &Path, event: &ProgressEvent) {
+    let line = match serde_json::to_string(event) {
+        Ok(l) => l,
+        Err(_) => return,
+    };
+    let result = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    if let Err(e) = result {
+        eprintln!("[DepotRunner] Failed to write progress log {:?}: {}", path, e);
+    }
 }
 
 /// Depot configuration for running DepotDownloaderMod.
 #[derive(Debug, Clone)]
 pub struct DepotRunConfig {
     pub depot_id: String,
-    pub manifest_id: String,
+    /// `None` (with no `manifest_path`) lets DDM download the depot's latest manifest
+    pub manifest_id: Option<String>,
+    /// Manifest file as actually saved, passed to `-manifestfile`
+    pub manifest_path: Option<PathBuf>,
+    /// Only download these files (DDM `-filelist` entries, `regex:` prefixes allowed)
+    pub file_list: Option<Vec<String>>,
+    /// The depot has a key in `steam.keys`; otherwise DDM downloads it anonymously
+    pub used_key: bool,
+}
+
+/// Settings that control how DepotDownloaderMod is launched for a job.
+#[derive(Debug, Clone)]
+pub struct RunnerOptions {
+    /// Arguments appended after the per-depot ones (e.g. `-max-downloads 8`).
+    pub extra_args: Vec<String>,
+    /// Run `dotnet DepotDownloaderMod.dll` with this dotnet instead of the `.exe` (Windows only).
+    #[cfg_attr(target_os = "linux", allow(dead_code))]
+    pub dotnet_path: Option<PathBuf>,
+    /// How long output lines are collected before they are sent as one event.
+    pub output_throttle: Duration,
+    /// Send buffered output early once this many lines are waiting.
+    pub output_buffer_lines: usize,
+    /// `-os` value; `None` lets DDM pick the host OS.
+    pub target_os: Option<String>,
+    /// `-osarch` value ("32" or "64"); `None` lets DDM pick the host architecture.
+    pub target_arch: Option<String>,
+}
+
+impl Default for RunnerOptions {
+    fn default() -> Self {
+        Self {
+            extra_args: Vec::new(),
+            dotnet_path: None,
+            output_throttle: Duration::from_millis(150),
+            output_buffer_lines: 50,
+            target_os: None,
+            target_arch: None,
+        }
+    }
+}
+
+/// Upper bound on output events per run, across stdout and stderr together, so a DDM
+/// flooding its output can't swamp the event bus whatever the throttle settings are.
+const MAX_OUTPUT_EVENTS_PER_SECOND: u32 = 20;
+
+/// Shared by a run's stdout and stderr readers to enforce `MAX_OUTPUT_EVENTS_PER_SECOND`.
+#[derive(Clone)]
+struct OutputRateLimit {
+    last_event: Arc<std::sync::Mutex<Option<tokio::time::Instant>>>,
+}
+
+impl OutputRateLimit {
+    fn new() -> Self {
+        Self { last_event: Arc::new(std::sync::Mutex::new(None)) }
+    }
+
+    /// Whether an event may be sent now; claims the slot if so.
+    fn try_acquire(&self) -> bool {
+        let min_gap = Duration::from_secs(1) / MAX_OUTPUT_EVENTS_PER_SECOND;
+        let now = tokio::time::Instant::now();
+        let Ok(mut last) = self.last_event.lock() else {
+            return true;
+        };
+        match *last {
+            Some(prev) if now.duration_since(prev) < min_gap => false,
+            _ => {
+                *last = Some(now);
+                true
+            }
+        }
+    }
+}
+
+/// Outcome of one DepotDownloaderMod run.
+#[derive(Debug, Clone)]
+pub struct DepotRunStats {
+    pub success: bool,
+    /// Compressed bytes DDM reports fetching (its "Depot N - Downloaded X bytes" line)
+    pub bytes_downloaded: Option<u64>,
+    pub duration_secs: f64,
+}
+
+impl DepotRunStats {
+    pub fn avg_speed_bps(&self) -> Option<u64> {
+        let bytes = self.bytes_downloaded?;
+        (self.duration_secs > 0.0).then(|| (bytes as f64 / self.duration_secs) as u64)
+    }
+}
+
+/// Pull the byte count out of DDM's per-depot summary line:
+/// `Depot 228990 - Downloaded 123456 bytes (234567 bytes uncompressed)`.
+fn parse_downloaded_bytes(line: &str) -> Option<u64> {
+    let (_, rest) = line.trim().split_once(" - Downloaded ")?;
+    let (bytes, _) = rest.split_once(" bytes")?;
+    bytes.trim().parse().ok()
+}
+
+/// Byte-based depot progress from a DDM output line: DDM prefixes each finished file
+/// with the share of the depot's bytes done so far, e.g. `01.83% depots\...`.
+fn parse_byte_percent(line: &str) -> Option<f64> {
+    let (number, _) = line.trim_start().split_once('%')?;
+    let whole = number.split('.').next().unwrap_or_default();
+    if whole.is_empty() || whole.len() > 3 || !whole.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    number.parse::<f64>().ok().map(|p| p.min(100.0))
+}
+
+/// Chunk-based depot progress from a line carrying `done/total` chunk counts,
+/// e.g. `Downloaded 120/480 chunks`, for output without byte percentages.
+fn parse_chunk_percent(line: &str) -> Option<f64> {
+    if !line.to_ascii_lowercase().contains("chunk") {
+        return None;
+    }
+    line.split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | '[' | ']' | ',' | ':'))
+        .find_map(|token| {
+            let (done, total) = token.split_once('/')?;
+            let done: u64 = done.parse().ok()?;
+            let total: u64 = total.parse().ok()?;
+            (total > 0 && done <= total).then(|| done as f64 * 100.0 / total as f64)
+        })
 }
 
 /// Platform-specific executable name for display purposes.
@@ -234,18 +468,17 @@ const DDM_DISPLAY_NAME: &str = "DepotDownloaderMod";
 
 /// Run DepotDownloaderMod for a single depot. Streams stdout/stderr to frontend.
 ///
-/// Returns Ok(true) if the process exited with code 0, Ok(false) if non-zero.
+/// `success` is whether the process exited with code 0.
 pub async fn run_depot_downloader(
     app: &AppHandle,
     exe_path: &Path,
     app_id: &str,
     depot: &DepotRunConfig,
     work_dir: &Path,
-    extra_args: &[String],
+    options: &RunnerOptions,
     job_id: &str,
     state: &AppState,
-) -> Result<bool, String> {
-    let manifest_file = format!("{}_{}.manifest", depot.depot_id, depot.manifest_id);
+) -> Result<DepotRunStats, String> {
     let keys_file = "steam.keys";
 
     let mut args = vec![
@@ -253,18 +486,58 @@ pub async fn run_depot_downloader(
         app_id.to_string(),
         "-depot".to_string(),
         depot.depot_id.clone(),
-        "-manifest".to_string(),
-        depot.manifest_id.clone(),
-        "-depotkeys".to_string(),
-        keys_file.to_string(),
-        "-manifestfile".to_string(),
-        manifest_file,
     ];
-    args.extend_from_slice(extra_args);
+    if let Some(ref manifest_id) = depot.manifest_id {
+        args.push("-manifest".to_string());
+        args.push(manifest_id.clone());
+    }
+    args.push("-depotkeys".to_string());
+    args.push(keys_file.to_string());
+    if let Some(ref manifest_path) = depot.manifest_path {
+        // Relative to the work dir (DDM's cwd) when possible, to keep the logged command short
+        let manifest_file = manifest_path.strip_prefix(work_dir).unwrap_or(manifest_path);
+        args.push("-manifestfile".to_string());
+        args.push(manifest_file.to_string_lossy().to_string());
+    }
+    // DDM reads the file list from disk, one entry per line
+    let file_list_path = match depot.file_list {
+        Some(ref files) => {
+            let name = format!("{}_filelist.txt", depot.depot_id);
+            let path = work_dir.join(&name);
+            tokio::fs::write(&path, files.join("\n"))
+                .await
+                .map_err(|e| format!("Failed to write file list for depot {}: {}", depot.depot_id, e))?;
+            args.push("-filelist".to_string());
+            args.push(name);
+            Some(path)
+        }
+        None => None,
+    };
+    if let Some(ref os) = options.target_os {
+        args.push("-os".to_string());
+        args.push(os.clone());
+    }
+    if let Some(ref arch) = options.target_arch {
+        args.push("-osarch".to_string());
+        args.push(arch.clone());
+    }
+    args.extend_from_slice(&options.extra_args);
+
+    // With a custom dotnet on Windows, launch the DLL through it instead of the apphost
+    #[cfg(target_os = "windows")]
+    let (program, display_name) = match options.dotnet_path {
+        Some(ref dotnet) => {
+            args.insert(0, exe_path.with_file_name("DepotDownloaderMod.dll").to_string_lossy().to_string());
+            (dotnet.clone(), "dotnet DepotDownloaderMod.dll")
+        }
+        None => (exe_path.to_path_buf(), DDM_DISPLAY_NAME),
+    };
+    #[cfg(target_os = "linux")]
+    let (program, display_name) = (exe_path.to_path_buf(), DDM_DISPLAY_NAME);
 
     let command_display = format!(
         "{} {}",
-        DDM_DISPLAY_NAME,
+        display_name,
         args.join(" ")
     );
 
@@ -280,9 +553,10 @@ pub async fn run_depot_downloader(
     let job_object = win_job::JobObject::new().map(Arc::new);
 
     // Spawn the process
-    // On Windows: run via exe directly (dotnet-dependent app with .exe entry point)
+    // On Windows: run via exe directly (dotnet-dependent app with .exe entry point),
+    //             or via `dotnet DepotDownloaderMod.dll` when a dotnet path is configured
     // On Linux: run the self-contained binary directly
-    let mut cmd = Command::new(exe_path);
+    let mut cmd = Command::new(&program);
     cmd.args(&args)
         .current_dir(work_dir)
         .stdout(Stdio::piped())
@@ -300,33 +574,45 @@ pub async fn run_depot_downloader(
         cmd.process_group(0);
     }
 
+    let started = tokio::time::Instant::now();
     let mut child = cmd.spawn()
         .map_err(|e| format!("Failed to start DepotDownloaderMod for depot {}: {}", depot.depot_id, e))?;
 
     // Track the PID and assign to Job Object
-    if let Some(pid) = child.id() {
-        #[cfg(target_os = "windows")]
-        if let Some(ref jo) = job_object {
-            jo.assign_process(pid);
-        }
+    let pid = child.id();
+    #[cfg(target_os = "windows")]
+    if let (Some(pid), Some(jo)) = (pid, &job_object) {
+        jo.assign_process(pid);
+    }
 
+    // The output readers push to the job's tail directly, not through `active_jobs`
+    let output_tail = {
         let mut jobs = state.active_jobs.lock().await;
-        if let Some(job) = jobs.get_mut(job_id) {
-            job.child_pid = Some(pid);
-            #[cfg(target_os = "windows")]
-            {
-                job.job_object = job_object.clone();
+        jobs.get_mut(job_id).map(|job| {
+            if pid.is_some() {
+                job.child_pid = pid;
+                #[cfg(target_os = "windows")]
+                {
+                    job.job_object = job_object.clone();
+                }
             }
-        }
-    }
+            job.output_tail.clone()
+        })
+    };
 
     // Stream stdout with throttling
     let stdout = child.stdout.take();
     let stderr = child.stderr.take();
 
+    let rate_limit = OutputRateLimit::new();
+    let throttle_interval = options.output_throttle;
+    let buffer_lines = options.output_buffer_lines.max(1);
+
     let app_stdout = app.clone();
     let job_id_stdout = job_id.to_string();
     let depot_id_stdout = depot.depot_id.clone();
+    let output_tail_stdout = output_tail.clone();
+    let rate_limit_stdout = rate_limit.clone();
 
     let stdout_handle = tokio::spawn(async move {
         if let Some(stdout) = stdout {
@@ -334,18 +620,37 @@ pub async fn run_depot_downloader(
             let mut lines = reader.lines();
             let mut last_emit = tokio::time::Instant::now();
             let mut buffer: Vec<String> = Vec::new();
-            let throttle_interval = tokio::time::Duration::from_millis(150);
+            let mut downloaded = None;
+            // Latest progress not yet sent; chunk counts only until DDM prints byte percentages
+            let mut percent: Option<f64> = None;
+            let mut byte_percent_seen = false;
 
             while let Ok(Some(line)) = lines.next_line().await {
+                if let Some(bytes) = parse_downloaded_bytes(&line) {
+                    downloaded = Some(bytes);
+                }
+                if let Some(p) = parse_byte_percent(&line) {
+                    percent = Some(p);
+                    byte_percent_seen = true;
+                } else if !byte_percent_seen {
+                    if let Some(p) = parse_chunk_percent(&line) {
+                        percent = Some(p);
+                    }
+                }
                 buffer.push(line);
 
                 let now = tokio::time::Instant::now();
-                if now.duration_since(last_emit) >= throttle_interval || buffer.len() >= 50 {
+                let due = now.duration_since(last_emit) >= throttle_interval || buffer.len() >= buffer_lines;
+                if due && rate_limit_stdout.try_acquire() {
+                    if let Some(ref tail) = output_tail_stdout {
+                        tail.push(&buffer);
+                    }
                     let combined = buffer.join("\n");
                     let mut event = ProgressEvent::new("output", &job_id_stdout);
                     event.depot_id = Some(depot_id_stdout.clone());
                     event.stream = Some("stdout".to_string());
                     event.output = Some(combined);
+                    event.percent = percent.take();
                     emit_progress(&app_stdout, &event);
                     buffer.clear();
                     last_emit = now;
@@ -354,19 +659,28 @@ pub async fn run_depot_downloader(
 
             // Emit remaining buffered lines
             if !buffer.is_empty() {
+                if let Some(ref tail) = output_tail_stdout {
+                    tail.push(&buffer);
+                }
                 let combined = buffer.join("\n");
                 let mut event = ProgressEvent::new("output", &job_id_stdout);
                 event.depot_id = Some(depot_id_stdout.clone());
                 event.stream = Some("stdout".to_string());
                 event.output = Some(combined);
+                event.percent = percent;
                 emit_progress(&app_stdout, &event);
             }
+            downloaded
+        } else {
+            None
         }
     });
 
     let app_stderr = app.clone();
     let job_id_stderr = job_id.to_string();
     let depot_id_stderr = depot.depot_id.clone();
+    let output_tail_stderr = output_tail;
+    let rate_limit_stderr = rate_limit;
 
     let stderr_handle = tokio::spawn(async move {
         if let Some(stderr) = stderr {
@@ -374,13 +688,16 @@ pub async fn run_depot_downloader(
             let mut lines = reader.lines();
             let mut last_emit = tokio::time::Instant::now();
             let mut buffer: Vec<String> = Vec::new();
-            let throttle_interval = tokio::time::Duration::from_millis(150);
 
             while let Ok(Some(line)) = lines.next_line().await {
                 buffer.push(line);
 
                 let now = tokio::time::Instant::now();
-                if now.duration_since(last_emit) >= throttle_interval || buffer.len() >= 50 {
+                let due = now.duration_since(last_emit) >= throttle_interval || buffer.len() >= buffer_lines;
+                if due && rate_limit_stderr.try_acquire() {
+                    if let Some(ref tail) = output_tail_stderr {
+                        tail.push(&buffer);
+                    }
                     let combined = buffer.join("\n");
                     let mut event = ProgressEvent::new("output", &job_id_stderr);
                     event.depot_id = Some(depot_id_stderr.clone());
@@ -394,6 +711,9 @@ pub async fn run_depot_downloader(
 
             // Emit remaining buffered lines
             if !buffer.is_empty() {
+                if let Some(ref tail) = output_tail_stderr {
+                    tail.push(&buffer);
+                }
                 let combined = buffer.join("\n");
                 let mut event = ProgressEvent::new("output", &job_id_stderr);
                 event.depot_id = Some(depot_id_stderr.clone());
@@ -410,8 +730,14 @@ pub async fn run_depot_downloader(
         .await
         .map_err(|e| format!("Failed to wait for DepotDownloaderMod: {}", e))?;
 
+    let duration_secs = started.elapsed().as_secs_f64();
+
+    if let Some(path) = file_list_path {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+
     // Wait for stream readers to finish
-    let _ = stdout_handle.await;
+    let bytes_downloaded = stdout_handle.await.ok().flatten();
     let _ = stderr_handle.await;
 
     // Clear the PID and job object
@@ -426,7 +752,11 @@ pub async fn run_depot_downloader(
         }
     }
 
-    Ok(status.success())
+    Ok(DepotRunStats {
+        success: status.success(),
+        bytes_downloaded,
+        duration_secs,
+    })
 }
 
 /// Run DepotDownloaderMod for all depots sequentially.
@@ -437,7 +767,7 @@ pub async fn run_all_depots(
     app_id: &str,
     depots: &[DepotRunConfig],
     work_dir: &Path,
-    extra_args: &[String],
+    options: &RunnerOptions,
     job_id: &str,
     state: &AppState,
 ) -> Result<Vec<serde_json::Value>, String> {
@@ -450,9 +780,11 @@ pub async fn run_all_depots(
             let jobs = state.active_jobs.lock().await;
             if let Some(job) = jobs.get(job_id) {
                 if job.status == "cancelled" {
-                    let mut event = ProgressEvent::new("cancelled", job_id);
-                    event.message = Some("Download cancelled by user.".to_string());
-                    emit_progress(app, &event);
+                    emit_progress(app, &cancelled_event(job_id, job.cancel_reason.as_deref()));
+                    break;
+                }
+                // Whoever paused the job has already reported why
+                if job.status == "paused" {
                     break;
                 }
             }
@@ -466,14 +798,19 @@ pub async fn run_all_depots(
         event.total = Some(total);
         emit_progress(app, &event);
 
-        match run_depot_downloader(app, exe_path, app_id, depot, work_dir, extra_args, job_id, state).await {
-            Ok(success) => {
+        match run_depot_downloader(app, exe_path, app_id, depot, work_dir, options, job_id, state).await {
+            Ok(stats) => {
+                let success = stats.success;
                 results.push(serde_json::json!({
                     "depotId": depot.depot_id,
                     "success": success,
+                    "usedKey": depot.used_key,
                     "error": if success { serde_json::Value::Null } else {
                         serde_json::Value::String(format!("DepotDownloader exited with non-zero code for depot {}", depot.depot_id))
-                    }
+                    },
+                    "bytesDownloaded": stats.bytes_downloaded,
+                    "durationSecs": (stats.duration_secs * 10.0).round() / 10.0,
+                    "avgSpeedBps": stats.avg_speed_bps(),
                 }));
 
                 let mut event = ProgressEvent::new("depot_complete", job_id);
@@ -488,9 +825,10 @@ pub async fn run_all_depots(
                     let jobs = state.active_jobs.lock().await;
                     if let Some(job) = jobs.get(job_id) {
                         if job.status == "cancelled" {
-                            let mut event = ProgressEvent::new("cancelled", job_id);
-                            event.message = Some("Download cancelled by user.".to_string());
-                            emit_progress(app, &event);
+                            emit_progress(app, &cancelled_event(job_id, job.cancel_reason.as_deref()));
+                            break;
+                        }
+                        if job.status == "paused" {
                             break;
                         }
                     }
@@ -499,6 +837,7 @@ pub async fn run_all_depots(
                 results.push(serde_json::json!({
                     "depotId": depot.depot_id,
                     "success": false,
+                    "usedKey": depot.used_key,
                     "error": e
                 }));
 
@@ -513,10 +852,40 @@ pub async fn run_all_depots(
     Ok(results)
 }
 
-/// Kill the active process for a job.
+/// A "cancelled" event for a job, its message saying why.
+pub fn cancelled_event(job_id: &str, reason: Option<&str>) -> ProgressEvent {
+    let mut event = ProgressEvent::new("cancelled", job_id);
+    event.message = Some(
+        match reason {
+            Some("shutdown") => "Download cancelled because the app is closing.",
+            Some("error") => "Download cancelled after an error.",
+            _ => "Download cancelled by user.",
+        }
+        .to_string(),
+    );
+    event.reason = reason.map(String::from);
+    event
+}
+
+/// Mark a job cancelled for `reason` (one of `CANCEL_REASONS`) without touching its process.
+pub async fn mark_cancelled(state: &AppState, job_id: &str, reason: &str) {
+    if let Some(job) = state.active_jobs.lock().await.get_mut(job_id) {
+        job.status = "cancelled".to_string();
+        job.cancel_reason = Some(reason.to_string());
+    }
+}
+
+/// Kill the active process for a job, recording why it was cancelled.
 /// On Windows: terminates via Job Object, then falls back to taskkill.
 /// On Linux: kills the entire process group via SIGKILL.
-pub async fn kill_job(state: &AppState, job_id: &str) -> bool {
+pub async fn kill_job(state: &AppState, job_id: &str, reason: &str) -> bool {
+    mark_cancelled(state, job_id, reason).await;
+    stop_job(state, job_id, "cancelled").await
+}
+
+/// Kill the active process for a job, marking it with `status` first.
+/// `"paused"` stops the job but leaves its files for DDM to resume from.
+pub async fn stop_job(state: &AppState, job_id: &str, status: &str) -> bool {
     let mut pid = None;
     #[cfg(target_os = "windows")]
     let mut job_object_opt: Option<Arc<win_job::JobObject>> = None;
@@ -524,7 +893,7 @@ pub async fn kill_job(state: &AppState, job_id: &str) -> bool {
     {
         let mut jobs = state.active_jobs.lock().await;
         if let Some(job) = jobs.get_mut(job_id) {
-            job.status = "cancelled".to_string();
+            job.status = status.to_string();
             pid = job.child_pid.take();
             #[cfg(target_os = "windows")]
             {
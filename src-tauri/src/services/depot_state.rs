@@ -0,0 +1,381 @@
+use flate2::read::DeflateDecoder;
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::services::manifest_downloader;
+use crate::services::manifest_parser::{self, DepotManifest, ManifestChunk, ProtoReader, Value};
+
+/// Folder DDM keeps its resume state in, inside each install dir.
+pub(crate) const STATE_DIR_NAME: &str = ".DepotDownloader";
+
+/// How much of a depot is already on disk.
+#[derive(Debug, Clone, Serialize)]
+pub struct DepotState {
+    #[serde(rename = "depotId")]
+    pub depot_id: String,
+    #[serde(rename = "manifestId")]
+    pub manifest_id: String,
+    #[serde(rename = "installDir")]
+    pub install_dir: String,
+    /// Manifest DDM recorded as fully installed, if any
+    #[serde(rename = "installedManifestId")]
+    pub installed_manifest_id: Option<String>,
+    #[serde(rename = "totalChunks")]
+    pub total_chunks: usize,
+    #[serde(rename = "completeChunks")]
+    pub complete_chunks: usize,
+    #[serde(rename = "totalBytes")]
+    pub total_bytes: u64,
+    #[serde(rename = "completeBytes")]
+    pub complete_bytes: u64,
+    pub percent: f64,
+}
+
+/// Work out how complete a depot download in `work_dir` is.
+///
+/// Uses the depot's manifest file in the work dir (named by `pattern`, the
+/// `manifest_filename_pattern` setting) as the list of chunks and checks each chunk
+/// on disk by SHA-1, since DDM pre-allocates files at full size.
+/// Returns `None` when the depot has no manifest or no DDM state yet.
+pub fn read_depot_state(work_dir: &Path, depot_id: &str, pattern: &str) -> Result<Option<DepotState>, String> {
+    let Some((manifest, install_dir)) = load_depot(work_dir, depot_id, pattern)? else {
+        return Ok(None);
+    };
+
+    let installed = read_installed_manifests(&install_dir.join(STATE_DIR_NAME).join("depot.config"));
+    let installed_manifest_id = depot_id
+        .parse::<u32>()
+        .ok()
+        .and_then(|id| installed.get(&id).copied());
+
+    let total_chunks = manifest.total_chunks();
+    let total_bytes = manifest.cb_disk_original;
+
+    // DDM only records a manifest once the depot finished, so trust it without hashing
+    let (complete_chunks, complete_bytes) = if installed_manifest_id == Some(manifest.manifest_id) {
+        (total_chunks, total_bytes)
+    } else {
+        count_complete_chunks(&manifest, &install_dir)
+    };
+
+    let percent = if total_bytes > 0 {
+        ((complete_bytes as f64 / total_bytes as f64) * 10000.0).round() / 100.0
+    } else if total_chunks > 0 {
+        ((complete_chunks as f64 / total_chunks as f64) * 10000.0).round() / 100.0
+    } else {
+        100.0
+    };
+
+    Ok(Some(DepotState {
+        depot_id: depot_id.to_string(),
+        manifest_id: manifest.manifest_id.to_string(),
+        install_dir: install_dir.to_string_lossy().to_string(),
+        installed_manifest_id: installed_manifest_id.map(|id| id.to_string()),
+        total_chunks,
+        complete_chunks,
+        total_bytes,
+        complete_bytes,
+        percent,
+    }))
+}
+
+/// Files of a downloaded depot that don't match its manifest.
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyReport {
+    #[serde(rename = "depotId")]
+    pub depot_id: String,
+    #[serde(rename = "checkedFiles")]
+    pub checked_files: usize,
+    #[serde(rename = "missingFiles")]
+    pub missing_files: Vec<String>,
+    /// Files with the wrong size or at least one chunk whose SHA-1 doesn't match
+    #[serde(rename = "corruptFiles")]
+    pub corrupt_files: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn is_clean(&self) -> bool {
+        self.missing_files.is_empty() && self.corrupt_files.is_empty()
+    }
+}
+
+/// Check every file of a finished depot against its manifest without changing anything.
+/// Unlike `read_depot_state` this always hashes, even if DDM recorded the depot as installed.
+pub fn verify_depot(work_dir: &Path, depot_id: &str, pattern: &str) -> Result<Option<VerifyReport>, String> {
+    let Some((manifest, install_dir)) = load_depot(work_dir, depot_id, pattern)? else {
+        return Ok(None);
+    };
+
+    let mut report = VerifyReport {
+        depot_id: depot_id.to_string(),
+        checked_files: 0,
+        missing_files: Vec::new(),
+        corrupt_files: Vec::new(),
+    };
+    let mut buffer = Vec::new();
+
+    for file in manifest.files.iter().filter(|f| !f.is_directory()) {
+        report.checked_files += 1;
+        let path = resolve_file_path(&install_dir, &file.filename);
+
+        let Ok(mut handle) = std::fs::File::open(&path) else {
+            report.missing_files.push(file.filename.clone());
+            continue;
+        };
+
+        let size_ok = handle.metadata().map(|m| m.len() == file.size).unwrap_or(false);
+        let chunks_ok = file
+            .chunks
+            .iter()
+            .all(|chunk| chunk_matches(&mut handle, chunk, &mut buffer));
+        if !size_ok || !chunks_ok {
+            report.corrupt_files.push(file.filename.clone());
+        }
+    }
+
+    Ok(Some(report))
+}
+
+/// Parse the depot's manifest (decrypting names) and find where DDM put its files.
+fn load_depot(work_dir: &Path, depot_id: &str, pattern: &str) -> Result<Option<(DepotManifest, PathBuf)>, String> {
+    let Some((manifest_path, _)) = find_manifest_file(work_dir, depot_id, pattern) else {
+        return Ok(None);
+    };
+    let Some(install_dir) = find_install_dir(work_dir, depot_id) else {
+        return Ok(None);
+    };
+
+    let data = std::fs::read(&manifest_path)
+        .map_err(|e| format!("Failed to read manifest {:?}: {}", manifest_path, e))?;
+    let mut manifest = manifest_parser::parse_manifest(&data)?;
+
+    if manifest.filenames_encrypted {
+        let key = read_depot_key(work_dir, depot_id)
+            .ok_or_else(|| format!("Manifest filenames are encrypted and no key for depot {} is in steam.keys", depot_id))?;
+        manifest.decrypt_filenames(&key)?;
+    }
+
+    Ok(Some((manifest, install_dir)))
+}
+
+/// Delete DDM's resume state for a depot in `work_dir` so its next run starts clean.
+///
+/// A per-depot install dir (`depots/{id}/{build}`) loses its whole state folder. When DDM
+/// shares the work dir's state folder between depots, only the depot's own `{id}_*` files
+/// and `depot.config` go; the other depots then get re-checked instead of trusted.
+/// Returns the removed paths (empty when there was no state).
+pub fn clear_depot_state(work_dir: &Path, depot_id: &str) -> Result<Vec<PathBuf>, String> {
+    let Some(install_dir) = find_install_dir(work_dir, depot_id) else {
+        return Ok(Vec::new());
+    };
+    let state_dir = install_dir.join(STATE_DIR_NAME);
+
+    // Never delete anything outside the work dir, whatever symlinks are in the way
+    let root = work_dir
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve work dir: {}", e))?;
+    let resolved = state_dir
+        .canonicalize()
+        .map_err(|e| format!("Failed to resolve DDM state dir: {}", e))?;
+    if !resolved.starts_with(&root) {
+        return Err(format!("DDM state dir {} is outside the work dir", resolved.display()));
+    }
+
+    if install_dir != work_dir {
+        std::fs::remove_dir_all(&resolved)
+            .map_err(|e| format!("Failed to delete DDM state: {}", e))?;
+        return Ok(vec![resolved]);
+    }
+
+    let prefix = format!("{}_", depot_id);
+    let mut removed = Vec::new();
+    let entries = std::fs::read_dir(&resolved)
+        .map_err(|e| format!("Failed to read DDM state dir: {}", e))?;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with(&prefix) && name != "depot.config" {
+            continue;
+        }
+        let path = entry.path();
+        let result = if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+        result.map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+        removed.push(path);
+    }
+    Ok(removed)
+}
+
+/// A depot found in a download folder.
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadedDepot {
+    #[serde(rename = "depotId")]
+    pub depot_id: String,
+    #[serde(rename = "manifestId")]
+    pub manifest_id: String,
+    /// DDM recorded this manifest as fully installed
+    pub installed: bool,
+}
+
+/// Depots that have a manifest file named by `pattern` in `work_dir`, newest manifest per depot,
+/// and whether DDM finished each. Only reads DDM's `depot.config`, never the downloaded files,
+/// so it stays quick on large installs.
+pub fn list_downloaded_depots(work_dir: &Path, pattern: &str) -> Vec<DownloadedDepot> {
+    let mut depot_ids: Vec<String> = std::fs::read_dir(work_dir)
+        .into_iter()
+        .flat_map(|entries| entries.flatten())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            manifest_downloader::parse_manifest_filename(pattern, &name).map(|(depot_id, _)| depot_id)
+        })
+        .collect();
+    depot_ids.sort_by_key(|id| id.parse::<u64>().unwrap_or(u64::MAX));
+    depot_ids.dedup();
+
+    depot_ids
+        .into_iter()
+        .filter_map(|depot_id| {
+            let (_, manifest_id) = find_manifest_file(work_dir, &depot_id, pattern)?;
+
+            let installed = find_install_dir(work_dir, &depot_id)
+                .map(|dir| read_installed_manifests(&dir.join(STATE_DIR_NAME).join("depot.config")))
+                .and_then(|installed| installed.get(&depot_id.parse::<u32>().ok()?).copied())
+                .is_some_and(|id| id.to_string() == manifest_id);
+
+            Some(DownloadedDepot {
+                depot_id,
+                manifest_id,
+                installed,
+            })
+        })
+        .collect()
+}
+
+/// Newest manifest file for `depot_id` named by `pattern` in the work dir, with its manifest id.
+fn find_manifest_file(work_dir: &Path, depot_id: &str, pattern: &str) -> Option<(PathBuf, String)> {
+    std::fs::read_dir(work_dir)
+        .ok()?
+        .flatten()
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            let (depot, manifest_id) = manifest_downloader::parse_manifest_filename(pattern, &name)?;
+            (depot == depot_id).then_some((e, manifest_id))
+        })
+        .max_by_key(|(e, _)| e.metadata().and_then(|m| m.modified()).ok())
+        .map(|(e, manifest_id)| (e.path(), manifest_id))
+}
+
+/// DDM installs to `depots/{depot_id}/{build}` under its working dir unless `-dir` is given,
+/// in which case the state folder sits in the work dir itself.
+fn find_install_dir(work_dir: &Path, depot_id: &str) -> Option<PathBuf> {
+    let depot_root = work_dir.join("depots").join(depot_id);
+    let nested = std::fs::read_dir(&depot_root)
+        .ok()
+        .into_iter()
+        .flat_map(|entries| entries.flatten())
+        .map(|e| e.path())
+        .filter(|p| p.join(STATE_DIR_NAME).is_dir())
+        .max_by_key(|p| p.join(STATE_DIR_NAME).metadata().and_then(|m| m.modified()).ok());
+
+    nested.or_else(|| {
+        if work_dir.join(STATE_DIR_NAME).is_dir() {
+            Some(work_dir.to_path_buf())
+        } else {
+            None
+        }
+    })
+}
+
+/// Key for a depot from the `steam.keys` file the pipeline writes (`depotId;hexKey` lines).
+fn read_depot_key(work_dir: &Path, depot_id: &str) -> Option<String> {
+    let content = std::fs::read_to_string(work_dir.join("steam.keys")).ok()?;
+    content.lines().find_map(|line| {
+        let (id, key) = line.trim().split_once(';')?;
+        (id.trim() == depot_id).then(|| key.trim().to_string())
+    })
+}
+
+/// Read DDM's `depot.config`: a deflated protobuf map of depot id -> installed manifest id.
+/// Anything unreadable is treated as "nothing installed".
+fn read_installed_manifests(path: &Path) -> HashMap<u32, u64> {
+    let mut installed = HashMap::new();
+
+    let Ok(compressed) = std::fs::read(path) else {
+        return installed;
+    };
+    let mut data = Vec::new();
+    if DeflateDecoder::new(&compressed[..]).read_to_end(&mut data).is_err() {
+        return installed;
+    }
+
+    for field in ProtoReader::new(&data) {
+        let Ok((1, Value::Bytes(entry))) = field else {
+            continue;
+        };
+        let mut key = None;
+        let mut value = None;
+        for inner in ProtoReader::new(entry).flatten() {
+            match inner {
+                (1, Value::Varint(v)) => key = Some(v as u32),
+                (2, Value::Varint(v)) => value = Some(v),
+                _ => {}
+            }
+        }
+        if let (Some(k), Some(v)) = (key, value) {
+            installed.insert(k, v);
+        }
+    }
+
+    installed
+}
+
+/// Count chunks whose bytes on disk match the manifest's SHA-1.
+fn count_complete_chunks(manifest: &DepotManifest, install_dir: &Path) -> (usize, u64) {
+    let mut chunks = 0;
+    let mut bytes = 0;
+    let mut buffer = Vec::new();
+
+    for file in manifest.files.iter().filter(|f| !f.is_directory()) {
+        let path = resolve_file_path(install_dir, &file.filename);
+
+        let Ok(mut handle) = std::fs::File::open(&path) else {
+            continue;
+        };
+
+        for chunk in &file.chunks {
+            if chunk_matches(&mut handle, chunk, &mut buffer) {
+                chunks += 1;
+                bytes += chunk.cb_original as u64;
+            }
+        }
+    }
+
+    (chunks, bytes)
+}
+
+/// Manifest path (always `/`-separated) under the install dir, ignoring `..` components.
+fn resolve_file_path(install_dir: &Path, filename: &str) -> PathBuf {
+    filename
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != "..")
+        .fold(install_dir.to_path_buf(), |p, part| p.join(part))
+}
+
+/// Whether the chunk's bytes in the file hash to the manifest's SHA-1.
+fn chunk_matches(handle: &mut std::fs::File, chunk: &ManifestChunk, buffer: &mut Vec<u8>) -> bool {
+    buffer.resize(chunk.cb_original as usize, 0);
+    let read_ok = handle.seek(SeekFrom::Start(chunk.offset)).is_ok()
+        && handle.read_exact(buffer).is_ok();
+    if !read_ok {
+        return false;
+    }
+
+    let digest = Sha1::digest(&buffer[..]);
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    hex == chunk.sha
+}
@@ -0,0 +1,126 @@
+use regex::Regex;
+use serde::Serialize;
+
+/// One depot row read from a pasted table.
+#[derive(Debug, Clone, Serialize)]
+pub struct DepotTableRow {
+    pub depot_id: String,
+    pub name: Option<String>,
+    pub manifest_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DepotTableResult {
+    pub rows: Vec<DepotTableRow>,
+    /// Non-empty lines that looked like data but had no depot id
+    pub unparsed: Vec<String>,
+}
+
+/// Parse a depot table copied from SteamDB (or similar): rows with a depot id, usually a
+/// name and, on the manifests view, a manifest id. Columns may be tab- or space-separated.
+/// Header rows are skipped; a depot listed twice keeps its first row, filling in a missing
+/// name or manifest id from later ones.
+pub fn parse_depot_table(text: &str) -> DepotTableResult {
+    let patterns = Patterns {
+        // Sizes as SteamDB prints them ("1.23 GiB", "512 MB"), which are never depot names
+        size: Regex::new(r"(?i)^\d+(?:[.,]\d+)?\s*(?:[KMGT]i?B|B|bytes)$").unwrap(),
+        // Column gap when a row has no tabs
+        columns: Regex::new(r"\s{2,}").unwrap(),
+    };
+    let mut rows: Vec<DepotTableRow> = Vec::new();
+    let mut unparsed = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match parse_row(line, &patterns) {
+            Some(row) => match rows.iter_mut().find(|r| r.depot_id == row.depot_id) {
+                Some(existing) => {
+                    existing.name = existing.name.take().or(row.name);
+                    existing.manifest_id = existing.manifest_id.take().or(row.manifest_id);
+                }
+                None => rows.push(row),
+            },
+            // Header and caption rows have no long numbers; anything else is worth reporting
+            None if has_number(line, 3) => unparsed.push(line.to_string()),
+            None => {}
+        }
+    }
+
+    DepotTableResult { rows, unparsed }
+}
+
+struct Patterns {
+    size: Regex,
+    columns: Regex,
+}
+
+fn parse_row(line: &str, patterns: &Patterns) -> Option<DepotTableRow> {
+    let fields: Vec<&str> = if line.contains('\t') {
+        line.split('\t').map(str::trim).filter(|f| !f.is_empty()).collect()
+    } else {
+        let columns: Vec<&str> = patterns.columns.split(line).collect();
+        if columns.len() > 1 {
+            columns
+        } else {
+            // Single-spaced: depot id, then the name words up to the next number
+            return parse_single_spaced(line);
+        }
+    };
+
+    let (first, rest) = fields.split_first()?;
+    let depot_id = depot_id(first)?;
+    let manifest_id = rest.iter().find_map(|f| manifest_id(f));
+    let name = rest
+        .iter()
+        .find(|f| f.chars().any(char::is_alphabetic) && !patterns.size.is_match(f))
+        .map(|f| f.to_string());
+
+    Some(DepotTableRow { depot_id, name, manifest_id })
+}
+
+fn parse_single_spaced(line: &str) -> Option<DepotTableRow> {
+    let mut tokens = line.split_whitespace();
+    let depot_id = depot_id(tokens.next()?)?;
+
+    let rest: Vec<&str> = tokens.collect();
+    let manifest_id = rest.iter().find_map(|t| manifest_id(t));
+    let name_words: Vec<&str> = rest
+        .iter()
+        .take_while(|t| !t.chars().all(|c| c.is_ascii_digit()))
+        .copied()
+        .collect();
+    let name = Some(name_words.join(" ")).filter(|n| !n.is_empty());
+
+    Some(DepotTableRow { depot_id, name, manifest_id })
+}
+
+/// Depot ids are u32s; SteamDB never shows them shorter than 3 digits.
+fn depot_id(field: &str) -> Option<String> {
+    let field = field.trim();
+    if field.len() < 3 || !field.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    field.parse::<u32>().ok().map(|id| id.to_string())
+}
+
+/// Manifest ids are u64s, in practice 15+ digits (possibly with digit-group separators).
+fn manifest_id(field: &str) -> Option<String> {
+    let digits: String = field
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, ',' | '_' | '\''))
+        .collect();
+    if digits.len() < 15 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse::<u64>().ok().map(|id| id.to_string())
+}
+
+fn has_number(line: &str, min_digits: usize) -> bool {
+    line.split(|c: char| !c.is_ascii_digit())
+        .any(|run| run.len() >= min_digits)
+}
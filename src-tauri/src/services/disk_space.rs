@@ -0,0 +1,72 @@
+use std::path::{Path, PathBuf};
+
+/// Free and total bytes of the volume a path lives on.
+#[derive(Debug, Clone, Copy)]
+pub struct DiskSpace {
+    /// Bytes available to this user (quotas and reserved blocks excluded)
+    pub free: u64,
+    pub total: u64,
+}
+
+impl DiskSpace {
+    pub fn free_gb(&self) -> f64 {
+        let gb = (self.free as f64) / (1024.0 * 1024.0 * 1024.0);
+        (gb * 100.0).round() / 100.0
+    }
+}
+
+/// Query the volume that actually holds `path`, following mount points, junctions and
+/// symlinks, rather than the drive root. A path that doesn't exist yet is checked at its
+/// nearest existing parent. Returns the space and the path that was queried.
+pub fn query(path: &Path) -> Option<(DiskSpace, PathBuf)> {
+    let existing = path.ancestors().find(|p| !p.as_os_str().is_empty() && p.exists())?;
+    query_existing(existing).map(|space| (space, existing.to_path_buf()))
+}
+
+#[cfg(target_os = "windows")]
+fn query_existing(path: &Path) -> Option<DiskSpace> {
+    use std::os::windows::ffi::OsStrExt;
+
+    extern "system" {
+        fn GetDiskFreeSpaceExW(
+            lp_directory_name: *const u16,
+            lp_free_bytes_available_to_caller: *mut u64,
+            lp_total_number_of_bytes: *mut u64,
+            lp_total_number_of_free_bytes: *mut u64,
+        ) -> i32;
+    }
+
+    // UNC paths need the trailing separator; it's harmless for the rest
+    let mut wide: Vec<u16> = path.as_os_str().encode_wide().collect();
+    if wide.last() != Some(&(b'\\' as u16)) {
+        wide.push(b'\\' as u16);
+    }
+    wide.push(0);
+
+    let mut free = 0u64;
+    let mut total = 0u64;
+    let ok = unsafe { GetDiskFreeSpaceExW(wide.as_ptr(), &mut free, &mut total, std::ptr::null_mut()) };
+    if ok == 0 {
+        return None;
+    }
+    Some(DiskSpace { free, total })
+}
+
+#[cfg(target_os = "linux")]
+fn query_existing(path: &Path) -> Option<DiskSpace> {
+    use std::ffi::CString;
+
+    let c_path = CString::new(path.to_string_lossy().as_ref()).ok()?;
+
+    unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(c_path.as_ptr(), &mut stat) != 0 {
+            return None;
+        }
+
+        Some(DiskSpace {
+            free: (stat.f_bavail as u64) * (stat.f_frsize as u64),
+            total: (stat.f_blocks as u64) * (stat.f_frsize as u64),
+        })
+    }
+}
@@ -0,0 +1,156 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// DoH JSON endpoints, tried in order. Addressed by IP so that looking them up
+/// never goes through the (possibly tampered) local resolver.
+const DOH_ENDPOINTS: &[&str] = &[
+    "https://1.1.1.1/dns-query",
+    "https://8.8.8.8/resolve",
+];
+
+/// How long a DoH answer is reused before asking again.
+const CACHE_TTL: Duration = Duration::from_secs(300);
+
+/// Build the shared HTTP client, optionally resolving hostnames over DNS-over-HTTPS.
+///
+/// DoH costs one extra HTTPS round-trip for each new host (cached for 5 minutes),
+/// so it is only worth enabling where local DNS is poisoned. Proxies configured via
+/// `HTTP(S)_PROXY` keep working: only the proxy host is resolved here, and the proxy
+/// resolves the target itself.
+pub fn build_http_client(use_doh: bool) -> reqwest::Client {
+    if !use_doh {
+        return reqwest::Client::new();
+    }
+
+    reqwest::Client::builder()
+        .dns_resolver(Arc::new(DohResolver::new()))
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("[DoH] Failed to build DoH client, using system DNS: {}", e);
+            reqwest::Client::new()
+        })
+}
+
+/// Host -> (resolved at, addresses).
+type DnsCache = Arc<Mutex<HashMap<String, (Instant, Vec<IpAddr>)>>>;
+
+/// Resolver that asks Cloudflare, then Google, then falls back to system DNS.
+pub struct DohResolver {
+    client: reqwest::Client,
+    cache: DnsCache,
+}
+
+impl DohResolver {
+    fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            client,
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl Resolve for DohResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let client = self.client.clone();
+        let cache = self.cache.clone();
+        let host = name.as_str().to_string();
+
+        Box::pin(async move {
+            let cached = cache
+                .lock()
+                .ok()
+                .and_then(|c| c.get(&host).cloned())
+                .filter(|(at, _)| at.elapsed() < CACHE_TTL)
+                .map(|(_, ips)| ips);
+
+            let ips = match cached {
+                Some(ips) => ips,
+                None => {
+                    let mut found = Vec::new();
+                    for endpoint in DOH_ENDPOINTS {
+                        match query_endpoint(&client, endpoint, &host).await {
+                            Ok(ips) if !ips.is_empty() => {
+                                found = ips;
+                                break;
+                            }
+                            Ok(_) => {}
+                            Err(e) => eprintln!("[DoH] {} failed for {}: {}", endpoint, host, e),
+                        }
+                    }
+                    if !found.is_empty() {
+                        if let Ok(mut c) = cache.lock() {
+                            c.insert(host.clone(), (Instant::now(), found.clone()));
+                        }
+                    }
+                    found
+                }
+            };
+
+            if ips.is_empty() {
+                // Fall back to system DNS
+                let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+                return Ok(Box::new(addrs.into_iter()) as Addrs);
+            }
+
+            let addrs: Vec<SocketAddr> = ips.into_iter().map(|ip| SocketAddr::new(ip, 0)).collect();
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Query one DoH endpoint for A and AAAA records.
+async fn query_endpoint(client: &reqwest::Client, endpoint: &str, host: &str) -> Result<Vec<IpAddr>, String> {
+    let (v4, v6) = tokio::join!(
+        query_records(client, endpoint, host, "A"),
+        query_records(client, endpoint, host, "AAAA"),
+    );
+
+    let mut ips = v4?;
+    // IPv6 is a bonus; an AAAA failure shouldn't discard good A records
+    ips.extend(v6.unwrap_or_default());
+    Ok(ips)
+}
+
+async fn query_records(
+    client: &reqwest::Client,
+    endpoint: &str,
+    host: &str,
+    record_type: &str,
+) -> Result<Vec<IpAddr>, String> {
+    let response = client
+        .get(endpoint)
+        .query(&[("name", host), ("type", record_type)])
+        .header("Accept", "application/dns-json")
+        .send()
+        .await
+        .map_err(|e| format!("request failed: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+
+    let data: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("invalid response: {}", e))?;
+
+    // Answers may include CNAME records; keep only entries whose data is an IP
+    Ok(data["Answer"]
+        .as_array()
+        .map(|answers| {
+            answers
+                .iter()
+                .filter_map(|a| a["data"].as_str())
+                .filter_map(|d| d.parse::<IpAddr>().ok())
+                .collect()
+        })
+        .unwrap_or_default())
+}
@@ -1,5 +1,19 @@
-use std::path::PathBuf;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+/// Bytes written between cancellation checks while extracting a file
+const EXTRACT_CHUNK_BYTES: usize = 4 * 1024 * 1024;
+
+/// A single chunk write or flush taking longer than this counts as a stalled extraction
+const EXTRACT_STALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Set by `cancel_extraction`; checked between chunks and cleared when an extraction starts.
+static EXTRACTION_CANCELLED: AtomicBool = AtomicBool::new(false);
 
 // ---------------------------------------------------------------------------
 // Platform-specific embedded DepotDownloaderMod files
@@ -30,33 +44,79 @@ mod platform {
     pub const EXE_NAME: &str = "DepotDownloaderMod";
 }
 
+/// Result of checking one extracted DDM file against its embedded copy.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileCheck {
+    pub name: String,
+    #[serde(rename = "expectedSize")]
+    pub expected_size: u64,
+    /// Size found on disk before any repair, None if the file was missing
+    #[serde(rename = "foundSize")]
+    pub found_size: Option<u64>,
+    /// "ok", "repaired" or "failed"
+    pub status: String,
+    pub error: Option<String>,
+}
+
+/// One embedded DDM file, as built into this binary.
+#[derive(Debug, Clone, Serialize)]
+pub struct BundledFile {
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Name, size and SHA-256 of every embedded DDM file. Hashes the embedded bytes,
+/// so call it off the async runtime.
+pub fn bundled_files() -> Vec<BundledFile> {
+    platform::DDM_FILES
+        .iter()
+        .map(|(name, data)| BundledFile {
+            name: name.to_string(),
+            size: data.len() as u64,
+            sha256: Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect(),
+        })
+        .collect()
+}
+
+/// Directory the embedded files are extracted to.
+pub fn extraction_dir() -> PathBuf {
+    // Use the system temp directory + app-specific subfolder
+    std::env::temp_dir().join("SteamManifestDownloader").join("DepotDownloaderMod")
+}
+
+/// Stop an extraction in progress. The file being written is removed and the
+/// `.extracted` marker is not written, so the next run extracts again.
+pub fn cancel_extraction() {
+    EXTRACTION_CANCELLED.store(true, Ordering::SeqCst);
+}
+
 /// Extract embedded DepotDownloaderMod files to a directory.
 /// Returns the path to the DepotDownloaderMod executable.
-/// Uses a marker file to avoid re-extracting on every run.
+/// Uses a marker file to avoid re-extracting on every run, but still re-extracts
+/// any file that has gone missing or has the wrong size since (AV quarantine, cleanup tools).
 pub async fn ensure_extracted() -> Result<PathBuf, String> {
-    // Use the system temp directory + app-specific subfolder
-    let base_dir = std::env::temp_dir().join("SteamManifestDownloader").join("DepotDownloaderMod");
+    let base_dir = extraction_dir();
 
     let marker_file = base_dir.join(".extracted");
-
-    // Check if already extracted (marker file exists and exe exists)
     let exe_path = base_dir.join(platform::EXE_NAME);
-    if marker_file.exists() && exe_path.exists() {
-        return Ok(exe_path);
-    }
 
-    // Extract all files
-    eprintln!("[EmbeddedTools] Extracting DepotDownloaderMod to {:?}", base_dir);
-
-    fs::create_dir_all(&base_dir)
-        .await
-        .map_err(|e| format!("Failed to create extraction directory: {}", e))?;
+    let report = verify_extracted().await?;
+    let failed: Vec<&FileCheck> = report.iter().filter(|f| f.status == "failed").collect();
+    if failed.iter().any(|f| f.error.as_deref() == Some("cancelled")) {
+        return Err("Extraction of DepotDownloaderMod was cancelled".to_string());
+    }
+    if !failed.is_empty() {
+        let details: Vec<String> = failed
+            .iter()
+            .map(|f| format!("{} ({})", f.name, f.error.as_deref().unwrap_or("unknown error")))
+            .collect();
+        return Err(format!("Failed to extract DepotDownloaderMod files: {}", details.join(", ")));
+    }
 
-    for (name, data) in platform::DDM_FILES {
-        let file_path = base_dir.join(name);
-        fs::write(&file_path, data)
-            .await
-            .map_err(|e| format!("Failed to extract {}: {}", name, e))?;
+    let repaired = report.iter().filter(|f| f.status == "repaired").count();
+    if marker_file.exists() && repaired == 0 {
+        return Ok(exe_path);
     }
 
     // On Linux, set executable permissions
@@ -71,11 +131,104 @@ pub async fn ensure_extracted() -> Result<PathBuf, String> {
             .map_err(|e| format!("Failed to set executable permission: {}", e))?;
     }
 
-    // Write marker file
-    fs::write(&marker_file, "extracted")
-        .await
-        .map_err(|e| format!("Failed to write marker file: {}", e))?;
+    // Write marker file, only once every file above is on disk
+    write_synced(&marker_file, b"extracted").await?;
 
-    eprintln!("[EmbeddedTools] Extraction complete");
+    eprintln!("[EmbeddedTools] Extraction complete ({} files written)", repaired);
     Ok(exe_path)
 }
+
+/// Check every file in `DDM_FILES` exists with the embedded size, re-extracting
+/// missing or mismatched ones. Returns a per-file report. Files left unchecked after
+/// `cancel_extraction` are reported as failed.
+pub async fn verify_extracted() -> Result<Vec<FileCheck>, String> {
+    let base_dir = extraction_dir();
+    let marker_file = base_dir.join(".extracted");
+    EXTRACTION_CANCELLED.store(false, Ordering::SeqCst);
+
+    fs::create_dir_all(&base_dir)
+        .await
+        .map_err(|e| format!("Failed to create extraction directory: {}", e))?;
+
+    let mut report = Vec::new();
+
+    for (name, data) in platform::DDM_FILES {
+        let file_path = base_dir.join(name);
+        let expected_size = data.len() as u64;
+        let found_size = fs::metadata(&file_path).await.ok().map(|m| m.len());
+
+        if found_size == Some(expected_size) {
+            report.push(FileCheck {
+                name: name.to_string(),
+                expected_size,
+                found_size,
+                status: "ok".to_string(),
+                error: None,
+            });
+            continue;
+        }
+
+        // Anything being rewritten means the set is no longer complete until this run finishes
+        let _ = fs::remove_file(&marker_file).await;
+
+        eprintln!("[EmbeddedTools] Extracting {} to {:?}", name, base_dir);
+        let (status, error) = match write_synced(&file_path, data).await {
+            Ok(_) => ("repaired", None),
+            Err(e) => ("failed", Some(e)),
+        };
+        report.push(FileCheck {
+            name: name.to_string(),
+            expected_size,
+            found_size,
+            status: status.to_string(),
+            error,
+        });
+    }
+
+    Ok(report)
+}
+
+/// Write `data` to `{path}.part` in chunks, flush it to disk, then rename it over `path`,
+/// so an interrupted write never leaves a file that looks complete. Gives up when
+/// `cancel_extraction` is called or a chunk takes longer than `EXTRACT_STALL_TIMEOUT`.
+async fn write_synced(path: &Path, data: &[u8]) -> Result<(), String> {
+    let mut part_name = path.file_name().unwrap_or_default().to_os_string();
+    part_name.push(".part");
+    let part_path = path.with_file_name(part_name);
+
+    let written = async {
+        let mut file = fs::File::create(&part_path)
+            .await
+            .map_err(|e| format!("Failed to create file: {}", e))?;
+        for chunk in data.chunks(EXTRACT_CHUNK_BYTES) {
+            if EXTRACTION_CANCELLED.load(Ordering::SeqCst) {
+                return Err("cancelled".to_string());
+            }
+            tokio::time::timeout(EXTRACT_STALL_TIMEOUT, file.write_all(chunk))
+                .await
+                .map_err(|_| stall_error())?
+                .map_err(|e| format!("Failed to write file: {}", e))?;
+        }
+        tokio::time::timeout(EXTRACT_STALL_TIMEOUT, file.sync_all())
+            .await
+            .map_err(|_| stall_error())?
+            .map_err(|e| format!("Failed to flush file: {}", e))?;
+        drop(file);
+        fs::rename(&part_path, path)
+            .await
+            .map_err(|e| format!("Failed to move file into place: {}", e))
+    }
+    .await;
+
+    if written.is_err() {
+        let _ = fs::remove_file(&part_path).await;
+    }
+    written
+}
+
+fn stall_error() -> String {
+    format!(
+        "no progress for {}s, the disk may be full or unresponsive",
+        EXTRACT_STALL_TIMEOUT.as_secs()
+    )
+}
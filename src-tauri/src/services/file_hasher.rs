@@ -0,0 +1,96 @@
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::services::depot_state::STATE_DIR_NAME;
+
+/// Files and folders that describe the download rather than being part of it.
+const SKIPPED_NAMES: &[&str] = &["hashes.json", "progress.ndjson", STATE_DIR_NAME, ".staging"];
+
+#[derive(Debug, Clone, Copy)]
+pub enum HashAlgorithm {
+    Sha1,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().replace('-', "").as_str() {
+            "sha1" => Ok(Self::Sha1),
+            "sha256" => Ok(Self::Sha256),
+            _ => Err(format!("Unsupported hash algorithm: {}. Use 'sha1' or 'sha256'.", name)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+        }
+    }
+}
+
+/// Hash a file in fixed-size chunks so large files never sit in memory. Blocking.
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> Result<String, String> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+
+    match algorithm {
+        HashAlgorithm::Sha1 => stream_digest(&mut file, Sha1::new(), path),
+        HashAlgorithm::Sha256 => stream_digest(&mut file, Sha256::new(), path),
+    }
+}
+
+fn stream_digest<D: Digest>(file: &mut std::fs::File, mut hasher: D, path: &Path) -> Result<String, String> {
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Recursively list the downloaded files under `dir`, sorted, skipping bookkeeping files. Blocking.
+pub fn list_download_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files);
+    files.sort();
+    files
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(e) => {
+            eprintln!("[FileHasher] Failed to read {:?}: {}", dir, e);
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if SKIPPED_NAMES.contains(&name.as_str()) {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
+
+/// Path relative to `root` with forward slashes, so hashes.json is portable across platforms.
+pub fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
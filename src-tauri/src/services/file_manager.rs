@@ -0,0 +1,21 @@
+use std::path::Path;
+use tokio::process::Command;
+
+/// Show a folder in the system file manager (Explorer on Windows, the `xdg-open`
+/// handler on Linux). Returns once the file manager is launched, not when it closes.
+pub fn open_folder(path: &Path) -> Result<(), String> {
+    if !path.is_dir() {
+        return Err(format!("Folder not found: {}", path.display()));
+    }
+
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(target_os = "linux")]
+    let program = "xdg-open";
+
+    Command::new(program)
+        .arg(path)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open folder: {}", e))
+}
@@ -0,0 +1,145 @@
+use serde::Serialize;
+use std::path::Path;
+
+use crate::services::depot_state::STATE_DIR_NAME;
+use crate::services::manifest_downloader;
+use crate::services::steam_store_api;
+
+/// Counter suffixes tried before giving up on a suggestion
+const MAX_SUGGESTION_COUNTER: usize = 1000;
+
+/// One entry at the top of the download location.
+#[derive(Debug, Clone)]
+pub struct FolderEntry {
+    pub name: String,
+    pub is_dir: bool,
+    /// Empty, or holding an earlier download, so downloading into it overwrites nothing unrelated
+    pub reusable: bool,
+}
+
+/// How a proposed download folder name relates to what's already in the download location.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderCollision {
+    pub proposed_name: String,
+    /// Name of the entry the download would land in, if there is one (may differ in case)
+    pub existing_name: Option<String>,
+    /// Downloading would write into a file, a differently-cased folder, or a folder holding something else
+    pub conflict: bool,
+    /// Other folders for the same App ID under a different name
+    pub same_app_folders: Vec<String>,
+    /// A free name to use instead, when there's a conflict
+    pub suggestion: Option<String>,
+}
+
+/// The App ID a download folder is named after: `{app_id}` or `{app_id} - {name}`.
+fn folder_app_id(name: &str) -> Option<&str> {
+    let id = name.split(" - ").next().unwrap_or(name);
+    steam_store_api::is_valid_app_id(id).then_some(id)
+}
+
+/// Compare `proposed` against the entries of the download location. Names are matched
+/// case-insensitively, since Windows and macOS would put both in the same folder.
+pub fn check_collision(entries: &[FolderEntry], proposed: &str) -> FolderCollision {
+    let existing = entries
+        .iter()
+        .find(|e| e.name == proposed)
+        .or_else(|| entries.iter().find(|e| e.name.eq_ignore_ascii_case(proposed)));
+
+    let conflict = existing.is_some_and(|e| !e.is_dir || !e.reusable || e.name != proposed);
+
+    let same_app_folders = match folder_app_id(proposed) {
+        Some(app_id) => entries
+            .iter()
+            .filter(|e| e.is_dir && folder_app_id(&e.name) == Some(app_id))
+            .filter(|e| !e.name.eq_ignore_ascii_case(proposed))
+            .map(|e| e.name.clone())
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let suggestion = if conflict {
+        (2..=MAX_SUGGESTION_COUNTER)
+            .map(|n| format!("{} ({})", proposed, n))
+            .find(|name| !entries.iter().any(|e| e.name.eq_ignore_ascii_case(name)))
+    } else {
+        None
+    };
+
+    FolderCollision {
+        proposed_name: proposed.to_string(),
+        existing_name: existing.map(|e| e.name.clone()),
+        conflict,
+        same_app_folders,
+        suggestion,
+    }
+}
+
+/// List the entries at the top of `base_dir`. Only entries named like `proposed`
+/// (ignoring case) are looked into for `reusable`; the rest are reported reusable.
+/// Manifests are recognized by `manifest_pattern` (the `manifest_filename_pattern` setting).
+/// A missing `base_dir` has no entries.
+pub fn scan_entries(base_dir: &Path, proposed: &str, manifest_pattern: &str) -> Vec<FolderEntry> {
+    let Ok(entries) = std::fs::read_dir(base_dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let path = entry.path();
+            let is_dir = path.is_dir();
+            let reusable = !is_dir || !name.eq_ignore_ascii_case(proposed) || is_reusable_dir(&path, manifest_pattern);
+            FolderEntry { name, is_dir, reusable }
+        })
+        .collect()
+}
+
+/// Whether a folder is empty or already holds a download (DDM state or manifest files).
+fn is_reusable_dir(dir: &Path, manifest_pattern: &str) -> bool {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return false;
+    };
+
+    let mut empty = true;
+    for entry in entries.flatten() {
+        empty = false;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name == STATE_DIR_NAME || manifest_downloader::parse_manifest_filename(manifest_pattern, &name).is_some() {
+            return true;
+        }
+    }
+    empty
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn test_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("folder-collision-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn recognizes_manifests_named_by_custom_pattern() {
+        let base = test_dir();
+        let folder = base.join("440 - Team Fortress 2");
+        std::fs::create_dir(&folder).unwrap();
+        std::fs::write(folder.join("depot-441-manifest-123456789.bin"), b"").unwrap();
+
+        let pattern = "depot-{depot_id}-manifest-{manifest_id}.bin";
+        let entries = scan_entries(&base, "440 - Team Fortress 2", pattern);
+        assert!(!check_collision(&entries, "440 - Team Fortress 2").conflict);
+
+        // The same folder holds something unrelated under the default pattern
+        let default_pattern = manifest_downloader::DEFAULT_MANIFEST_FILENAME_PATTERN;
+        let entries = scan_entries(&base, "440 - Team Fortress 2", default_pattern);
+        assert!(check_collision(&entries, "440 - Team Fortress 2").conflict);
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
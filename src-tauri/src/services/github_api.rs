@@ -1,9 +1,47 @@
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
 
 const USER_AGENT: &str = "SteamManifestDownloader";
 
+/// Parallel GitHub API requests while the rate limit has plenty left
+const MAX_CONCURRENCY: usize = 8;
+
+/// Below this many remaining requests, bulk operations slow down
+const LOW_REMAINING: u64 = 50;
+
+/// Below this many, requests go one at a time with a longer gap
+const CRITICAL_REMAINING: u64 = 10;
+
+/// Rate limit as last reported by GitHub's `X-RateLimit-*` response headers.
+#[derive(Debug, Clone, Copy)]
+struct ObservedRateLimit {
+    remaining: u64,
+    limit: Option<u64>,
+    /// Unix seconds when the window resets
+    reset_at: Option<i64>,
+}
+
+static OBSERVED_RATE_LIMIT: Mutex<Option<ObservedRateLimit>> = Mutex::new(None);
+
+/// How hard bulk GitHub operations may currently hit the API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdaptiveState {
+    /// Remaining requests from the last response; `None` before any or after the window reset
+    pub remaining: Option<u64>,
+    pub limit: Option<u64>,
+    pub reset_time: Option<String>,
+    pub concurrency: usize,
+    /// Pause before each API request
+    pub delay_ms: u64,
+}
+
+/// Repo `check_branch` looks in, and the download default when no repo was picked.
+pub const DEFAULT_REPO: &str = "SteamAutoCracks/ManifestHub";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BranchCheckResult {
     pub exists: bool,
@@ -14,7 +52,6 @@ pub struct BranchCheckResult {
     pub rate_limited: bool,
 }
 
-#[allow(dead_code)] // Available for future rate-limit checking features
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RateLimitInfo {
     pub remaining: u64,
@@ -38,6 +75,60 @@ fn build_headers(token: Option<&str>) -> reqwest::header::HeaderMap {
     headers
 }
 
+/// Record the rate limit headers of a GitHub API response.
+fn observe_rate_limit(headers: &reqwest::header::HeaderMap) {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let Some(remaining) = header("x-ratelimit-remaining").and_then(|v| v.parse::<u64>().ok()) else {
+        return;
+    };
+    let observed = ObservedRateLimit {
+        remaining,
+        limit: header("x-ratelimit-limit").and_then(|v| v.parse().ok()),
+        reset_at: header("x-ratelimit-reset").and_then(|v| v.parse().ok()),
+    };
+    if let Ok(mut state) = OBSERVED_RATE_LIMIT.lock() {
+        *state = Some(observed);
+    }
+}
+
+/// Current throttling, derived from the last observed rate limit. Once the reset time has
+/// passed the old numbers no longer apply, so it ramps straight back to full speed.
+pub fn adaptive_state() -> AdaptiveState {
+    let observed = OBSERVED_RATE_LIMIT.lock().ok().and_then(|s| *s);
+    let now = chrono::Utc::now().timestamp();
+    let current = observed.filter(|o| o.reset_at.is_none_or(|reset| reset > now));
+
+    let (concurrency, delay_ms) = match current.map(|o| o.remaining) {
+        Some(r) if r <= CRITICAL_REMAINING => (1, 1000),
+        Some(r) if r <= LOW_REMAINING => (2, 250),
+        _ => (MAX_CONCURRENCY, 0),
+    };
+
+    AdaptiveState {
+        remaining: current.map(|o| o.remaining),
+        limit: current.and_then(|o| o.limit),
+        reset_time: current
+            .and_then(|o| o.reset_at)
+            .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+            .map(|dt| dt.to_rfc3339()),
+        concurrency,
+        delay_ms,
+    }
+}
+
+/// How many GitHub API requests bulk operations should run at once right now.
+pub fn adaptive_concurrency() -> usize {
+    adaptive_state().concurrency
+}
+
+/// Space out requests while the rate limit is running low.
+async fn throttle() {
+    let delay_ms = adaptive_state().delay_ms;
+    if delay_ms > 0 {
+        tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+    }
+}
+
 /// Check if response indicates rate limiting.
 fn is_rate_limited(status: reqwest::StatusCode) -> bool {
     status == reqwest::StatusCode::FORBIDDEN || status == reqwest::StatusCode::TOO_MANY_REQUESTS
@@ -50,16 +141,18 @@ pub async fn check_branch(
     token: Option<&str>,
 ) -> Result<BranchCheckResult, String> {
     let url = format!(
-        "https://api.github.com/repos/SteamAutoCracks/ManifestHub/branches/{}",
-        app_id
+        "https://api.github.com/repos/{}/branches/{}",
+        DEFAULT_REPO, app_id
     );
 
+    throttle().await;
     let response = client
         .get(&url)
         .headers(build_headers(token))
         .send()
         .await
         .map_err(|e| format!("GitHub API request failed: {}", e))?;
+    observe_rate_limit(response.headers());
 
     let status = response.status();
 
@@ -129,24 +222,44 @@ pub async fn check_branch(
     })
 }
 
-/// Get git tree for a repo at a given SHA.
+/// Directories listed through the contents API when a recursive tree comes back truncated
+const MAX_FALLBACK_DIRS: usize = 50;
+
+/// The contents API returns at most this many entries per directory
+const CONTENTS_API_LIMIT: usize = 1000;
+
+/// Every file path in a branch tree.
+#[derive(Debug, Clone)]
+pub struct TreeListing {
+    pub blobs: Vec<String>,
+    /// False when GitHub truncated the tree and the directory fallback couldn't list everything
+    pub complete: bool,
+}
+
+/// Get git tree for a repo at a given SHA. `recursive` includes every nested entry,
+/// up to GitHub's size limit; the response's `truncated` says whether it was hit.
 pub async fn get_tree(
     client: &Client,
     repo: &str,
     sha: &str,
+    recursive: bool,
     token: Option<&str>,
 ) -> Result<Value, String> {
     let url = format!(
-        "https://api.github.com/repos/{}/git/trees/{}",
-        repo, sha
+        "https://api.github.com/repos/{}/git/trees/{}{}",
+        repo,
+        sha,
+        if recursive { "?recursive=1" } else { "" }
     );
 
+    throttle().await;
     let response = client
         .get(&url)
         .headers(build_headers(token))
         .send()
         .await
         .map_err(|e| format!("GitHub Tree API request failed: {}", e))?;
+    observe_rate_limit(response.headers());
 
     let status = response.status();
 
@@ -164,8 +277,198 @@ pub async fn get_tree(
         .map_err(|e| format!("Failed to parse tree response: {}", e))
 }
 
+/// List one directory of a branch through the contents API.
+async fn list_directory(
+    client: &Client,
+    repo: &str,
+    sha: &str,
+    path: &str,
+    token: Option<&str>,
+) -> Result<Vec<Value>, String> {
+    let url = format!(
+        "https://api.github.com/repos/{}/contents/{}?ref={}",
+        repo, path, sha
+    );
+
+    throttle().await;
+    let response = client
+        .get(&url)
+        .headers(build_headers(token))
+        .send()
+        .await
+        .map_err(|e| format!("GitHub Contents API request failed: {}", e))?;
+    observe_rate_limit(response.headers());
+
+    let status = response.status();
+
+    if is_rate_limited(status) {
+        return Err("GitHub API rate limit exceeded".to_string());
+    }
+
+    if !status.is_success() {
+        return Err(format!("GitHub Contents API error for {}: {}", path, status));
+    }
+
+    let entries: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse contents response: {}", e))?;
+    entries
+        .as_array()
+        .cloned()
+        .ok_or_else(|| format!("{} is not a directory", path))
+}
+
+/// List every file in a branch tree. Uses one recursive tree request; if GitHub truncates it,
+/// lists the top level again and walks its directories through the contents API.
+pub async fn list_tree_blobs(
+    client: &Client,
+    repo: &str,
+    sha: &str,
+    token: Option<&str>,
+) -> Result<TreeListing, String> {
+    let blobs_of = |tree: &Value| -> Vec<(String, String)> {
+        tree["tree"]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| Some((item["type"].as_str()?.to_string(), item["path"].as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let tree = get_tree(client, repo, sha, true, token).await?;
+    if tree["tree"].as_array().is_none() {
+        return Err("Missing tree array in GitHub response".to_string());
+    }
+    let mut blobs: BTreeSet<String> = blobs_of(&tree)
+        .into_iter()
+        .filter(|(kind, _)| kind == "blob")
+        .map(|(_, path)| path)
+        .collect();
+
+    if !tree["truncated"].as_bool().unwrap_or(false) {
+        return Ok(TreeListing {
+            blobs: blobs.into_iter().collect(),
+            complete: true,
+        });
+    }
+
+    eprintln!("[GitHub] Tree for {}@{} is truncated, listing directories instead", repo, sha);
+    let root = get_tree(client, repo, sha, false, token).await?;
+    let mut complete = !root["truncated"].as_bool().unwrap_or(false);
+    let mut dirs: VecDeque<String> = VecDeque::new();
+    for (kind, path) in blobs_of(&root) {
+        match kind.as_str() {
+            "blob" => {
+                blobs.insert(path);
+            }
+            "tree" => dirs.push_back(path),
+            _ => {}
+        }
+    }
+
+    let mut listed = 0;
+    while let Some(dir) = dirs.pop_front() {
+        if listed >= MAX_FALLBACK_DIRS {
+            complete = false;
+            break;
+        }
+        listed += 1;
+
+        let entries = match list_directory(client, repo, sha, &dir, token).await {
+            Ok(entries) => entries,
+            Err(e) => {
+                eprintln!("[GitHub] {}", e);
+                complete = false;
+                continue;
+            }
+        };
+        if entries.len() >= CONTENTS_API_LIMIT {
+            complete = false;
+        }
+        for entry in entries {
+            let Some(path) = entry["path"].as_str() else {
+                continue;
+            };
+            match entry["type"].as_str() {
+                Some("file") => {
+                    blobs.insert(path.to_string());
+                }
+                Some("dir") => dirs.push_back(path.to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    Ok(TreeListing {
+        blobs: blobs.into_iter().collect(),
+        complete,
+    })
+}
+
+/// Git blob SHA of every file in a branch tree, by path. Uses one recursive tree request,
+/// so files past GitHub's truncation limit are missing.
+pub async fn get_blob_shas(
+    client: &Client,
+    repo: &str,
+    sha: &str,
+    token: Option<&str>,
+) -> Result<HashMap<String, String>, String> {
+    let tree = get_tree(client, repo, sha, true, token).await?;
+    let items = tree["tree"]
+        .as_array()
+        .ok_or_else(|| "Missing tree array in GitHub response".to_string())?;
+
+    Ok(items
+        .iter()
+        .filter(|item| item["type"].as_str() == Some("blob"))
+        .filter_map(|item| Some((item["path"].as_str()?.to_string(), item["sha"].as_str()?.to_string())))
+        .collect())
+}
+
+/// Fetch a file's content by its git blob SHA. Unlike raw.githubusercontent.com,
+/// the blob API isn't served from a CDN cache, so it can't return an older version.
+pub async fn get_blob(
+    client: &Client,
+    repo: &str,
+    blob_sha: &str,
+    token: Option<&str>,
+) -> Result<Vec<u8>, String> {
+    let url = format!("https://api.github.com/repos/{}/git/blobs/{}", repo, blob_sha);
+
+    let mut headers = build_headers(token);
+    headers.insert("Accept", "application/vnd.github.raw+json".parse().unwrap());
+
+    throttle().await;
+    let response = client
+        .get(&url)
+        .headers(headers)
+        .send()
+        .await
+        .map_err(|e| format!("GitHub Blob API request failed: {}", e))?;
+    observe_rate_limit(response.headers());
+
+    let status = response.status();
+
+    if is_rate_limited(status) {
+        return Err("GitHub API rate limit exceeded".to_string());
+    }
+
+    if !status.is_success() {
+        return Err(format!("GitHub Blob API error: {}", status));
+    }
+
+    response
+        .bytes()
+        .await
+        .map(|bytes| bytes.to_vec())
+        .map_err(|e| format!("Failed to read blob response: {}", e))
+}
+
 /// Check GitHub API rate limit status.
-#[allow(dead_code)]
 pub async fn check_rate_limit(
     client: &Client,
     token: Option<&str>,
@@ -178,6 +481,8 @@ pub async fn check_rate_limit(
         .send()
         .await
         .map_err(|e| format!("GitHub rate limit API request failed: {}", e))?;
+    // The rate_limit endpoint itself is free, so no throttle() before it
+    observe_rate_limit(response.headers());
 
     if !response.status().is_success() {
         return Err(format!(
@@ -223,12 +528,14 @@ pub async fn get_branch_info(
         repo, app_id
     );
 
+    throttle().await;
     let response = client
         .get(&url)
         .headers(build_headers(token))
         .send()
         .await
         .map_err(|e| format!("GitHub API request failed: {}", e))?;
+    observe_rate_limit(response.headers());
 
     let status = response.status();
 
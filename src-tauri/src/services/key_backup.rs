@@ -0,0 +1,94 @@
+use std::path::{Path, PathBuf};
+
+use crate::services::atomic_file;
+use crate::services::steam_store_api;
+
+/// `{app_data_dir}/key_backups/{app_id}.keys`
+pub fn backup_path(app_data_dir: &Path, app_id: &str) -> Result<PathBuf, String> {
+    if !steam_store_api::is_valid_app_id(app_id) {
+        return Err(format!("Invalid App ID: {}", app_id));
+    }
+    Ok(app_data_dir.join("key_backups").join(format!("{}.keys", app_id)))
+}
+
+/// A download folder for `app_id` under `base_dir` that has a `steam.keys`: either
+/// `{app_id}` or `{app_id} - {name}`, as the pipeline names them.
+pub fn find_keys_folder(base_dir: &Path, app_id: &str) -> Option<PathBuf> {
+    let prefix = format!("{} - ", app_id);
+    std::fs::read_dir(base_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name == app_id || name.starts_with(&prefix)
+        })
+        .map(|entry| entry.path())
+        .find(|dir| dir.join("steam.keys").is_file())
+}
+
+/// Add `incoming` key lines to `existing`, keeping existing entries for the same depot.
+/// Returns the merged file content and how many depots were added.
+fn merge_key_lines(existing: &str, incoming: &str) -> (String, usize) {
+    let mut lines: Vec<&str> = existing.lines().map(str::trim).filter(|l| !l.is_empty()).collect();
+    let mut added = 0;
+
+    for line in incoming.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let depot_id = line.split(';').next().unwrap_or(line);
+        let known = lines
+            .iter()
+            .any(|l| l.split(';').next().unwrap_or(l) == depot_id);
+        if !known {
+            lines.push(line);
+            added += 1;
+        }
+    }
+
+    (lines.join("\n") + "\n", added)
+}
+
+/// Merge `keys_file` into `target`, writing it atomically. Returns how many depots were added.
+async fn merge_into(keys_file: &Path, target: &Path) -> Result<usize, String> {
+    let incoming = tokio::fs::read_to_string(keys_file)
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", keys_file.display(), e))?;
+    let existing = tokio::fs::read_to_string(target).await.unwrap_or_default();
+
+    let (merged, added) = merge_key_lines(&existing, &incoming);
+    if added == 0 && !existing.is_empty() {
+        return Ok(0);
+    }
+    if merged.trim().is_empty() {
+        return Err(format!("{} has no depot keys", keys_file.display()));
+    }
+
+    if let Some(parent) = target.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    atomic_file::write_atomic_async(target, merged.into_bytes())
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+    Ok(added)
+}
+
+/// Copy a `steam.keys` into the app's key backup for `app_id`. Keys already backed up
+/// are kept, so one backup collects every depot key the app has seen.
+/// Returns the backup file and how many depots it gained.
+pub async fn backup_keys(app_data_dir: &Path, app_id: &str, keys_file: &Path) -> Result<(PathBuf, usize), String> {
+    let backup = backup_path(app_data_dir, app_id)?;
+    let added = merge_into(keys_file, &backup).await?;
+    Ok((backup, added))
+}
+
+/// Put the backed-up keys for `app_id` into `{target_dir}/steam.keys`, keeping any keys
+/// the file there already has. Returns the written file and how many depots it gained.
+pub async fn restore_keys(app_data_dir: &Path, app_id: &str, target_dir: &Path) -> Result<(PathBuf, usize), String> {
+    let backup = backup_path(app_data_dir, app_id)?;
+    if !backup.is_file() {
+        return Err(format!("No key backup for App ID {}", app_id));
+    }
+    let target = target_dir.join("steam.keys");
+    let added = merge_into(&backup, &target).await?;
+    Ok((target, added))
+}
@@ -12,13 +12,18 @@ pub struct DepotInfo {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LuaParseResult {
     pub main_app_id: Option<u64>,
+    /// Every keyless `addappid(id)` in file order; more than one means a multi-game dump.
+    #[serde(default)]
+    pub app_ids: Vec<u64>,
     pub depots: Vec<DepotInfo>,
 }
 
-/// Parse `.lua` file content, extracting `addappid()` and `setManifestid()` calls.
+/// Parse `.lua` file content, extracting `addappid()`, `depotkey()`/`setDepotKey()`
+/// and `setManifestid()` calls.
 pub fn parse_lua_file(content: &str) -> LuaParseResult {
     let mut result = LuaParseResult {
         main_app_id: None,
+        app_ids: Vec::new(),
         depots: Vec::new(),
     };
 
@@ -40,6 +45,9 @@ pub fn parse_lua_file(content: &str) -> LuaParseResult {
             if result.main_app_id.is_none() {
                 result.main_app_id = Some(id);
             }
+            if !result.app_ids.contains(&id) {
+                result.app_ids.push(id);
+            }
         } else {
             let depot_key = cap[3].to_string();
             depot_map
@@ -53,6 +61,26 @@ pub fn parse_lua_file(content: &str) -> LuaParseResult {
         }
     }
 
+    // Match alternate key declarations
+    // Pattern: depotkey(depotId, "hexKey") or setDepotKey(depotId, "hexKey")
+    // addappid keys stay primary; these only fill depots that don't have one yet.
+    let depot_key_re =
+        Regex::new(r#"(?i)\b(?:set)?depotkey\((\d+)\s*,\s*"([a-f0-9]+)"\)"#).unwrap();
+
+    for cap in depot_key_re.captures_iter(content) {
+        let depot_id: u64 = cap[1].parse().unwrap_or(0);
+        let depot_key = cap[2].to_string();
+
+        let depot = depot_map.entry(depot_id).or_insert(DepotInfo {
+            depot_id,
+            depot_key: None,
+            manifest_id: None,
+        });
+        if depot.depot_key.is_none() {
+            depot.depot_key = Some(depot_key);
+        }
+    }
+
     // Match setManifestid calls
     // Pattern: setManifestid(depotId, "manifestId")
     let set_manifest_re = Regex::new(r#"(?i)setManifestid\((\d+)\s*,\s*"(\d+)"\)"#).unwrap();
@@ -81,3 +109,68 @@ pub fn parse_lua_file(content: &str) -> LuaParseResult {
 
     result
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_A: &str = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    const KEY_B: &str = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+
+    fn depot_key(result: &LuaParseResult, depot_id: u64) -> Option<&str> {
+        result
+            .depots
+            .iter()
+            .find(|d| d.depot_id == depot_id)
+            .and_then(|d| d.depot_key.as_deref())
+    }
+
+    #[test]
+    fn parses_depotkey_form() {
+        let result = parse_lua_file(&format!("addappid(440)\ndepotkey(441, \"{}\")\n", KEY_A));
+        assert_eq!(depot_key(&result, 441), Some(KEY_A));
+    }
+
+    #[test]
+    fn parses_set_depot_key_form() {
+        let result = parse_lua_file(&format!("addappid(440)\nsetDepotKey(441,\"{}\")\n", KEY_A));
+        assert_eq!(depot_key(&result, 441), Some(KEY_A));
+    }
+
+    #[test]
+    fn mixes_alternate_forms_with_addappid_keys() {
+        let content = format!(
+            "addappid(440)\n\
+             addappid(441, 1, \"{a}\")\n\
+             depotkey(441, \"{b}\")\n\
+             setDepotKey(442, \"{b}\")\n\
+             setManifestid(442, \"123456789\")\n",
+            a = KEY_A,
+            b = KEY_B
+        );
+        let result = parse_lua_file(&content);
+
+        // addappid stays primary for depots declared both ways
+        assert_eq!(depot_key(&result, 441), Some(KEY_A));
+        assert_eq!(depot_key(&result, 442), Some(KEY_B));
+        let depot = result.depots.iter().find(|d| d.depot_id == 442).unwrap();
+        assert_eq!(depot.manifest_id.as_deref(), Some("123456789"));
+        assert_eq!(result.main_app_id, Some(440));
+        assert_eq!(result.depots.len(), 2);
+    }
+
+    #[test]
+    fn lists_every_app_id_of_a_two_game_file() {
+        let content = format!(
+            "-- Game one\naddappid(1245620)\naddappid(1245621, 1, \"{a}\")\n\
+             -- Game two\naddappid(570)\naddappid(571, 1, \"{b}\")\naddappid(1245620)\n",
+            a = KEY_A,
+            b = KEY_B
+        );
+        let result = parse_lua_file(&content);
+
+        assert_eq!(result.app_ids, vec![1245620, 570]);
+        assert_eq!(result.main_app_id, Some(1245620));
+        assert_eq!(result.depots.len(), 2);
+    }
+}
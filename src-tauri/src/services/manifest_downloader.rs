@@ -1,6 +1,30 @@
+use regex::Regex;
 use reqwest::Client;
+use std::collections::BTreeMap;
+use sha1::{Digest, Sha1};
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use tokio::fs;
+use tokio::sync::Mutex;
+
+use crate::services::github_api;
+use crate::services::manifest_parser;
+
+/// Bytes requested for the metadata section; it is normally well under 100 bytes.
+const METADATA_PROBE_BYTES: u64 = 512;
+
+/// Maximum cached manifest sizes before the size cache is cleared
+const MAX_SIZE_CACHE_SIZE: usize = 1000;
+
+/// `{depot_id}_{manifest_id}` -> uncompressed size. A manifest id never changes content,
+/// so entries don't expire.
+static MANIFEST_SIZE_CACHE: Mutex<BTreeMap<String, u64>> = Mutex::const_new(BTreeMap::new());
+
+/// Tries per repo text file (Key.vdf, lua) before a transient failure is given up on.
+const TEXT_FETCH_ATTEMPTS: u32 = 3;
+
+/// Delay before the first text file retry; doubled for each further one.
+const TEXT_FETCH_RETRY_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
 
 /// Build authorization headers for GitHub raw downloads.
 fn build_auth_header(token: Option<&str>) -> reqwest::header::HeaderMap {
@@ -17,26 +41,147 @@ fn build_auth_header(token: Option<&str>) -> reqwest::header::HeaderMap {
     headers
 }
 
+/// Decompress a body that still starts with the gzip magic (`1f 8b`).
+///
+/// The client decodes `Content-Encoding: gzip` itself, but a body that was gzipped
+/// without the header (or double-encoded) would otherwise be saved as-is and rejected by DDM.
+/// Steam manifests never start with these bytes, so anything else is returned unchanged.
+pub fn gunzip_if_compressed(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    if !bytes.starts_with(&[0x1f, 0x8b]) {
+        return Ok(bytes.to_vec());
+    }
+
+    let mut decoded = Vec::new();
+    flate2::read::GzDecoder::new(bytes)
+        .read_to_end(&mut decoded)
+        .map_err(|e| format!("Failed to decompress gzipped manifest: {}", e))?;
+    Ok(decoded)
+}
+
+/// Normalize a user-supplied manifest id.
+///
+/// Strips whitespace and digit-group separators (`,`, `_`, `'`) and checks that the
+/// remainder parses as a u64. Spreadsheet scientific notation (`1.23E+18`) is rejected
+/// since the trailing digits are already lost by the time it is pasted.
+pub fn normalize_manifest_id(input: &str) -> Result<String, String> {
+    let cleaned: String = input
+        .chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, ',' | '_' | '\''))
+        .collect();
+
+    if cleaned.is_empty() {
+        return Err("Manifest ID is empty".to_string());
+    }
+
+    if cleaned.contains(['e', 'E']) {
+        return Err(format!(
+            "Manifest ID '{}' looks like scientific notation; copy it as plain text so no digits are lost",
+            input.trim()
+        ));
+    }
+
+    if !cleaned.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Manifest ID '{}' contains non-digit characters", input.trim()));
+    }
+
+    cleaned
+        .parse::<u64>()
+        .map(|id| id.to_string())
+        .map_err(|_| format!("Manifest ID '{}' is too large for a 64-bit ID", input.trim()))
+}
+
+/// Git's object id for a file's content: the SHA-1 of `blob {len}\0{content}`.
+pub fn git_blob_sha(content: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(format!("blob {}\0", content.len()).as_bytes());
+    hasher.update(content);
+    hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Default `manifest_filename_pattern`: the name DDM and the manifest repos use.
+pub const DEFAULT_MANIFEST_FILENAME_PATTERN: &str = "{depot_id}_{manifest_id}.manifest";
+
+/// Local file name of a manifest under `pattern` (the `manifest_filename_pattern` setting).
+/// Every stage that writes or points DDM at a manifest file names it through this, so they
+/// stay in step when the pattern changes. Repo paths keep the repos' own naming.
+pub fn manifest_filename(pattern: &str, depot_id: &str, manifest_id: &str) -> String {
+    pattern
+        .replace("{depot_id}", depot_id)
+        .replace("{manifest_id}", manifest_id)
+}
+
+/// Depot and manifest id of a file named by `pattern`, or None for any other name.
+pub fn parse_manifest_filename(pattern: &str, name: &str) -> Option<(String, String)> {
+    let regex = format!(
+        "^{}$",
+        regex::escape(pattern)
+            .replace(r"\{depot_id\}", r"(?P<depot>\d+)")
+            .replace(r"\{manifest_id\}", r"(?P<manifest>\d+)")
+    );
+    let caps = Regex::new(&regex).ok()?.captures(name)?;
+    Some((caps.name("depot")?.as_str().to_string(), caps.name("manifest")?.as_str().to_string()))
+}
+
+/// Raw GitHub URL of a manifest file in a repo's app branch.
+pub fn manifest_url(repo: &str, app_id: &str, depot_id: &str, manifest_id: &str) -> String {
+    // Use app_id as branch reference for raw URLs
+    format!(
+        "https://raw.githubusercontent.com/{}/{}/{}_{}.manifest",
+        repo, app_id, depot_id, manifest_id
+    )
+}
+
+/// Optional inputs for one manifest download, from GitHub or ManifestHub.
+#[derive(Debug, Clone, Copy)]
+pub struct ManifestFetchOptions<'a> {
+    /// Local file name pattern (see `manifest_filename`)
+    pub filename_pattern: &'a str,
+    /// Where the repo keeps the manifest when it isn't `{depot_id}_{manifest_id}.manifest` at the root
+    pub repo_path: Option<&'a str>,
+    /// The file's git blob SHA from the branch tree
+    pub expected_blob_sha: Option<&'a str>,
+    pub github_token: Option<&'a str>,
+    /// ManifestHub API hosts to try in order; empty for `manifest_hub_api::DEFAULT_HOST`
+    pub hosts: &'a [String],
+}
+
+impl Default for ManifestFetchOptions<'_> {
+    fn default() -> Self {
+        Self {
+            filename_pattern: DEFAULT_MANIFEST_FILENAME_PATTERN,
+            repo_path: None,
+            expected_blob_sha: None,
+            github_token: None,
+            hosts: &[],
+        }
+    }
+}
+
 /// Download a `.manifest` file from a GitHub repo.
 ///
-/// URL pattern: `https://raw.githubusercontent.com/{repo}/{sha_or_appid}/{depot_id}_{manifest_id}.manifest`
-/// Saves to: `{output_dir}/{depot_id}_{manifest_id}.manifest`
+/// URL pattern: `https://raw.githubusercontent.com/{repo}/{app_id}/{depot_id}_{manifest_id}.manifest`,
+/// or `.../{repo_path}` for a manifest the repo keeps in a subfolder.
+/// Saves to: `{output_dir}/{filename}`
+///
+/// The local file is named by `filename_pattern` (see `manifest_filename`).
+///
+/// With `expected_blob_sha`, a raw CDN response that doesn't match is treated as stale
+/// and the file is fetched again through the blob API.
 pub async fn download_manifest(
     client: &Client,
     app_id: &str,
     depot_id: &str,
     manifest_id: &str,
     repo: &str,
-    sha: &str,
     output_dir: &Path,
-    token: Option<&str>,
+    options: &ManifestFetchOptions<'_>,
 ) -> Result<PathBuf, String> {
-    let filename = format!("{}_{}.manifest", depot_id, manifest_id);
-    // Use app_id as branch reference for raw URLs
-    let url = format!(
-        "https://raw.githubusercontent.com/{}/{}/{}",
-        repo, app_id, filename
-    );
+    let token = options.github_token;
+    let filename = manifest_filename(options.filename_pattern, depot_id, manifest_id);
+    let url = match options.repo_path {
+        Some(path) => format!("https://raw.githubusercontent.com/{}/{}/{}", repo, app_id, path),
+        None => manifest_url(repo, app_id, depot_id, manifest_id),
+    };
 
     // Ensure output directory exists
     fs::create_dir_all(output_dir)
@@ -66,16 +211,135 @@ pub async fn download_manifest(
         .await
         .map_err(|e| format!("Failed to read manifest response body: {}", e))?;
 
+    // The blob SHA covers the file as committed, so check before any gunzip
+    let bytes = match options.expected_blob_sha {
+        Some(expected) if git_blob_sha(&bytes) != expected => {
+            eprintln!("[Manifest] Raw download of {} doesn't match blob {}, fetching it from the blob API", filename, expected);
+            let blob = github_api::get_blob(client, repo, expected, token).await?;
+            if git_blob_sha(&blob) != expected {
+                return Err(format!("Manifest {} from the blob API doesn't match its git SHA {}", filename, expected));
+            }
+            blob
+        }
+        _ => bytes.to_vec(),
+    };
+    let bytes = gunzip_if_compressed(&bytes)?;
+
     fs::write(&output_path, &bytes)
         .await
         .map_err(|e| format!("Failed to write manifest file: {}", e))?;
 
-    // sha is available for reference but raw URLs use branch name (app_id)
-    let _ = sha;
-
     Ok(output_path)
 }
 
+/// Uncompressed size of a depot, read from its manifest in a repo branch.
+///
+/// Manifests store the small metadata section after the file list, so this asks for
+/// the first section header and then only the metadata with `Range` requests. Falls back
+/// to the whole file if the server ignores ranges or the manifest isn't in raw form.
+pub async fn fetch_manifest_size(
+    client: &Client,
+    app_id: &str,
+    depot_id: &str,
+    manifest_id: &str,
+    repo: &str,
+    token: Option<&str>,
+) -> Result<u64, String> {
+    let url = manifest_url(repo, app_id, depot_id, manifest_id);
+
+    let (head, partial) = fetch_range(client, &url, 0, 7, token).await?;
+    let payload_len = match manifest_parser::read_u32_le(&head, 0) {
+        Some(manifest_parser::PAYLOAD_MAGIC) if partial => manifest_parser::read_u32_le(&head, 4),
+        _ => None,
+    };
+    let Some(payload_len) = payload_len else {
+        let full = if partial {
+            fetch_range(client, &url, 0, u64::MAX, token).await?.0
+        } else {
+            head
+        };
+        return manifest_parser::parse_manifest(&gunzip_if_compressed(&full)?).map(|m| m.cb_disk_original);
+    };
+
+    let start = 8 + payload_len as u64;
+    let (mut section, _) = fetch_range(client, &url, start, start + METADATA_PROBE_BYTES - 1, token).await?;
+    if manifest_parser::read_u32_le(&section, 0) != Some(manifest_parser::METADATA_MAGIC) {
+        return Err(format!("Manifest for depot {} has no metadata where expected", depot_id));
+    }
+    let len = manifest_parser::read_u32_le(&section, 4).unwrap_or(0) as u64;
+    if (section.len() as u64) < 8 + len {
+        section = fetch_range(client, &url, start, start + 8 + len - 1, token).await?.0;
+    }
+
+    let metadata = section
+        .get(8..8 + len as usize)
+        .ok_or_else(|| format!("Manifest metadata for depot {} is truncated", depot_id))?;
+    manifest_parser::parse_metadata(metadata).map(|m| m.cb_disk_original)
+}
+
+/// `fetch_manifest_size`, remembering results for the rest of the session.
+pub async fn fetch_manifest_size_cached(
+    client: &Client,
+    app_id: &str,
+    depot_id: &str,
+    manifest_id: &str,
+    repo: &str,
+    token: Option<&str>,
+) -> Result<u64, String> {
+    let key = format!("{}_{}", depot_id, manifest_id);
+    if let Some(size) = MANIFEST_SIZE_CACHE.lock().await.get(&key) {
+        return Ok(*size);
+    }
+
+    let size = fetch_manifest_size(client, app_id, depot_id, manifest_id, repo, token).await?;
+
+    let mut cache = MANIFEST_SIZE_CACHE.lock().await;
+    if cache.len() >= MAX_SIZE_CACHE_SIZE {
+        cache.clear();
+    }
+    cache.insert(key, size);
+    Ok(size)
+}
+
+/// GET a byte range (inclusive; `u64::MAX` as `end` means to the end of the file).
+/// Returns the body and whether the server honoured the range.
+async fn fetch_range(
+    client: &Client,
+    url: &str,
+    start: u64,
+    end: u64,
+    token: Option<&str>,
+) -> Result<(Vec<u8>, bool), String> {
+    let range = if end == u64::MAX {
+        format!("bytes={}-", start)
+    } else {
+        format!("bytes={}-{}", start, end)
+    };
+
+    let response = client
+        .get(url)
+        .headers(build_auth_header(token))
+        .header(reqwest::header::RANGE, range)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch manifest: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!(
+            "Failed to fetch manifest: {} {}",
+            status,
+            status.canonical_reason().unwrap_or("")
+        ));
+    }
+
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read manifest response body: {}", e))?;
+    Ok((bytes.to_vec(), status == reqwest::StatusCode::PARTIAL_CONTENT))
+}
+
 /// Download Key.vdf from a repo branch.
 ///
 /// Returns the VDF file content as a string.
@@ -94,6 +358,7 @@ pub async fn download_key_vdf(
 /// Download any text file from a repo branch using raw GitHub URL.
 ///
 /// URL: `https://raw.githubusercontent.com/{repo}/{branch}/{filename}`
+/// Network errors and 5xx/429 responses are retried with backoff, up to `TEXT_FETCH_ATTEMPTS` tries.
 pub async fn download_repo_text_file(
     client: &Client,
     repo: &str,
@@ -101,6 +366,34 @@ pub async fn download_repo_text_file(
     filename: &str,
     token: Option<&str>,
 ) -> Result<String, String> {
+    let mut attempt = 1;
+    loop {
+        match fetch_repo_text_file(client, repo, branch, filename, token).await {
+            Err((e, true)) if attempt < TEXT_FETCH_ATTEMPTS => {
+                let delay = TEXT_FETCH_RETRY_DELAY * 2u32.pow(attempt - 1);
+                eprintln!(
+                    "[ManifestDownloader] {} (attempt {}/{}), retrying in {}ms",
+                    e,
+                    attempt,
+                    TEXT_FETCH_ATTEMPTS,
+                    delay.as_millis()
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result.map_err(|(e, _)| e),
+        }
+    }
+}
+
+/// One attempt of `download_repo_text_file`. Errors carry whether a retry could help.
+async fn fetch_repo_text_file(
+    client: &Client,
+    repo: &str,
+    branch: &str,
+    filename: &str,
+    token: Option<&str>,
+) -> Result<String, (String, bool)> {
     let url = format!(
         "https://raw.githubusercontent.com/{}/{}/{}",
         repo, branch, filename
@@ -111,19 +404,96 @@ pub async fn download_repo_text_file(
         .headers(build_auth_header(token))
         .send()
         .await
-        .map_err(|e| format!("Failed to download {}: {}", filename, e))?;
+        .map_err(|e| (format!("Failed to download {}: {}", filename, e), true))?;
 
-    if !response.status().is_success() {
-        return Err(format!(
-            "Failed to download {}: {} {}",
-            filename,
-            response.status(),
-            response.status().canonical_reason().unwrap_or("")
+    let status = response.status();
+    if !status.is_success() {
+        let transient = status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+        return Err((
+            format!(
+                "Failed to download {}: {} {}",
+                filename,
+                status,
+                status.canonical_reason().unwrap_or("")
+            ),
+            transient,
         ));
     }
 
     response
         .text()
         .await
-        .map_err(|e| format!("Failed to read text response for {}: {}", filename, e))
+        .map_err(|e| (format!("Failed to read text response for {}: {}", filename, e), true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    #[test]
+    fn gunzip_round_trips_gzipped_body() {
+        let original: Vec<u8> = (0..4096u32).map(|i| (i % 251) as u8).collect();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&original).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        assert!(gzipped.starts_with(&[0x1f, 0x8b]));
+
+        assert_eq!(gunzip_if_compressed(&gzipped), Ok(original));
+    }
+
+    #[test]
+    fn gunzip_passes_plain_bytes_through() {
+        let plain = b"\xd0\x17\xf8\x71 not gzipped".to_vec();
+        assert_eq!(gunzip_if_compressed(&plain), Ok(plain.clone()));
+        assert_eq!(gunzip_if_compressed(&[]), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn gunzip_rejects_truncated_gzip() {
+        assert!(gunzip_if_compressed(&[0x1f, 0x8b, 0x08]).is_err());
+    }
+
+    #[test]
+    fn manifest_filename_round_trips_through_pattern() {
+        let patterns = [
+            DEFAULT_MANIFEST_FILENAME_PATTERN,
+            "depot {depot_id} - {manifest_id}.bin",
+            "{manifest_id}.{depot_id}",
+        ];
+        for pattern in patterns {
+            let name = manifest_filename(pattern, "228988", "6645201662696499616");
+            assert_eq!(
+                parse_manifest_filename(pattern, &name),
+                Some(("228988".to_string(), "6645201662696499616".to_string()))
+            );
+        }
+        assert_eq!(manifest_filename(DEFAULT_MANIFEST_FILENAME_PATTERN, "1", "2"), "1_2.manifest");
+        assert_eq!(parse_manifest_filename(DEFAULT_MANIFEST_FILENAME_PATTERN, "228988_abc.manifest"), None);
+        assert_eq!(parse_manifest_filename(DEFAULT_MANIFEST_FILENAME_PATTERN, "x228988_1.manifest"), None);
+    }
+
+    #[test]
+    fn normalize_strips_digit_group_separators() {
+        assert_eq!(normalize_manifest_id("6,645,201,662,696,499,616"), Ok("6645201662696499616".to_string()));
+        assert_eq!(normalize_manifest_id("6_645_201_662_696_499_616"), Ok("6645201662696499616".to_string()));
+        assert_eq!(normalize_manifest_id("6'645'201'662'696'499'616"), Ok("6645201662696499616".to_string()));
+    }
+
+    #[test]
+    fn normalize_strips_whitespace_padding() {
+        assert_eq!(normalize_manifest_id("  6645201662696499616\n"), Ok("6645201662696499616".to_string()));
+        assert_eq!(normalize_manifest_id("\t6 645 201 662 696 499 616 "), Ok("6645201662696499616".to_string()));
+    }
+
+    #[test]
+    fn normalize_rejects_non_digit_input() {
+        assert!(normalize_manifest_id("abc123").unwrap_err().contains("non-digit"));
+        assert!(normalize_manifest_id("-42").unwrap_err().contains("non-digit"));
+        assert!(normalize_manifest_id("6.6452E+18").unwrap_err().contains("scientific notation"));
+        assert!(normalize_manifest_id(" , ").is_err());
+        assert!(normalize_manifest_id("99999999999999999999").unwrap_err().contains("too large"));
+    }
 }
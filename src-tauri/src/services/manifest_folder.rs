@@ -0,0 +1,80 @@
+use regex::Regex;
+use serde::Serialize;
+use std::path::Path;
+
+use crate::services::manifest_parser;
+use crate::services::multi_repo_search::MANIFEST_FILENAME_PATTERN;
+
+/// A manifest file found in a local folder.
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderManifest {
+    pub depot_id: String,
+    pub manifest_id: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestFolderScan {
+    pub manifests: Vec<FolderManifest>,
+    /// `.manifest` files whose ids couldn't be determined
+    pub unrecognized: Vec<String>,
+}
+
+/// Find the manifests in a folder (e.g. a backup), recursing into subfolders.
+///
+/// Ids come from the `{depot_id}_{manifest_id}.manifest` name repos use; files named
+/// any other way are identified from their own metadata instead. Blocking.
+pub fn scan_manifest_folder(dir: &Path) -> Result<ManifestFolderScan, String> {
+    if !dir.is_dir() {
+        return Err(format!("Not a folder: {}", dir.to_string_lossy()));
+    }
+
+    let name_re = Regex::new(MANIFEST_FILENAME_PATTERN).unwrap();
+    let mut scan = ManifestFolderScan {
+        manifests: Vec::new(),
+        unrecognized: Vec::new(),
+    };
+    visit(dir, &name_re, &mut scan);
+
+    scan.manifests.sort_by_key(|m| {
+        (m.depot_id.parse::<u64>().unwrap_or(u64::MAX), m.manifest_id.parse::<u64>().unwrap_or(u64::MAX))
+    });
+    Ok(scan)
+}
+
+fn visit(dir: &Path, name_re: &Regex, scan: &mut ManifestFolderScan) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            visit(&path, name_re, scan);
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.to_lowercase().ends_with(".manifest") {
+            continue;
+        }
+        let path_str = path.to_string_lossy().to_string();
+
+        let ids = match name_re.captures(&name) {
+            Some(caps) => Some((caps[1].to_string(), caps[2].to_string())),
+            None => std::fs::read(&path)
+                .ok()
+                .and_then(|data| manifest_parser::parse_manifest(&data).ok())
+                .map(|m| (m.depot_id.to_string(), m.manifest_id.to_string())),
+        };
+
+        match ids {
+            Some((depot_id, manifest_id)) => scan.manifests.push(FolderManifest {
+                depot_id,
+                manifest_id,
+                path: path_str,
+            }),
+            None => scan.unrecognized.push(path_str),
+        }
+    }
+}
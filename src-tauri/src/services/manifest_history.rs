@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+
+use crate::services::atomic_file;
+use crate::services::multi_repo_search::RepoManifests;
+
+/// Oldest snapshots are dropped once this many repo/app pairs are stored
+const MAX_HISTORY_ENTRIES: usize = 500;
+
+/// Serializes read-modify-write of the history file between concurrent commands
+static HISTORY_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// The manifests one branch tree held, keyed by depot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeSnapshot {
+    pub sha: String,
+    /// Unix seconds when this tree was first seen
+    pub seen_at: i64,
+    /// Depot id -> sorted manifest ids
+    pub manifests: BTreeMap<String, Vec<String>>,
+}
+
+/// The latest tree seen for a repo/app, plus the one before it.
+/// `previous` only moves when the tree SHA changes, so re-fetching the same tree keeps the last diff.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoHistory {
+    pub current: TreeSnapshot,
+    pub previous: Option<TreeSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangedDepot {
+    pub depot_id: String,
+    pub added_manifests: Vec<String>,
+    pub removed_manifests: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestDiff {
+    /// `None` when the branch has only been seen with one tree so far
+    pub previous_sha: Option<String>,
+    pub previous_seen_at: Option<i64>,
+    pub current_sha: String,
+    pub current_seen_at: i64,
+    /// Depots with manifests now that had none before, with their manifest ids
+    pub added: BTreeMap<String, Vec<String>>,
+    /// Depots that had manifests before and have none now
+    pub removed: BTreeMap<String, Vec<String>>,
+    pub changed: Vec<ChangedDepot>,
+}
+
+fn history_path(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join("manifest_history.json")
+}
+
+fn history_key(repo: &str, app_id: &str) -> String {
+    format!("{}#{}", repo.to_lowercase(), app_id)
+}
+
+async fn load_history(app_data_dir: &Path) -> BTreeMap<String, RepoHistory> {
+    match tokio::fs::read_to_string(history_path(app_data_dir)).await {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            eprintln!("[ManifestHistory] Ignoring unreadable history file: {}", e);
+            BTreeMap::new()
+        }),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+async fn save_history(app_data_dir: &Path, history: &BTreeMap<String, RepoHistory>) -> Result<(), String> {
+    tokio::fs::create_dir_all(app_data_dir)
+        .await
+        .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    // Compact on purpose; this file is never meant to be read by hand
+    let content = serde_json::to_vec(history).map_err(|e| format!("Failed to serialize manifest history: {}", e))?;
+    atomic_file::write_atomic_async(&history_path(app_data_dir), content)
+        .await
+        .map_err(|e| format!("Failed to write manifest history: {}", e))
+}
+
+/// The manifest-id set of a fetched tree.
+pub fn snapshot_of(sha: &str, manifests: &RepoManifests) -> TreeSnapshot {
+    let mut by_depot: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for m in &manifests.manifests {
+        by_depot.entry(m.depot_id.clone()).or_default().push(m.manifest_id.clone());
+    }
+    for ids in by_depot.values_mut() {
+        ids.sort();
+        ids.dedup();
+    }
+
+    TreeSnapshot {
+        sha: sha.to_string(),
+        seen_at: chrono::Utc::now().timestamp(),
+        manifests: by_depot,
+    }
+}
+
+/// Store `snapshot` as the latest tree for the repo/app and return the updated history.
+pub async fn record_snapshot(
+    app_data_dir: &Path,
+    repo: &str,
+    app_id: &str,
+    snapshot: TreeSnapshot,
+) -> Result<RepoHistory, String> {
+    let _guard = HISTORY_LOCK.lock().await;
+    let mut history = load_history(app_data_dir).await;
+    let key = history_key(repo, app_id);
+
+    let entry = match history.remove(&key) {
+        Some(existing) if existing.current.sha == snapshot.sha => existing,
+        Some(existing) => RepoHistory {
+            current: snapshot,
+            previous: Some(existing.current),
+        },
+        None => RepoHistory {
+            current: snapshot,
+            previous: None,
+        },
+    };
+    history.insert(key, entry.clone());
+
+    while history.len() > MAX_HISTORY_ENTRIES {
+        let oldest = history
+            .iter()
+            .min_by_key(|(_, h)| h.current.seen_at)
+            .map(|(k, _)| k.clone());
+        match oldest {
+            Some(k) => history.remove(&k),
+            None => break,
+        };
+    }
+
+    save_history(app_data_dir, &history).await?;
+    Ok(entry)
+}
+
+/// What changed between the previous and current tree of a history entry.
+pub fn diff_history(entry: &RepoHistory) -> ManifestDiff {
+    let current = &entry.current;
+    let mut diff = ManifestDiff {
+        previous_sha: entry.previous.as_ref().map(|p| p.sha.clone()),
+        previous_seen_at: entry.previous.as_ref().map(|p| p.seen_at),
+        current_sha: current.sha.clone(),
+        current_seen_at: current.seen_at,
+        added: BTreeMap::new(),
+        removed: BTreeMap::new(),
+        changed: Vec::new(),
+    };
+
+    let Some(previous) = &entry.previous else {
+        return diff;
+    };
+
+    for (depot_id, ids) in &current.manifests {
+        match previous.manifests.get(depot_id) {
+            None => {
+                diff.added.insert(depot_id.clone(), ids.clone());
+            }
+            Some(old_ids) if old_ids != ids => diff.changed.push(ChangedDepot {
+                depot_id: depot_id.clone(),
+                added_manifests: ids.iter().filter(|id| !old_ids.contains(id)).cloned().collect(),
+                removed_manifests: old_ids.iter().filter(|id| !ids.contains(id)).cloned().collect(),
+            }),
+            Some(_) => {}
+        }
+    }
+
+    for (depot_id, ids) in &previous.manifests {
+        if !current.manifests.contains_key(depot_id) {
+            diff.removed.insert(depot_id.clone(), ids.clone());
+        }
+    }
+
+    diff
+}
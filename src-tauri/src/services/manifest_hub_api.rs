@@ -1,12 +1,26 @@
 use reqwest::Client;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::fs;
 
-/// Download a manifest file from the ManifestHub API.
+use crate::services::manifest_downloader::{self, ManifestFetchOptions};
+use crate::services::manifest_parser;
+
+/// The ManifestHub API host used when no other hosts are configured.
+pub const DEFAULT_HOST: &str = "https://api.manifesthub1.filegear-sg.me";
+
+/// Tries per host for network errors and 5xx responses before moving to the next host
+const ATTEMPTS_PER_HOST: u32 = 2;
+
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Download a manifest file from the ManifestHub API, trying each of `options.hosts` in order
+/// until one serves a valid manifest. Returns the saved file and the host that served it.
 ///
-/// API URL: `https://api.manifesthub1.filegear-sg.me/manifest?apikey={key}&depotid={depot_id}&manifestid={manifest_id}`
+/// API URL: `{host}/manifest?apikey={key}&depotid={depot_id}&manifestid={manifest_id}`
 ///
-/// Important: Buffer the response body once to avoid consuming it twice (learned from the Electron bug).
+/// The body is checked to parse as a manifest before anything is written, so a mirror that
+/// answers with an error page moves on to the next host instead of leaving a broken file.
 pub async fn download_from_manifest_hub(
     client: &Client,
     app_id: &str,
@@ -14,13 +28,9 @@ pub async fn download_from_manifest_hub(
     manifest_id: &str,
     output_dir: &Path,
     api_key: &str,
-) -> Result<PathBuf, String> {
-    let url = format!(
-        "https://api.manifesthub1.filegear-sg.me/manifest?apikey={}&depotid={}&manifestid={}",
-        api_key, depot_id, manifest_id
-    );
-
-    let filename = format!("{}_{}.manifest", depot_id, manifest_id);
+    options: &ManifestFetchOptions<'_>,
+) -> Result<(PathBuf, String), String> {
+    let filename = manifest_downloader::manifest_filename(options.filename_pattern, depot_id, manifest_id);
 
     // Ensure output directory exists
     fs::create_dir_all(output_dir)
@@ -29,26 +39,94 @@ pub async fn download_from_manifest_hub(
 
     let output_path = output_dir.join(&filename);
 
+    let default_hosts = [DEFAULT_HOST.to_string()];
+    let hosts = if options.hosts.is_empty() { &default_hosts[..] } else { options.hosts };
+
+    let mut errors = Vec::new();
+    for host in hosts {
+        match fetch_from_host(client, host, depot_id, manifest_id, api_key).await {
+            Ok(bytes) => {
+                // Write binary response to file
+                fs::write(&output_path, &bytes)
+                    .await
+                    .map_err(|e| format!("Failed to write manifest file: {}", e))?;
+
+                // app_id is available for context but not needed in the URL
+                let _ = app_id;
+
+                return Ok((output_path, host.clone()));
+            }
+            Err(e) => {
+                eprintln!("[ManifestHub] {} failed for depot {}: {}", host, depot_id, e);
+                errors.push(format!("{}: {}", host, e));
+            }
+        }
+    }
+
+    Err(format!(
+        "ManifestHub API failed for depot {} on every host ({})",
+        depot_id,
+        errors.join("; ")
+    ))
+}
+
+/// Fetch and validate one manifest from one host, retrying transient failures.
+async fn fetch_from_host(
+    client: &Client,
+    host: &str,
+    depot_id: &str,
+    manifest_id: &str,
+    api_key: &str,
+) -> Result<Vec<u8>, String> {
+    let url = format!(
+        "{}/manifest?apikey={}&depotid={}&manifestid={}",
+        host.trim_end_matches('/'),
+        api_key,
+        depot_id,
+        manifest_id
+    );
+
+    let mut attempt = 1;
+    loop {
+        match request_manifest(client, &url).await {
+            Ok(bytes) => return Ok(bytes),
+            Err((message, transient)) => {
+                if !transient || attempt >= ATTEMPTS_PER_HOST {
+                    return Err(message);
+                }
+            }
+        }
+        tokio::time::sleep(RETRY_DELAY * attempt).await;
+        attempt += 1;
+    }
+}
+
+/// One request. Errors carry whether retrying the same host might help.
+///
+/// Important: Buffer the response body once to avoid consuming it twice (learned from the Electron bug).
+async fn request_manifest(client: &Client, url: &str) -> Result<Vec<u8>, (String, bool)> {
     let response = client
-        .get(&url)
+        .get(url)
         .header("User-Agent", "SteamManifestDownloader")
         .send()
         .await
-        .map_err(|e| format!("ManifestHub API request failed for depot {}: {}", depot_id, e))?;
+        .map_err(|e| (format!("request failed: {}", e), true))?;
 
-    if !response.status().is_success() {
-        let status = response.status();
+    let status = response.status();
+    if !status.is_success() {
         let error_text = response.text().await.unwrap_or_default();
-        return Err(format!(
-            "ManifestHub API error for depot {}: {} {}{}",
-            depot_id,
-            status,
-            status.canonical_reason().unwrap_or(""),
-            if error_text.is_empty() {
-                String::new()
-            } else {
-                format!(" - {}", error_text)
-            }
+        return Err((
+            format!(
+                "{} {}{}",
+                status,
+                status.canonical_reason().unwrap_or(""),
+                if error_text.is_empty() {
+                    String::new()
+                } else {
+                    format!(" - {}", error_text)
+                }
+            ),
+            status.is_server_error() || status.as_u16() == 429,
         ));
     }
 
@@ -63,7 +141,7 @@ pub async fn download_from_manifest_hub(
     let bytes = response
         .bytes()
         .await
-        .map_err(|e| format!("Failed to read ManifestHub response body: {}", e))?;
+        .map_err(|e| (format!("failed to read response body: {}", e), true))?;
 
     // Check if the response is a JSON error
     if content_type.contains("application/json") {
@@ -73,18 +151,16 @@ pub async fn download_from_manifest_hub(
                 .or_else(|| json.get("message"))
                 .and_then(|v| v.as_str());
             if let Some(msg) = error_msg {
-                return Err(format!("ManifestHub API: {}", msg));
+                return Err((msg.to_string(), false));
             }
         }
     }
 
-    // Write binary response to file
-    fs::write(&output_path, &bytes)
-        .await
-        .map_err(|e| format!("Failed to write manifest file: {}", e))?;
-
-    // app_id is available for context but not needed in the URL
-    let _ = app_id;
+    let bytes = manifest_downloader::gunzip_if_compressed(&bytes).map_err(|e| (e, false))?;
+    if let Err(e) = manifest_parser::parse_manifest(&bytes) {
+        let kind = if content_type.is_empty() { "unknown content" } else { content_type.as_str() };
+        return Err((format!("response is not a manifest ({}): {}", kind, e), false));
+    }
 
-    Ok(output_path)
+    Ok(bytes)
 }
@@ -0,0 +1,384 @@
+use aes::cipher::{BlockDecrypt, KeyInit};
+use aes::Aes256;
+use base64::Engine;
+use serde::Serialize;
+use std::io::Read;
+
+use crate::services::vdf_parser::hex_decode;
+
+/// Section magics of the binary depot manifest format.
+pub(crate) const PAYLOAD_MAGIC: u32 = 0x71F617D0;
+pub(crate) const METADATA_MAGIC: u32 = 0x1F4812BE;
+const SIGNATURE_MAGIC: u32 = 0x1B81B817;
+const END_MAGIC: u32 = 0x32C415AB;
+
+/// `EDepotFileFlag::Directory`
+const FLAG_DIRECTORY: u32 = 0x40;
+
+/// One chunk of a file in a depot manifest.
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestChunk {
+    /// SHA-1 of the uncompressed chunk data (hex)
+    pub sha: String,
+    pub crc: u32,
+    pub offset: u64,
+    pub cb_original: u32,
+    pub cb_compressed: u32,
+}
+
+/// One file or directory entry in a depot manifest.
+#[derive(Debug, Clone, Serialize)]
+pub struct ManifestFile {
+    /// Relative path with `/` separators (still base64 while filenames are encrypted)
+    pub filename: String,
+    pub size: u64,
+    pub flags: u32,
+    /// SHA-1 of the whole file (hex)
+    pub sha_content: String,
+    pub chunks: Vec<ManifestChunk>,
+    pub link_target: Option<String>,
+}
+
+impl ManifestFile {
+    pub fn is_directory(&self) -> bool {
+        self.flags & FLAG_DIRECTORY != 0
+    }
+}
+
+/// A parsed Steam depot manifest (the file DDM takes via `-manifestfile`).
+#[derive(Debug, Clone, Serialize)]
+pub struct DepotManifest {
+    pub depot_id: u32,
+    pub manifest_id: u64,
+    pub creation_time: u32,
+    pub filenames_encrypted: bool,
+    /// Total uncompressed size of all files
+    pub cb_disk_original: u64,
+    pub cb_disk_compressed: u64,
+    pub unique_chunks: u32,
+    pub files: Vec<ManifestFile>,
+}
+
+impl DepotManifest {
+    /// Number of chunks across all files.
+    pub fn total_chunks(&self) -> usize {
+        self.files.iter().map(|f| f.chunks.len()).sum()
+    }
+
+    /// Decrypt file names and link targets with the depot key (64 hex chars).
+    /// Does nothing if the names are already in clear text.
+    pub fn decrypt_filenames(&mut self, depot_key_hex: &str) -> Result<(), String> {
+        if !self.filenames_encrypted {
+            return Ok(());
+        }
+
+        let key = hex_decode(depot_key_hex.trim())
+            .filter(|k| k.len() == 32)
+            .ok_or_else(|| "Depot key must be 64 hex characters".to_string())?;
+        let cipher = Aes256::new_from_slice(&key)
+            .map_err(|e| format!("Invalid depot key: {}", e))?;
+
+        for file in &mut self.files {
+            file.filename = decrypt_name(&cipher, &file.filename)?;
+            if let Some(ref target) = file.link_target {
+                if !target.is_empty() {
+                    file.link_target = Some(decrypt_name(&cipher, target)?);
+                }
+            }
+        }
+
+        self.filenames_encrypted = false;
+        Ok(())
+    }
+}
+
+/// Encrypted names tried by `validate_key_against_manifest`. A wrong key gets past the
+/// padding and UTF-8 checks of one name by chance, but not of several.
+const KEY_CHECK_NAMES: usize = 4;
+
+/// Whether `depot_key` is the key the manifest's file names were encrypted with, found by
+/// test-decrypting a few of them. `None` when the names aren't encrypted, so there is
+/// nothing to check the key against.
+pub fn validate_key_against_manifest(manifest_path: &std::path::Path, depot_key: &str) -> Result<Option<bool>, String> {
+    let data = std::fs::read(manifest_path)
+        .map_err(|e| format!("Failed to read manifest {:?}: {}", manifest_path, e))?;
+    let manifest = parse_manifest(&data)?;
+    if !manifest.filenames_encrypted || manifest.files.is_empty() {
+        return Ok(None);
+    }
+
+    let key = hex_decode(depot_key.trim())
+        .filter(|k| k.len() == 32)
+        .ok_or_else(|| "Depot key must be 64 hex characters".to_string())?;
+    let cipher = Aes256::new_from_slice(&key)
+        .map_err(|e| format!("Invalid depot key: {}", e))?;
+
+    let all_decrypt = manifest
+        .files
+        .iter()
+        .take(KEY_CHECK_NAMES)
+        .all(|f| decrypt_name(&cipher, &f.filename).is_ok());
+    Ok(Some(all_decrypt))
+}
+
+/// Parse a depot manifest, accepting both the raw binary form and the zip
+/// the Steam CDN serves it in.
+pub fn parse_manifest(data: &[u8]) -> Result<DepotManifest, String> {
+    if data.starts_with(b"PK") {
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data))
+            .map_err(|e| format!("Failed to open zipped manifest: {}", e))?;
+        let mut entry = archive
+            .by_index(0)
+            .map_err(|e| format!("Zipped manifest is empty: {}", e))?;
+        let mut inner = Vec::new();
+        entry
+            .read_to_end(&mut inner)
+            .map_err(|e| format!("Failed to unzip manifest: {}", e))?;
+        return parse_sections(&inner);
+    }
+
+    parse_sections(data)
+}
+
+fn parse_sections(data: &[u8]) -> Result<DepotManifest, String> {
+    let mut payload: Option<&[u8]> = None;
+    let mut metadata: Option<&[u8]> = None;
+    let mut pos = 0;
+
+    loop {
+        let magic = read_u32_le(data, pos).ok_or("Manifest ended before the end marker")?;
+        pos += 4;
+        if magic == END_MAGIC {
+            break;
+        }
+
+        let len = read_u32_le(data, pos).ok_or("Truncated manifest section header")? as usize;
+        pos += 4;
+        let section = data
+            .get(pos..pos + len)
+            .ok_or("Manifest section runs past the end of the file")?;
+        pos += len;
+
+        match magic {
+            PAYLOAD_MAGIC => payload = Some(section),
+            METADATA_MAGIC => metadata = Some(section),
+            SIGNATURE_MAGIC => {}
+            other => return Err(format!("Unknown manifest section magic 0x{:08X}", other)),
+        }
+    }
+
+    let metadata = metadata.ok_or("Manifest has no metadata section")?;
+    let payload = payload.ok_or("Manifest has no payload section")?;
+
+    let mut manifest = parse_metadata(metadata)?;
+    for field in ProtoReader::new(payload) {
+        if let (1, Value::Bytes(mapping)) = field? {
+            manifest.files.push(parse_file_mapping(mapping)?);
+        }
+    }
+
+    Ok(manifest)
+}
+
+/// Parse only the metadata section body (ids, sizes, chunk count), leaving `files` empty.
+/// Enough to size a depot without its file list.
+pub fn parse_metadata(metadata: &[u8]) -> Result<DepotManifest, String> {
+    let mut manifest = DepotManifest {
+        depot_id: 0,
+        manifest_id: 0,
+        creation_time: 0,
+        filenames_encrypted: false,
+        cb_disk_original: 0,
+        cb_disk_compressed: 0,
+        unique_chunks: 0,
+        files: Vec::new(),
+    };
+
+    for field in ProtoReader::new(metadata) {
+        match field? {
+            (1, Value::Varint(v)) => manifest.depot_id = v as u32,
+            (2, Value::Varint(v)) => manifest.manifest_id = v,
+            (3, Value::Varint(v)) => manifest.creation_time = v as u32,
+            (4, Value::Varint(v)) => manifest.filenames_encrypted = v != 0,
+            (5, Value::Varint(v)) => manifest.cb_disk_original = v,
+            (6, Value::Varint(v)) => manifest.cb_disk_compressed = v,
+            (7, Value::Varint(v)) => manifest.unique_chunks = v as u32,
+            _ => {}
+        }
+    }
+
+    Ok(manifest)
+}
+
+fn parse_file_mapping(data: &[u8]) -> Result<ManifestFile, String> {
+    let mut file = ManifestFile {
+        filename: String::new(),
+        size: 0,
+        flags: 0,
+        sha_content: String::new(),
+        chunks: Vec::new(),
+        link_target: None,
+    };
+
+    for field in ProtoReader::new(data) {
+        match field? {
+            (1, Value::Bytes(b)) => file.filename = normalize_separators(&String::from_utf8_lossy(b)),
+            (2, Value::Varint(v)) => file.size = v,
+            (3, Value::Varint(v)) => file.flags = v as u32,
+            (5, Value::Bytes(b)) => file.sha_content = hex_encode(b),
+            (6, Value::Bytes(b)) => file.chunks.push(parse_chunk(b)?),
+            (7, Value::Bytes(b)) => file.link_target = Some(String::from_utf8_lossy(b).to_string()),
+            _ => {}
+        }
+    }
+
+    // Keep chunks in file order so callers can walk them sequentially
+    file.chunks.sort_by_key(|c| c.offset);
+    Ok(file)
+}
+
+fn parse_chunk(data: &[u8]) -> Result<ManifestChunk, String> {
+    let mut chunk = ManifestChunk {
+        sha: String::new(),
+        crc: 0,
+        offset: 0,
+        cb_original: 0,
+        cb_compressed: 0,
+    };
+
+    for field in ProtoReader::new(data) {
+        match field? {
+            (1, Value::Bytes(b)) => chunk.sha = hex_encode(b),
+            (2, Value::Fixed32(v)) => chunk.crc = v,
+            (3, Value::Varint(v)) => chunk.offset = v,
+            (4, Value::Varint(v)) => chunk.cb_original = v as u32,
+            (5, Value::Varint(v)) => chunk.cb_compressed = v as u32,
+            _ => {}
+        }
+    }
+
+    Ok(chunk)
+}
+
+/// Decrypt one base64 filename: AES-256-ECB encrypted IV followed by AES-256-CBC data.
+fn decrypt_name(cipher: &Aes256, encoded: &str) -> Result<String, String> {
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| format!("Failed to decode encrypted filename: {}", e))?;
+    if data.len() < 32 || data.len() % 16 != 0 {
+        return Err("Encrypted filename has an invalid length".to_string());
+    }
+
+    let mut iv = aes::Block::clone_from_slice(&data[..16]);
+    cipher.decrypt_block(&mut iv);
+
+    let mut plain = Vec::with_capacity(data.len() - 16);
+    let mut prev = iv;
+    for block in data[16..].chunks(16) {
+        let mut b = aes::Block::clone_from_slice(block);
+        cipher.decrypt_block(&mut b);
+        plain.extend(b.iter().zip(prev.iter()).map(|(x, y)| x ^ y));
+        prev = aes::Block::clone_from_slice(block);
+    }
+
+    // Strip PKCS7 padding
+    let pad = *plain.last().unwrap_or(&0) as usize;
+    if pad == 0 || pad > 16 || pad > plain.len() {
+        return Err("Wrong depot key or corrupt filename (bad padding)".to_string());
+    }
+    plain.truncate(plain.len() - pad);
+
+    let name = String::from_utf8(plain)
+        .map_err(|_| "Wrong depot key or corrupt filename (not UTF-8)".to_string())?;
+    Ok(normalize_separators(name.trim_end_matches('\0')))
+}
+
+fn normalize_separators(name: &str) -> String {
+    name.replace('\\', "/")
+}
+
+// ---------------------------------------------------------------------------
+// Minimal protobuf wire-format reader
+// ---------------------------------------------------------------------------
+
+pub(crate) enum Value<'a> {
+    Varint(u64),
+    Fixed64,
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+pub(crate) struct ProtoReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    failed: bool,
+}
+
+impl<'a> ProtoReader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0, failed: false }
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.data.get(self.pos)?;
+            self.pos += 1;
+            value |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+}
+
+impl<'a> Iterator for ProtoReader<'a> {
+    type Item = Result<(u64, Value<'a>), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.pos >= self.data.len() {
+            return None;
+        }
+
+        let field = (|| {
+            let key = self.varint()?;
+            let value = match key & 0x7 {
+                0 => Value::Varint(self.varint()?),
+                1 => {
+                    self.take(8)?;
+                    Value::Fixed64
+                }
+                2 => {
+                    let len = self.varint()? as usize;
+                    Value::Bytes(self.take(len)?)
+                }
+                5 => Value::Fixed32(u32::from_le_bytes(self.take(4)?.try_into().ok()?)),
+                _ => return None,
+            };
+            Some((key >> 3, value))
+        })();
+
+        match field {
+            Some(f) => Some(Ok(f)),
+            None => {
+                self.failed = true;
+                Some(Err("Corrupt manifest: invalid protobuf data".to_string()))
+            }
+        }
+    }
+}
+
+pub(crate) fn read_u32_le(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
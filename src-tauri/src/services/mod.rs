@@ -11,8 +11,30 @@ pub mod depot_runner;
 pub mod steam_store_api;
 pub mod settings;
 pub mod embedded_tools;
+pub mod staging;
+pub mod doh_resolver;
+pub mod file_hasher;
+pub mod config_bundle;
+pub mod public_appinfo;
+pub mod manifest_parser;
+pub mod depot_state;
+pub mod steam_library;
+pub mod output_layout;
+pub mod atomic_file;
+pub mod depot_table_parser;
+pub mod disk_space;
+pub mod manifest_folder;
+pub mod trash;
+pub mod manifest_history;
+pub mod shutdown;
+pub mod key_backup;
+pub mod ddm_appinfo;
+pub mod folder_collision;
+pub mod file_manager;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tauri::AppHandle;
@@ -23,32 +45,198 @@ pub struct AppState {
     pub active_jobs: Arc<Mutex<HashMap<String, JobInfo>>>,
     pub http_client: reqwest::Client,
     pub steam_cache: Arc<Mutex<HashMap<String, serde_json::Value>>>,
+    /// Job id -> `progress.ndjson` path. A std Mutex because `emit_progress` is synchronous.
+    pub progress_logs: Arc<std::sync::Mutex<HashMap<String, PathBuf>>>,
+    /// Set once the user chose to quit anyway, so the close guard lets the window go.
+    pub close_confirmed: Arc<AtomicBool>,
+    /// Job id -> the job's latest progress event other than output, for `list_jobs`.
+    /// A std Mutex because `emit_progress` is synchronous.
+    pub job_progress: Arc<std::sync::Mutex<HashMap<String, depot_runner::ProgressEvent>>>,
+    /// Jobs whose task is still running, readable without locking `active_jobs`.
+    pub running_jobs: RunningJobs,
 }
 
+/// Maximum number of output lines kept per job for `get_job_output_tail`.
+pub const OUTPUT_TAIL_LINES: usize = 500;
+
 pub struct JobInfo {
+    /// "download" or "hashing"
+    pub kind: String,
     pub status: String,
+    pub app_id: Option<String>,
+    /// Unix seconds
+    pub started_at: i64,
     pub child_pid: Option<u32>,
     pub download_dir: Option<String>,
     #[cfg(target_os = "windows")]
     pub job_object: Option<Arc<depot_runner::win_job::JobObject>>,
+    /// Most recent stdout/stderr lines. Has its own lock so the output readers
+    /// don't contend with status reads on `active_jobs`.
+    pub output_tail: OutputTail,
+    /// Pending deletion of a cancelled job's files, until it actually starts deleting.
+    pub cleanup_task: Option<tokio::task::AbortHandle>,
+    /// Why a cancelled job was cancelled: one of `CANCEL_REASONS`.
+    pub cancel_reason: Option<String>,
+    /// Never delete the download folder on cancel, e.g. a folder the user picked to resume into.
+    pub keep_files_on_cancel: bool,
+}
+
+/// Why a job was cancelled. Only `"shutdown"` leaves a job that's worth resuming;
+/// the user meant `"user"` cancels, and `"error"` ones would likely fail again.
+pub const CANCEL_REASONS: &[&str] = &["user", "shutdown", "error"];
+
+impl JobInfo {
+    pub fn new(download_dir: Option<String>) -> Self {
+        Self {
+            kind: "download".to_string(),
+            status: "running".to_string(),
+            app_id: None,
+            started_at: chrono::Utc::now().timestamp(),
+            child_pid: None,
+            download_dir,
+            #[cfg(target_os = "windows")]
+            job_object: None,
+            output_tail: OutputTail::default(),
+            cleanup_task: None,
+            cancel_reason: None,
+            keep_files_on_cancel: false,
+        }
+    }
+}
+
+/// Count of running job tasks. Each task holds a `RunningJob` for as long as it runs,
+/// so the count can't drift from status changes and is read with a single atomic load.
+#[derive(Clone, Default)]
+pub struct RunningJobs(Arc<AtomicUsize>);
+
+impl RunningJobs {
+    /// Count a job as running until the returned guard is dropped.
+    pub fn start(&self) -> RunningJob {
+        self.0.fetch_add(1, Ordering::SeqCst);
+        RunningJob(self.0.clone())
+    }
+
+    pub fn any(&self) -> bool {
+        self.0.load(Ordering::SeqCst) > 0
+    }
+}
+
+/// Keeps its job counted in `RunningJobs` until dropped.
+pub struct RunningJob(Arc<AtomicUsize>);
+
+impl Drop for RunningJob {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A job's recent output lines, oldest first, capped at `OUTPUT_TAIL_LINES`.
+/// Cheap to clone; clones share the same lines. Take a clone while holding
+/// `active_jobs`, then push or read after releasing it.
+#[derive(Clone, Default)]
+pub struct OutputTail(Arc<std::sync::Mutex<VecDeque<String>>>);
+
+impl OutputTail {
+    /// Append output lines, dropping the oldest ones beyond the cap.
+    pub fn push(&self, lines: &[String]) {
+        let Ok(mut tail) = self.0.lock() else {
+            return;
+        };
+        for line in lines {
+            if tail.len() >= OUTPUT_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line.clone());
+        }
+    }
+
+    /// The last `count` lines, oldest first.
+    pub fn last(&self, count: usize) -> Vec<String> {
+        let Ok(tail) = self.0.lock() else {
+            return Vec::new();
+        };
+        let count = count.min(tail.len());
+        tail.iter().skip(tail.len() - count).cloned().collect()
+    }
 }
 
 impl AppState {
-    pub fn new(app_handle: AppHandle) -> Self {
+    pub fn new(app_handle: AppHandle, settings: &settings::Settings) -> Self {
         Self {
             app_handle,
             active_jobs: Arc::new(Mutex::new(HashMap::new())),
-            http_client: reqwest::Client::new(),
+            http_client: doh_resolver::build_http_client(settings.use_doh),
             steam_cache: Arc::new(Mutex::new(HashMap::new())),
+            progress_logs: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            close_confirmed: Arc::new(AtomicBool::new(false)),
+            job_progress: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            running_jobs: RunningJobs::default(),
         }
     }
 
+    /// Called from the window event handler, so it must not wait on `active_jobs`.
     pub fn has_active_downloads(&self) -> bool {
-        // Use try_lock to avoid blocking the UI thread
-        if let Ok(jobs) = self.active_jobs.try_lock() {
-            jobs.values().any(|j| j.status == "downloading" || j.status == "running")
-        } else {
-            true // Assume active if we can't check
+        self.running_jobs.any()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn running_jobs_tracks_many_concurrent_jobs() {
+        const JOBS: usize = 200;
+        let running = RunningJobs::default();
+        let (release_tx, release_rx) = tokio::sync::watch::channel(false);
+        let (started_tx, mut started_rx) = tokio::sync::mpsc::channel(JOBS);
+
+        let handles: Vec<_> = (0..JOBS)
+            .map(|i| {
+                let guard = running.start();
+                let mut release = release_rx.clone();
+                let started_tx = started_tx.clone();
+                tokio::spawn(async move {
+                    let _guard = guard;
+                    started_tx.send(i).await.unwrap();
+                    release.wait_for(|released| *released).await.unwrap();
+                })
+            })
+            .collect();
+
+        for _ in 0..JOBS {
+            started_rx.recv().await.unwrap();
+        }
+        assert_eq!(running.0.load(Ordering::SeqCst), JOBS);
+        assert!(running.any());
+
+        // Readers never wait, even while every job is mid-flight
+        let readers: Vec<_> = (0..8)
+            .map(|_| {
+                let running = running.clone();
+                std::thread::spawn(move || (0..10_000).all(|_| running.any()))
+            })
+            .collect();
+        for reader in readers {
+            assert!(reader.join().unwrap());
         }
+
+        release_tx.send(true).unwrap();
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert!(!running.any());
+    }
+
+    #[test]
+    fn guard_drop_ends_job_even_on_panic() {
+        let running = RunningJobs::default();
+        let guard = running.start();
+        let result = std::panic::catch_unwind(move || {
+            let _guard = guard;
+            panic!("job task failed");
+        });
+        assert!(result.is_err());
+        assert!(!running.any());
     }
 }
@@ -1,7 +1,8 @@
 use regex::Regex;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::Mutex;
 
 use crate::services::github_api;
 use crate::services::manifest_downloader;
@@ -16,6 +17,9 @@ pub const REPOS: &[&str] = &[
     "PrintedWaste/GameManifests",
 ];
 
+/// Manifest file names as repos store them: `{depot_id}_{manifest_id}.manifest`.
+pub const MANIFEST_FILENAME_PATTERN: &str = r"^(\d+)_(\d+)\.manifest$";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepoResult {
     pub repo: String,
@@ -42,6 +46,7 @@ pub struct ManifestEntry {
     pub depot_id: String,
     pub manifest_id: String,
     pub filename: String,
+    pub path: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,17 +54,100 @@ pub struct ManifestWithKey {
     pub depot_id: String,
     pub manifest_id: String,
     pub filename: String,
+    /// Path within the branch; differs from `filename` when the repo nests manifests in folders
+    pub path: String,
     pub depot_key: Option<String>,
 }
 
+/// All manifest versions a branch holds for one depot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepotManifestVersions {
+    pub depot_id: String,
+    pub manifest_ids: Vec<String>,
+    /// The manifest the branch's lua selects via `setManifestid`, otherwise the first listed.
+    pub default_manifest_id: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepoManifests {
     pub manifests: Vec<ManifestWithKey>,
+    /// `manifests` grouped by depot, for a per-depot version picker.
+    pub depot_versions: Vec<DepotManifestVersions>,
     pub has_key_vdf: bool,
     pub key_vdf_filename: Option<String>,
     pub lua_filename: Option<String>,
     pub files: Vec<String>,
     pub depot_keys: HashMap<String, String>,
+    /// False when the branch was too large to list in full, so manifests may be missing
+    #[serde(default)]
+    pub tree_complete: bool,
+    /// Manifest ids the branch's lua selects that have no `.manifest` file in the tree,
+    /// so downloading them from this branch will fail
+    #[serde(default)]
+    pub dangling_manifests: Vec<DanglingManifest>,
+}
+
+/// A manifest id a repo's lua refers to without the branch holding its file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DanglingManifest {
+    pub depot_id: String,
+    pub manifest_id: String,
+    /// Whether the branch has other manifests for the depot
+    pub depot_has_manifests: bool,
+}
+
+/// GitHub API requests an operation will make. Raw file downloads (manifests, Key.vdf,
+/// lua) go through raw.githubusercontent.com and don't count against the API rate limit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiCostEstimate {
+    pub operation: String,
+    pub requests: usize,
+    /// One line per kind of request, e.g. "5 x branch check"
+    pub breakdown: Vec<String>,
+}
+
+/// Operations `estimate_api_cost` knows.
+pub const API_COST_OPERATIONS: &[&str] = &[
+    "search",
+    "find_any_repo",
+    "get_manifests",
+    "diff_manifests",
+    "depot_coverage",
+    "freshness",
+    "download",
+    "score_repos",
+];
+
+/// How many GitHub API requests `operation` makes. `repo_count` is the number of repos a
+/// search covers (all known repos by default); `has_sha` skips the branch lookup that
+/// manifest listings need when they're only given the app's branch name.
+pub fn estimate_api_cost(operation: &str, repo_count: Option<usize>, has_sha: bool) -> Result<ApiCostEstimate, String> {
+    let branch_lookup = usize::from(!has_sha);
+    let parts: Vec<(usize, &str)> = match operation {
+        "search" | "find_any_repo" => vec![(repo_count.unwrap_or(REPOS.len()), "branch check")],
+        "get_manifests" | "diff_manifests" | "depot_coverage" => {
+            vec![(branch_lookup, "branch lookup"), (1, "tree listing")]
+        }
+        "freshness" => vec![(1, "branch check")],
+        // Only when the job uses the default repo without a SHA; manifests come from raw URLs
+        "download" => vec![(branch_lookup, "branch check")],
+        // Search results carry each branch's SHA, so scoring only lists trees
+        "score_repos" => vec![(repo_count.unwrap_or(REPOS.len()), "tree listing")],
+        _ => {
+            return Err(format!(
+                "Unknown operation: {}. Use one of: {}",
+                operation,
+                API_COST_OPERATIONS.join(", ")
+            ))
+        }
+    };
+
+    let parts: Vec<(usize, &str)> = parts.into_iter().filter(|(n, _)| *n > 0).collect();
+    Ok(ApiCostEstimate {
+        operation: operation.to_string(),
+        requests: parts.iter().map(|(n, _)| n).sum(),
+        breakdown: parts.iter().map(|(n, what)| format!("{} x {}", n, what)).collect(),
+    })
 }
 
 /// Search all repos for an App ID. Checks each repo in parallel for a branch matching the app_id.
@@ -68,14 +156,35 @@ pub async fn search_repos(
     app_id: &str,
     token: Option<&str>,
 ) -> Result<SearchResult, String> {
-    let mut handles = Vec::new();
+    search_repos_streaming(client, app_id, token, |_| {}).await
+}
+
+/// Like `search_repos`, but calls `on_found` with each repo as soon as its branch check
+/// succeeds, so a slow repo doesn't hold back the others. The returned batch is the same.
+pub async fn search_repos_streaming(
+    client: &Client,
+    app_id: &str,
+    token: Option<&str>,
+    mut on_found: impl FnMut(&RepoResult),
+) -> Result<SearchResult, String> {
+    let mut tasks = tokio::task::JoinSet::new();
+    let mut found = Vec::new();
+    let mut github_rate_limited = false;
+
+    for (index, &repo) in REPOS.iter().enumerate() {
+        // Fewer checks in flight while GitHub reports the rate limit running low
+        while tasks.len() >= github_api::adaptive_concurrency() {
+            match tasks.join_next().await {
+                Some(joined) => collect_branch_check(joined, &mut found, &mut github_rate_limited, &mut on_found),
+                None => break,
+            }
+        }
 
-    for &repo in REPOS {
         let client = client.clone();
         let app_id = app_id.to_string();
         let token = token.map(String::from);
 
-        handles.push(tokio::spawn(async move {
+        tasks.spawn(async move {
             let result = github_api::get_branch_info(
                 &client,
                 repo,
@@ -107,23 +216,18 @@ pub async fn search_repos(
                 }
                 Err(_) => None,
             }
-        }));
+            .map(|found| (index, found))
+        });
     }
 
-    let mut found = Vec::new();
-    let mut github_rate_limited = false;
-
-    for handle in handles {
-        if let Ok(Some((result, rate_limited))) = handle.await {
-            if rate_limited {
-                github_rate_limited = true;
-            }
-            if let Some(repo_result) = result {
-                found.push(repo_result);
-            }
-        }
+    while let Some(joined) = tasks.join_next().await {
+        collect_branch_check(joined, &mut found, &mut github_rate_limited, &mut on_found);
     }
 
+    // Back to the fixed repo order so ties in the date sort stay deterministic
+    found.sort_by_key(|(index, _)| *index);
+    let mut found: Vec<RepoResult> = found.into_iter().map(|(_, r)| r).collect();
+
     // Sort by date (newest first), items without dates go to the end
     found.sort_by(|a, b| {
         match (&a.date, &b.date) {
@@ -140,8 +244,162 @@ pub async fn search_repos(
     })
 }
 
+/// (repo order index, (found repo, rate limited)) from one branch check task
+type BranchCheckOutcome = Option<(usize, (Option<RepoResult>, bool))>;
+
+fn collect_branch_check(
+    joined: Result<BranchCheckOutcome, tokio::task::JoinError>,
+    found: &mut Vec<(usize, RepoResult)>,
+    github_rate_limited: &mut bool,
+    on_found: &mut impl FnMut(&RepoResult),
+) {
+    if let Ok(Some((index, (result, rate_limited)))) = joined {
+        if rate_limited {
+            *github_rate_limited = true;
+        }
+        if let Some(repo_result) = result {
+            on_found(&repo_result);
+            found.push((index, repo_result));
+        }
+    }
+}
+
+/// Find the best repo other than `exclude` with a branch for the App ID, i.e. the most
+/// recently updated one. Also returns whether GitHub rate limited any of the checks.
+pub async fn find_alternative_repo(
+    client: &Client,
+    app_id: &str,
+    token: Option<&str>,
+    exclude: &str,
+) -> Result<(Option<RepoResult>, bool), String> {
+    let result = search_repos(client, app_id, token).await?;
+    let best = result
+        .repos
+        .into_iter()
+        .find(|r| !r.repo.eq_ignore_ascii_case(exclude));
+    Ok((best, result.github_rate_limited))
+}
+
+/// How complete a repo's branch is for downloading: how many of its depots have a key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoScore {
+    pub repo: String,
+    pub sha: String,
+    pub date: Option<String>,
+    /// Depots the branch has at least one manifest for
+    pub depot_count: usize,
+    /// Of those, depots with a key from Key.vdf or the lua
+    pub keyed_depots: usize,
+    /// `keyed_depots / depot_count`, 0 for a branch without manifests
+    pub key_coverage: f64,
+    pub has_key_vdf: bool,
+    pub has_lua: bool,
+}
+
+/// Maximum cached scores before the cache is cleared
+const MAX_SCORE_CACHE_SIZE: usize = 200;
+
+/// `repo#sha` -> score. A branch tree never changes for a given SHA, so entries don't expire.
+static SCORE_CACHE: Mutex<BTreeMap<String, RepoScore>> = Mutex::const_new(BTreeMap::new());
+
+/// Score one search result by listing its branch tree (one GitHub API request unless cached).
+pub async fn score_repo(
+    client: &Client,
+    app_id: &str,
+    repo: &RepoResult,
+    token: Option<&str>,
+) -> Result<RepoScore, String> {
+    let sha = repo
+        .sha
+        .as_deref()
+        .ok_or_else(|| format!("No branch SHA for {}", repo.repo))?;
+    let cache_key = format!("{}#{}", repo.repo.to_lowercase(), sha);
+    if let Some(score) = SCORE_CACHE.lock().await.get(&cache_key) {
+        return Ok(score.clone());
+    }
+
+    let manifests = get_repo_manifests(client, app_id, &repo.repo, sha, token).await?;
+    let depot_count = manifests.depot_versions.len();
+    let keyed_depots = manifests
+        .depot_versions
+        .iter()
+        .filter(|d| manifests.depot_keys.contains_key(&d.depot_id))
+        .count();
+
+    let score = RepoScore {
+        repo: repo.repo.clone(),
+        sha: sha.to_string(),
+        date: repo.date.clone(),
+        depot_count,
+        keyed_depots,
+        key_coverage: if depot_count == 0 { 0.0 } else { keyed_depots as f64 / depot_count as f64 },
+        has_key_vdf: manifests.has_key_vdf,
+        has_lua: manifests.lua_filename.is_some(),
+    };
+
+    let mut cache = SCORE_CACHE.lock().await;
+    if cache.len() >= MAX_SCORE_CACHE_SIZE {
+        cache.clear();
+    }
+    cache.insert(cache_key, score.clone());
+    Ok(score)
+}
+
+/// Score the GitHub repos among search results and order them best first: highest key
+/// coverage, then most keyed depots, then most depots. Ties keep the search order (newest first).
+/// Repos that fail to score are left out.
+pub async fn rank_repos(
+    client: &Client,
+    app_id: &str,
+    repos: &[RepoResult],
+    token: Option<&str>,
+) -> Vec<RepoScore> {
+    let mut tasks = tokio::task::JoinSet::new();
+    let mut scores = Vec::new();
+    for (index, repo) in repos.iter().filter(|r| r.source_type == "github").enumerate() {
+        // Fewer repos scored at once while GitHub reports the rate limit running low
+        while tasks.len() >= github_api::adaptive_concurrency() {
+            match tasks.join_next().await {
+                Some(joined) => collect_repo_score(joined, &mut scores),
+                None => break,
+            }
+        }
+
+        let client = client.clone();
+        let app_id = app_id.to_string();
+        let repo = repo.clone();
+        let token = token.map(String::from);
+        tasks.spawn(async move { (index, score_repo(&client, &app_id, &repo, token.as_deref()).await) });
+    }
+
+    while let Some(joined) = tasks.join_next().await {
+        collect_repo_score(joined, &mut scores);
+    }
+
+    scores.sort_by(|(ia, a), (ib, b)| {
+        b.key_coverage
+            .total_cmp(&a.key_coverage)
+            .then(b.keyed_depots.cmp(&a.keyed_depots))
+            .then(b.depot_count.cmp(&a.depot_count))
+            .then(ia.cmp(ib))
+    });
+    scores.into_iter().map(|(_, score)| score).collect()
+}
+
+/// Keep a finished scoring task's result, logging the ones that failed.
+fn collect_repo_score(
+    joined: Result<(usize, Result<RepoScore, String>), tokio::task::JoinError>,
+    scores: &mut Vec<(usize, RepoScore)>,
+) {
+    match joined {
+        Ok((index, Ok(score))) => scores.push((index, score)),
+        Ok((_, Err(e))) => eprintln!("[MultiRepoSearch] Failed to score repo: {}", e),
+        Err(e) => eprintln!("[MultiRepoSearch] Repo scoring task failed: {}", e),
+    }
+}
+
 /// Get manifest file listing from a repo's branch using GitHub Tree API.
-/// Parses tree entries to find `.manifest` files, `Key.vdf`/`key.vdf`, and `.lua` files.
+/// Parses tree entries to find `.manifest` files (at any depth), `Key.vdf`/`key.vdf`, and `.lua` files.
 /// If Key.vdf is found, downloads and parses it. If lua file is found, downloads and parses it.
 pub async fn get_repo_manifests(
     client: &Client,
@@ -150,13 +408,9 @@ pub async fn get_repo_manifests(
     sha: &str,
     token: Option<&str>,
 ) -> Result<RepoManifests, String> {
-    let tree_data = github_api::get_tree(client, repo, sha, token).await?;
-
-    let tree = tree_data["tree"]
-        .as_array()
-        .ok_or("Missing tree array in GitHub response")?;
+    let tree = github_api::list_tree_blobs(client, repo, sha, token).await?;
 
-    let manifest_re = Regex::new(r"^(\d+)_(\d+)\.manifest$").unwrap();
+    let manifest_re = Regex::new(MANIFEST_FILENAME_PATTERN).unwrap();
 
     let mut manifests = Vec::new();
     let mut has_key_vdf = false;
@@ -164,13 +418,8 @@ pub async fn get_repo_manifests(
     let mut lua_filename: Option<String> = None;
     let mut files = Vec::new();
 
-    for item in tree {
-        let item_type = item["type"].as_str().unwrap_or("");
-        if item_type != "blob" {
-            continue;
-        }
-
-        let path = item["path"].as_str().unwrap_or("");
+    for path in &tree.blobs {
+        let path = path.as_str();
         files.push(path.to_string());
 
         // Check for Key.vdf (case-insensitive)
@@ -185,65 +434,90 @@ pub async fn get_repo_manifests(
             lua_filename = Some(path.to_string());
         }
 
-        // Parse manifest filenames like "1995891_3438272076824159257.manifest"
-        if let Some(caps) = manifest_re.captures(path) {
+        // Parse manifest filenames like "1995891_3438272076824159257.manifest",
+        // also when nested (e.g. "manifests/1995891/1995891_3438272076824159257.manifest")
+        let basename = path.rsplit('/').next().unwrap_or(path);
+        if let Some(caps) = manifest_re.captures(basename) {
             manifests.push(ManifestEntry {
                 depot_id: caps[1].to_string(),
                 manifest_id: caps[2].to_string(),
-                filename: path.to_string(),
+                filename: basename.to_string(),
+                path: path.to_string(),
             });
         }
     }
 
-    // Download and parse Key.vdf if present
+    // Key.vdf and the lua file are independent, so fetch them together
+    let key_vdf_fetch = async {
+        match key_vdf_filename {
+            Some(ref vdf_file) => Some(
+                manifest_downloader::download_key_vdf(
+                    client,
+                    app_id,
+                    repo,
+                    sha,
+                    Some(vdf_file.as_str()),
+                    token,
+                )
+                .await,
+            ),
+            None => None,
+        }
+    };
+    let lua_fetch = async {
+        match lua_filename {
+            Some(ref lua_file) => Some(
+                manifest_downloader::download_repo_text_file(client, repo, app_id, lua_file, token).await,
+            ),
+            None => None,
+        }
+    };
+    let (key_vdf_content, lua_content) = tokio::join!(key_vdf_fetch, lua_fetch);
+
+    // Parse Key.vdf if present
     let mut depot_keys: HashMap<String, String> = HashMap::new();
 
-    if has_key_vdf {
-        if let Some(ref vdf_file) = key_vdf_filename {
-            match manifest_downloader::download_key_vdf(
-                client,
-                app_id,
-                repo,
-                sha,
-                Some(vdf_file.as_str()),
-                token,
-            )
-            .await
-            {
-                Ok(vdf_content) => {
-                    depot_keys = vdf_parser::parse_key_vdf(&vdf_content, Some(repo));
+    match key_vdf_content {
+        Some(Ok(vdf_content)) => {
+            depot_keys = vdf_parser::parse_key_vdf(&vdf_content, Some(repo));
+        }
+        Some(Err(e)) => {
+            eprintln!("[MultiRepoSearch] Failed to download Key.vdf from {}: {}", repo, e);
+        }
+        None => {}
+    }
+
+    // Parse lua file if present
+    let mut lua_manifest_ids: HashMap<String, String> = HashMap::new();
+
+    match lua_content {
+        Some(Ok(lua_content)) => {
+            let lua_result = crate::services::lua_parser::parse_lua_file(&lua_content);
+            // Merge lua depot keys into our depot_keys map
+            for depot in &lua_result.depots {
+                if let Some(ref key) = depot.depot_key {
+                    depot_keys.insert(depot.depot_id.to_string(), key.clone());
                 }
-                Err(e) => {
-                    eprintln!("[MultiRepoSearch] Failed to download Key.vdf from {}: {}", repo, e);
+                if let Some(ref manifest_id) = depot.manifest_id {
+                    lua_manifest_ids.insert(depot.depot_id.to_string(), manifest_id.clone());
                 }
             }
         }
+        Some(Err(e)) => {
+            eprintln!("[MultiRepoSearch] Failed to download lua file from {}: {}", repo, e);
+        }
+        None => {}
     }
 
-    // Download and parse lua file if present
-    if let Some(ref lua_file) = lua_filename {
-        match manifest_downloader::download_repo_text_file(
-            client,
-            repo,
+    let depot_versions = group_manifest_versions(&manifests, &lua_manifest_ids);
+    let dangling_manifests = find_dangling_manifests(&manifests, &lua_manifest_ids);
+    if !dangling_manifests.is_empty() {
+        eprintln!(
+            "[MultiRepoSearch] {} lua in {} names {} manifest(s) missing from the branch",
             app_id,
-            lua_file,
-            token,
-        )
-        .await
-        {
-            Ok(lua_content) => {
-                let lua_result = crate::services::lua_parser::parse_lua_file(&lua_content);
-                // Merge lua depot keys into our depot_keys map
-                for depot in &lua_result.depots {
-                    if let Some(ref key) = depot.depot_key {
-                        depot_keys.insert(depot.depot_id.to_string(), key.clone());
-                    }
-                }
-            }
-            Err(e) => {
-                eprintln!("[MultiRepoSearch] Failed to download lua file from {}: {}", repo, e);
-            }
-        }
+            repo,
+            dangling_manifests.len()
+        );
     }
 
     // Combine manifests with depot keys
@@ -255,6 +529,7 @@ pub async fn get_repo_manifests(
                 depot_id: m.depot_id,
                 manifest_id: m.manifest_id,
                 filename: m.filename,
+                path: m.path,
                 depot_key,
             }
         })
@@ -262,10 +537,64 @@ pub async fn get_repo_manifests(
 
     Ok(RepoManifests {
         manifests: manifests_with_keys,
+        depot_versions,
         has_key_vdf,
         key_vdf_filename,
         lua_filename,
         files,
         depot_keys,
+        tree_complete: tree.complete,
+        dangling_manifests,
     })
 }
+
+/// Lua `setManifestid` entries whose `{depot}_{manifest}.manifest` isn't in the tree, by depot id.
+fn find_dangling_manifests(
+    manifests: &[ManifestEntry],
+    lua_manifest_ids: &HashMap<String, String>,
+) -> Vec<DanglingManifest> {
+    let mut dangling: Vec<DanglingManifest> = lua_manifest_ids
+        .iter()
+        .filter(|(depot_id, manifest_id)| {
+            !manifests
+                .iter()
+                .any(|m| &m.depot_id == *depot_id && &m.manifest_id == *manifest_id)
+        })
+        .map(|(depot_id, manifest_id)| DanglingManifest {
+            depot_id: depot_id.clone(),
+            manifest_id: manifest_id.clone(),
+            depot_has_manifests: manifests.iter().any(|m| &m.depot_id == depot_id),
+        })
+        .collect();
+    dangling.sort_by_key(|d| d.depot_id.parse::<u64>().unwrap_or(u64::MAX));
+    dangling
+}
+
+/// Group manifest entries by depot, keeping tree order for both depots and versions.
+fn group_manifest_versions(
+    manifests: &[ManifestEntry],
+    lua_manifest_ids: &HashMap<String, String>,
+) -> Vec<DepotManifestVersions> {
+    let mut groups: Vec<DepotManifestVersions> = Vec::new();
+
+    for entry in manifests {
+        match groups.iter_mut().find(|g| g.depot_id == entry.depot_id) {
+            Some(group) => group.manifest_ids.push(entry.manifest_id.clone()),
+            None => groups.push(DepotManifestVersions {
+                depot_id: entry.depot_id.clone(),
+                manifest_ids: vec![entry.manifest_id.clone()],
+                default_manifest_id: entry.manifest_id.clone(),
+            }),
+        }
+    }
+
+    for group in &mut groups {
+        if let Some(lua_id) = lua_manifest_ids.get(&group.depot_id) {
+            if group.manifest_ids.contains(lua_id) {
+                group.default_manifest_id = lua_id.clone();
+            }
+        }
+    }
+
+    groups
+}
@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+// DDM's own state folder is left where DDM expects it so resume and verify keep working.
+use crate::services::depot_state::STATE_DIR_NAME;
+
+/// How downloaded files are arranged in the job folder once DDM is done.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputLayout {
+    /// Leave DDM's `depots/{depot_id}/{build}/...` tree as is.
+    Native,
+    /// Every depot's files merged into one tree at the top of the job folder.
+    Merged,
+    /// One top-level `{depot_id}/` folder per depot.
+    ByDepot,
+}
+
+impl OutputLayout {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().replace('-', "_").as_str() {
+            "native" | "" => Ok(Self::Native),
+            "merged" => Ok(Self::Merged),
+            "by_depot" => Ok(Self::ByDepot),
+            _ => Err(format!("Unsupported output layout: {}. Use 'native', 'merged' or 'by_depot'.", name)),
+        }
+    }
+}
+
+/// A file in DDM's native layout: `depots/{depot_id}/{build}/{path}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NativeFile {
+    pub depot_id: String,
+    pub build: String,
+    /// Path inside the build folder, `/`-separated
+    pub path: String,
+}
+
+impl NativeFile {
+    fn source(&self) -> String {
+        format!("depots/{}/{}/{}", self.depot_id, self.build, self.path)
+    }
+}
+
+/// One move relative to the job folder, `/`-separated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedMove {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayoutPlan {
+    pub moves: Vec<PlannedMove>,
+    /// Destination paths more than one depot wrote to; the last depot in order wins
+    pub collisions: Vec<String>,
+    /// Destination paths renamed because the job folder already holds that name
+    pub renamed: Vec<String>,
+}
+
+/// Work out the moves that turn the native layout into `layout`. Pure; touches no files.
+///
+/// `depot_order` is the job's depot order. In `Merged`, a later depot overwrites an earlier
+/// one at the same path (as Steam applies language/DLC depots over the base). In `ByDepot`
+/// depots can't collide with each other; if one depot has several builds, the newest
+/// build (last in `files`) wins per path. Either way each collision is reported once.
+///
+/// `root_entries` are the names already in the job folder (`steam.keys`, the manifests,
+/// `progress.ndjson`, `depots`); a destination whose top-level part matches one is renamed
+/// to `{name} (depot {depot_id})` instead of overwriting it.
+pub fn plan_layout(
+    files: &[NativeFile],
+    depot_order: &[String],
+    layout: OutputLayout,
+    root_entries: &[String],
+) -> LayoutPlan {
+    if layout == OutputLayout::Native {
+        return LayoutPlan::default();
+    }
+
+    let rank = |depot_id: &str| {
+        depot_order
+            .iter()
+            .position(|d| d == depot_id)
+            .unwrap_or(depot_order.len())
+    };
+    let mut ordered: Vec<(usize, &NativeFile)> = files.iter().enumerate().collect();
+    ordered.sort_by_key(|(i, f)| (rank(&f.depot_id), *i));
+
+    let mut winners: HashMap<String, usize> = HashMap::new();
+    let mut collisions = Vec::new();
+    let mut moves: Vec<PlannedMove> = Vec::new();
+    let mut renamed = Vec::new();
+
+    for (_, file) in ordered {
+        let mut to = match layout {
+            OutputLayout::Merged => file.path.clone(),
+            OutputLayout::ByDepot => format!("{}/{}", file.depot_id, file.path),
+            OutputLayout::Native => unreachable!(),
+        };
+        if let Some(new_to) = avoid_root_entries(&to, &file.depot_id, root_entries) {
+            renamed.push(std::mem::replace(&mut to, new_to));
+        }
+
+        let key = to.to_lowercase();
+        let mv = PlannedMove { from: file.source(), to };
+        match winners.get(&key) {
+            Some(&index) => {
+                if !collisions.iter().any(|c: &String| c.to_lowercase() == key) {
+                    collisions.push(mv.to.clone());
+                }
+                moves[index] = mv;
+            }
+            None => {
+                winners.insert(key, moves.len());
+                moves.push(mv);
+            }
+        }
+    }
+
+    LayoutPlan { moves, collisions, renamed }
+}
+
+/// The new destination for `to` if its top-level part would land on one of `root_entries`.
+fn avoid_root_entries(to: &str, depot_id: &str, root_entries: &[String]) -> Option<String> {
+    let (top, rest) = match to.split_once('/') {
+        Some((top, rest)) => (top, Some(rest)),
+        None => (to, None),
+    };
+    if !root_entries.iter().any(|e| e.eq_ignore_ascii_case(top)) {
+        return None;
+    }
+
+    // Keep a file's extension last so it still opens with the same program
+    let top = match top.rfind('.') {
+        Some(dot) if dot > 0 && rest.is_none() => format!("{} (depot {}){}", &top[..dot], depot_id, &top[dot..]),
+        _ => format!("{} (depot {})", top, depot_id),
+    };
+    Some(match rest {
+        Some(rest) => format!("{}/{}", top, rest),
+        None => top,
+    })
+}
+
+/// Names of the entries directly in `work_dir`. Blocking.
+pub fn list_root_entries(work_dir: &Path) -> Vec<String> {
+    std::fs::read_dir(work_dir)
+        .map(|entries| entries.flatten().map(|e| e.file_name().to_string_lossy().to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// List files in DDM's native layout under `work_dir`, skipping its state folders. Blocking.
+pub fn list_native_files(work_dir: &Path) -> Vec<NativeFile> {
+    let mut files = Vec::new();
+
+    for depot in read_dirs(&work_dir.join("depots")) {
+        let depot_id = depot.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let mut builds = read_dirs(&depot);
+        // Oldest first so the newest build wins collisions
+        builds.sort_by_key(|b| b.metadata().and_then(|m| m.modified()).ok());
+
+        for build in builds {
+            let build_name = build.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            collect_files(&build, "", &mut |path| {
+                files.push(NativeFile {
+                    depot_id: depot_id.clone(),
+                    build: build_name.clone(),
+                    path,
+                })
+            });
+        }
+    }
+
+    files
+}
+
+/// Carry out a plan inside `work_dir`, replacing files at the destination, then remove
+/// folders the moves left empty. Blocking.
+pub fn apply_plan(work_dir: &Path, plan: &LayoutPlan) -> Result<usize, String> {
+    for mv in &plan.moves {
+        let from = join_relative(work_dir, &mv.from);
+        let to = join_relative(work_dir, &mv.to);
+
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+        }
+        if to.is_file() {
+            std::fs::remove_file(&to)
+                .map_err(|e| format!("Failed to replace {:?}: {}", to, e))?;
+        }
+        if std::fs::rename(&from, &to).is_err() {
+            std::fs::copy(&from, &to)
+                .map_err(|e| format!("Failed to move {:?} to {:?}: {}", from, to, e))?;
+            std::fs::remove_file(&from)
+                .map_err(|e| format!("Failed to remove {:?}: {}", from, e))?;
+        }
+    }
+
+    // Files that lost a collision and DDM's state folders stay in the native tree
+    prune_empty_dirs(&work_dir.join("depots"));
+
+    Ok(plan.moves.len())
+}
+
+fn read_dirs(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect())
+        .unwrap_or_default()
+}
+
+fn collect_files(dir: &Path, prefix: &str, out: &mut dyn FnMut(String)) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name == STATE_DIR_NAME {
+            continue;
+        }
+        let relative = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, &relative, out);
+        } else {
+            out(relative);
+        }
+    }
+}
+
+fn join_relative(base: &Path, relative: &str) -> PathBuf {
+    relative
+        .split('/')
+        .filter(|p| !p.is_empty() && *p != "..")
+        .fold(base.to_path_buf(), |p, part| p.join(part))
+}
+
+/// Remove empty folders bottom-up.
+fn prune_empty_dirs(dir: &Path) {
+    for child in read_dirs(dir) {
+        prune_empty_dirs(&child);
+    }
+    let _ = std::fs::remove_dir(dir);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(depot_id: &str, build: &str, path: &str) -> NativeFile {
+        NativeFile {
+            depot_id: depot_id.to_string(),
+            build: build.to_string(),
+            path: path.to_string(),
+        }
+    }
+
+    fn order(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn native_moves_nothing() {
+        let files = vec![file("441", "1", "game.exe")];
+        let plan = plan_layout(&files, &order(&["441"]), OutputLayout::Native, &[]);
+        assert_eq!(plan, LayoutPlan::default());
+    }
+
+    #[test]
+    fn by_depot_uses_a_folder_per_depot() {
+        let files = vec![file("441", "1", "bin/game.exe"), file("442", "1", "bin/game.exe")];
+        let plan = plan_layout(&files, &order(&["441", "442"]), OutputLayout::ByDepot, &[]);
+
+        let targets: Vec<&str> = plan.moves.iter().map(|m| m.to.as_str()).collect();
+        assert_eq!(targets, vec!["441/bin/game.exe", "442/bin/game.exe"]);
+        assert_eq!(plan.moves[0].from, "depots/441/1/bin/game.exe");
+        assert!(plan.collisions.is_empty());
+    }
+
+    #[test]
+    fn merged_collisions_go_to_the_later_depot_and_newest_build() {
+        // Builds are listed oldest first, as list_native_files does
+        let files = vec![
+            file("442", "old", "data.pak"),
+            file("442", "new", "data.pak"),
+            file("441", "1", "Data.pak"),
+            file("441", "1", "base.txt"),
+        ];
+        let plan = plan_layout(&files, &order(&["441", "442"]), OutputLayout::Merged, &[]);
+
+        assert_eq!(plan.moves.len(), 2);
+        let pak = plan.moves.iter().find(|m| m.to == "data.pak").unwrap();
+        assert_eq!(pak.from, "depots/442/new/data.pak");
+        assert_eq!(plan.collisions, vec!["data.pak".to_string()]);
+    }
+
+    #[test]
+    fn merged_never_overwrites_job_files() {
+        let files = vec![
+            file("441", "1", "steam.keys"),
+            file("441", "1", "PROGRESS.ndjson"),
+            file("441", "1", "depots/readme.txt"),
+            file("441", "1", "game.exe"),
+        ];
+        let root = order(&["steam.keys", "progress.ndjson", "441_123.manifest", "depots"]);
+        let plan = plan_layout(&files, &order(&["441"]), OutputLayout::Merged, &root);
+
+        let targets: Vec<&str> = plan.moves.iter().map(|m| m.to.as_str()).collect();
+        assert_eq!(
+            targets,
+            vec![
+                "steam (depot 441).keys",
+                "PROGRESS (depot 441).ndjson",
+                "depots (depot 441)/readme.txt",
+                "game.exe",
+            ]
+        );
+        assert_eq!(plan.renamed.len(), 3);
+    }
+}
@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Public mirror of Steam's PICS app info. Unofficial, so everything here is best-effort.
+const APPINFO_URL: &str = "https://api.steamcmd.net/v1/info";
+
+/// Minimum gap between requests so we stay well inside the mirror's rate limits.
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_secs(2);
+
+static LAST_REQUEST: Mutex<Option<Instant>> = Mutex::const_new(None);
+
+/// How long a fetched depot list is reused. Depot configs rarely change, unlike manifests.
+const DEPOT_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Maximum cached apps before the depot cache is cleared
+const MAX_DEPOT_CACHE_SIZE: usize = 100;
+
+/// App id -> (fetched at, app info). `None` records apps the mirror doesn't know.
+type AppInfoCache = BTreeMap<String, (Instant, Option<serde_json::Value>)>;
+
+static DEPOT_CACHE: Mutex<AppInfoCache> = Mutex::const_new(BTreeMap::new());
+
+/// Latest public manifest for a depot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicManifest {
+    #[serde(rename = "depotId")]
+    pub depot_id: String,
+    #[serde(rename = "manifestId")]
+    pub manifest_id: String,
+    /// Uncompressed size in bytes, when the mirror reports it
+    pub size: Option<u64>,
+    pub source: String,
+}
+
+/// A depot Steam lists for an app.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppDepot {
+    #[serde(rename = "depotId")]
+    pub depot_id: String,
+    /// Language the depot is for; `None` for depots every language gets
+    pub language: Option<String>,
+    pub oslist: Option<String>,
+    /// Set when the depot belongs to a DLC rather than the base game
+    #[serde(rename = "dlcAppId")]
+    pub dlc_app_id: Option<String>,
+    /// Steam marks the depot optional (extra content the game runs without)
+    #[serde(default)]
+    pub optional: bool,
+}
+
+/// An app depot with whether it is needed on a given OS and language.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepotRequirement {
+    #[serde(flatten)]
+    pub depot: AppDepot,
+    /// The depot's `oslist` and `language` (if any) include the requested ones
+    #[serde(rename = "matchesPlatform")]
+    pub matches_platform: bool,
+    /// Base-game, non-optional and matching the platform: what the game needs to run
+    pub required: bool,
+}
+
+/// Fetch the mirror's app info JSON for an app.
+/// Returns `Ok(None)` when the mirror doesn't know the app.
+async fn fetch_app_info(client: &reqwest::Client, app_id: &str) -> Result<Option<serde_json::Value>, String> {
+    // Space out requests; holding the lock while waiting serializes concurrent lookups
+    {
+        let mut last = LAST_REQUEST.lock().await;
+        if let Some(at) = *last {
+            let elapsed = at.elapsed();
+            if elapsed < MIN_REQUEST_INTERVAL {
+                tokio::time::sleep(MIN_REQUEST_INTERVAL - elapsed).await;
+            }
+        }
+        *last = Some(Instant::now());
+    }
+
+    let url = format!("{}/{}", APPINFO_URL, app_id);
+    let response = client
+        .get(&url)
+        .timeout(Duration::from_secs(15))
+        .send()
+        .await
+        .map_err(|e| format!("[AppInfo] Request failed for appId {}: {}", app_id, e))?;
+
+    if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err("[AppInfo] Rate limited by the public app info service, try again later".to_string());
+    }
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+
+    let data: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("[AppInfo] Failed to parse JSON for appId {}: {}", app_id, e))?;
+
+    match data["data"].get(app_id) {
+        Some(info) if info.is_object() => Ok(Some(info.clone())),
+        _ => Ok(None),
+    }
+}
+
+/// Like `fetch_app_info`, but reuses a lookup made within `DEPOT_CACHE_TTL`.
+async fn fetch_app_info_cached(client: &reqwest::Client, app_id: &str) -> Result<Option<serde_json::Value>, String> {
+    {
+        let mut cache = DEPOT_CACHE.lock().await;
+        if cache.len() > MAX_DEPOT_CACHE_SIZE {
+            cache.clear();
+        }
+        if let Some((at, info)) = cache.get(app_id) {
+            if at.elapsed() < DEPOT_CACHE_TTL {
+                return Ok(info.clone());
+            }
+        }
+    }
+
+    let info = fetch_app_info(client, app_id).await?;
+    DEPOT_CACHE
+        .lock()
+        .await
+        .insert(app_id.to_string(), (Instant::now(), info.clone()));
+    Ok(info)
+}
+
+/// Look up the manifest currently on the `public` branch for a depot.
+/// Returns `Ok(None)` when the app or depot is unknown or has no public manifest.
+pub async fn lookup_public_manifest(
+    client: &reqwest::Client,
+    app_id: &str,
+    depot_id: &str,
+) -> Result<Option<PublicManifest>, String> {
+    let Some(info) = fetch_app_info(client, app_id).await? else {
+        return Ok(None);
+    };
+
+    let public = &info["depots"][depot_id]["manifests"]["public"];
+
+    // Newer responses use { gid, size, download }, older ones a bare gid string
+    let (manifest_id, size) = match public {
+        serde_json::Value::String(gid) => (gid.clone(), None),
+        serde_json::Value::Object(obj) => {
+            let gid = match obj.get("gid").and_then(|v| v.as_str()) {
+                Some(gid) => gid.to_string(),
+                None => return Ok(None),
+            };
+            let size = obj.get("size").and_then(|v| match v {
+                serde_json::Value::String(s) => s.parse().ok(),
+                other => other.as_u64(),
+            });
+            (gid, size)
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(PublicManifest {
+        depot_id: depot_id.to_string(),
+        manifest_id,
+        size,
+        source: "steamcmd.net".to_string(),
+    }))
+}
+
+/// List the depots an app can download: those with a manifest, skipping ones shared
+/// from another app (redistributables such as 228988). Returns `Ok(None)` for unknown apps.
+pub async fn list_app_depots(client: &reqwest::Client, app_id: &str) -> Result<Option<Vec<AppDepot>>, String> {
+    let Some(info) = fetch_app_info_cached(client, app_id).await? else {
+        return Ok(None);
+    };
+    let Some(depots) = info["depots"].as_object() else {
+        return Ok(Some(Vec::new()));
+    };
+
+    let text = |v: &serde_json::Value| v.as_str().filter(|s| !s.is_empty()).map(|s| s.to_string());
+
+    let mut result: Vec<AppDepot> = depots
+        .iter()
+        .filter(|(id, _)| id.chars().all(|c| c.is_ascii_digit()))
+        .filter(|(_, depot)| depot.get("depotfromapp").is_none() && depot.get("manifests").is_some())
+        .map(|(id, depot)| AppDepot {
+            depot_id: id.clone(),
+            language: text(&depot["config"]["language"]),
+            oslist: text(&depot["config"]["oslist"]),
+            dlc_app_id: text(&depot["dlcappid"]),
+            optional: text(&depot["optional"]).is_some_and(|v| v == "1"),
+        })
+        .collect();
+    result.sort_by_key(|d| d.depot_id.parse::<u64>().unwrap_or(u64::MAX));
+
+    Ok(Some(result))
+}
+
+/// Flag which depots a player on `os` ("windows", "macos", "linux") with `language`
+/// (Steam's names, e.g. "english") needs. Depots without an OS or language restriction match all.
+pub fn depot_requirements(depots: Vec<AppDepot>, os: &str, language: &str) -> Vec<DepotRequirement> {
+    depots
+        .into_iter()
+        .map(|depot| {
+            let os_matches = depot
+                .oslist
+                .as_deref()
+                .is_none_or(|list| list.split(',').any(|o| o.trim().eq_ignore_ascii_case(os)));
+            let language_matches = depot
+                .language
+                .as_deref()
+                .is_none_or(|l| l.eq_ignore_ascii_case(language));
+            let matches_platform = os_matches && language_matches;
+            let required = matches_platform && !depot.optional && depot.dlc_app_id.is_none();
+            DepotRequirement {
+                depot,
+                matches_platform,
+                required,
+            }
+        })
+        .collect()
+}
+
+/// An app's depots grouped by the language Steam tags them with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguageDepots {
+    /// Language -> depot ids, for depots with a `config > language`
+    pub languages: BTreeMap<String, Vec<String>>,
+    /// Depots every language gets
+    pub neutral: Vec<String>,
+    /// The neutral depots plus those for a preferred language; empty without preferences
+    #[serde(rename = "autoSelect")]
+    pub auto_select: Vec<String>,
+}
+
+/// Group depots by language and pick the ones a player of any of `preferred`
+/// (Steam's names, e.g. "english") needs.
+pub fn group_language_depots(depots: &[AppDepot], preferred: &[String]) -> LanguageDepots {
+    let mut languages: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut neutral = Vec::new();
+    let mut auto_select = Vec::new();
+
+    for depot in depots {
+        match depot.language {
+            Some(ref language) => {
+                let language = language.to_lowercase();
+                if preferred.iter().any(|p| p.eq_ignore_ascii_case(&language)) {
+                    auto_select.push(depot.depot_id.clone());
+                }
+                languages.entry(language).or_default().push(depot.depot_id.clone());
+            }
+            None => {
+                neutral.push(depot.depot_id.clone());
+                if !preferred.is_empty() {
+                    auto_select.push(depot.depot_id.clone());
+                }
+            }
+        }
+    }
+
+    LanguageDepots {
+        languages,
+        neutral,
+        auto_select,
+    }
+}
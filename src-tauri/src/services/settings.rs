@@ -1,7 +1,13 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use tokio::fs;
 
+use crate::services::atomic_file;
+use crate::services::manifest_downloader;
+use crate::services::manifest_hub_api;
+use crate::services::output_layout::OutputLayout;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
     #[serde(default = "default_download_location")]
@@ -10,6 +16,95 @@ pub struct Settings {
     pub github_token: String,
     #[serde(default = "default_dd_extra_args")]
     pub dd_extra_args: Vec<String>,
+    /// Mirror every progress event into `progress.ndjson` inside the job's work dir.
+    #[serde(default)]
+    pub write_progress_log: bool,
+    /// Download into `.staging/{job_id}` and move into the final folder only on success.
+    #[serde(default)]
+    pub use_staging_dir: bool,
+    /// Resolve hostnames via DNS-over-HTTPS (Cloudflare, then Google, then system DNS).
+    /// Adds a lookup round-trip per new host; takes effect on the next launch.
+    #[serde(default)]
+    pub use_doh: bool,
+    /// Ask PrintedWaste for depot keys the repo didn't provide before writing `steam.keys`.
+    #[serde(default)]
+    pub auto_fetch_missing_keys: bool,
+    /// Allow looking up public manifest ids from an unofficial app info mirror (best-effort).
+    #[serde(default)]
+    pub enable_public_manifest_lookup: bool,
+    /// Warn when free space on the download drive drops below this many GB (0 disables).
+    #[serde(default = "default_low_disk_threshold_gb")]
+    pub low_disk_threshold_gb: f64,
+    /// Stop DDM (keeping its files) when the low disk threshold is crossed.
+    #[serde(default)]
+    pub pause_on_low_disk: bool,
+    /// dotnet executable (or its folder) used to run `DepotDownloaderMod.dll` on Windows.
+    /// Empty launches the bundled `.exe` directly.
+    #[serde(default)]
+    pub dotnet_path: String,
+    /// After a successful download, re-check every file against its manifest (roughly doubles disk reads).
+    #[serde(default)]
+    pub verify_after_download: bool,
+    /// How files are arranged once DDM is done: "native" (`depots/{id}/{build}`), "merged" or "by_depot".
+    #[serde(default = "default_output_layout")]
+    pub output_layout: String,
+    /// Alternative key/manifest sources that may be queried ("printedwaste", "kernelos").
+    #[serde(default = "default_enabled_alternative_sources")]
+    pub enabled_alternative_sources: Vec<String>,
+    /// Pass `-verify-all` so DDM re-hashes existing files on resume. Safer; turning it off
+    /// makes resumes of nearly complete installs much faster by only fetching missing chunks.
+    #[serde(default = "default_true")]
+    pub verify_all: bool,
+    /// Minutes a finished job stays in the job list before it is dropped; 0 keeps it until exit.
+    #[serde(default = "default_job_retention_minutes")]
+    pub job_retention_minutes: u64,
+    /// What happens to a cancelled download's folder: "delete", "trash" (recycle bin) or "keep".
+    #[serde(default = "default_cancel_cleanup_mode")]
+    pub cancel_cleanup_mode: String,
+    /// ManifestHub API hosts tried in order for custom manifests, falling over when one is down.
+    #[serde(default = "default_manifest_hub_hosts")]
+    pub manifest_hub_hosts: Vec<String>,
+    /// DDM output is collected for this many ms before being sent to the UI as one event.
+    #[serde(default = "default_output_throttle_ms")]
+    pub output_throttle_ms: u64,
+    /// Buffered DDM output is sent early once this many lines are waiting.
+    #[serde(default = "default_output_buffer_lines")]
+    pub output_buffer_lines: usize,
+    /// Copy each generated `steam.keys` into `key_backups/{app_id}.keys` in the app data folder.
+    #[serde(default = "default_true")]
+    pub auto_backup_keys: bool,
+    /// Test each depot key against its manifest's encrypted file names before downloading.
+    #[serde(default)]
+    pub validate_keys: bool,
+    /// Platform whose depots DDM downloads (`-os`): "windows", "macos" or "linux". Empty uses the host's.
+    #[serde(default)]
+    pub target_os: String,
+    /// Architecture DDM downloads for (`-osarch`): "32" or "64". Empty uses the host's.
+    #[serde(default)]
+    pub target_arch: String,
+    /// After a search, rank repos by how many of their depots have keys and recommend the best.
+    /// Costs one GitHub API request per repo found.
+    #[serde(default)]
+    pub auto_select_best_repo: bool,
+    /// Steam language names (e.g. "english", "schinese") whose depots are selected automatically,
+    /// along with depots every language gets. Empty leaves depot selection alone.
+    #[serde(default)]
+    pub preferred_languages: Vec<String>,
+    /// Check each manifest downloaded from a repo against its git blob SHA and re-fetch it
+    /// through the blob API when the raw CDN served a stale copy. Costs one tree request per download.
+    #[serde(default)]
+    pub verify_manifest_sha: bool,
+    /// When some of a job's manifests can't be fetched: "continue" with the depots that have one,
+    /// or "abort" the job before any keys are generated or files downloaded.
+    #[serde(default = "default_manifest_failure_policy")]
+    pub manifest_failure_policy: String,
+    /// Show the download folder in the file manager once a job finishes with at least one depot downloaded.
+    #[serde(default)]
+    pub open_folder_on_complete: bool,
+    /// Name manifests are saved under, and passed to DDM as, in the work dir. Must contain
+    /// `{depot_id}` and `{manifest_id}` once each, e.g. `{depot_id}_{manifest_id}.manifest`.
+    #[serde(default = "default_manifest_filename_pattern")]
+    pub manifest_filename_pattern: String,
 }
 
 fn default_download_location() -> String {
@@ -32,11 +127,69 @@ fn dirs_next_home() -> Option<String> {
     }
 }
 
+fn default_low_disk_threshold_gb() -> f64 {
+    2.0
+}
+
+fn default_output_layout() -> String {
+    "native".to_string()
+}
+
+/// Alternative sources `enabled_alternative_sources` accepts.
+pub const ALTERNATIVE_SOURCES: &[&str] = &["printedwaste", "kernelos"];
+
+fn default_enabled_alternative_sources() -> Vec<String> {
+    ALTERNATIVE_SOURCES.iter().map(|s| s.to_string()).collect()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_job_retention_minutes() -> u64 {
+    30
+}
+
+/// Values `target_os` accepts besides empty (the host OS).
+pub const TARGET_OSES: &[&str] = &["windows", "macos", "linux"];
+
+/// Values `target_arch` accepts besides empty (the host architecture).
+pub const TARGET_ARCHS: &[&str] = &["32", "64"];
+
+/// Values `cancel_cleanup_mode` accepts.
+pub const CANCEL_CLEANUP_MODES: &[&str] = &["delete", "trash", "keep"];
+
+fn default_cancel_cleanup_mode() -> String {
+    "delete".to_string()
+}
+
+/// Values `manifest_failure_policy` accepts.
+pub const MANIFEST_FAILURE_POLICIES: &[&str] = &["continue", "abort"];
+
+fn default_manifest_failure_policy() -> String {
+    "continue".to_string()
+}
+
+fn default_manifest_filename_pattern() -> String {
+    manifest_downloader::DEFAULT_MANIFEST_FILENAME_PATTERN.to_string()
+}
+
+fn default_manifest_hub_hosts() -> Vec<String> {
+    vec![manifest_hub_api::DEFAULT_HOST.to_string()]
+}
+
+fn default_output_throttle_ms() -> u64 {
+    150
+}
+
+fn default_output_buffer_lines() -> usize {
+    50
+}
+
 fn default_dd_extra_args() -> Vec<String> {
     vec![
         "-max-downloads".to_string(),
         "8".to_string(),
-        "-verify-all".to_string(),
     ]
 }
 
@@ -46,8 +199,284 @@ impl Default for Settings {
             download_location: default_download_location(),
             github_token: String::new(),
             dd_extra_args: default_dd_extra_args(),
+            write_progress_log: false,
+            use_staging_dir: false,
+            use_doh: false,
+            auto_fetch_missing_keys: false,
+            enable_public_manifest_lookup: false,
+            low_disk_threshold_gb: default_low_disk_threshold_gb(),
+            pause_on_low_disk: false,
+            dotnet_path: String::new(),
+            verify_after_download: false,
+            output_layout: default_output_layout(),
+            enabled_alternative_sources: default_enabled_alternative_sources(),
+            verify_all: true,
+            job_retention_minutes: default_job_retention_minutes(),
+            cancel_cleanup_mode: default_cancel_cleanup_mode(),
+            manifest_hub_hosts: default_manifest_hub_hosts(),
+            output_throttle_ms: default_output_throttle_ms(),
+            output_buffer_lines: default_output_buffer_lines(),
+            auto_backup_keys: true,
+            validate_keys: false,
+            target_os: String::new(),
+            target_arch: String::new(),
+            auto_select_best_repo: false,
+            preferred_languages: Vec::new(),
+            verify_manifest_sha: false,
+            manifest_failure_policy: default_manifest_failure_policy(),
+            open_folder_on_complete: false,
+            manifest_filename_pattern: default_manifest_filename_pattern(),
+        }
+    }
+}
+
+/// Settings that only make sense globally and can't be overridden per job.
+/// The manifest filename pattern is read back when inspecting finished downloads.
+const NON_OVERRIDABLE: &[&str] = &["github_token", "use_doh", "manifest_filename_pattern"];
+
+impl Settings {
+    /// Check the settings for values DDM or the pipeline can't work with.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.download_location.trim().is_empty() {
+            return Err("Download location cannot be empty".to_string());
+        }
+
+        if !self.low_disk_threshold_gb.is_finite() || self.low_disk_threshold_gb < 0.0 {
+            return Err("Low disk threshold must be a positive number of GB".to_string());
+        }
+
+        if !self.dotnet_path.trim().is_empty() && !Path::new(self.dotnet_path.trim()).exists() {
+            return Err(format!("dotnet path does not exist: {}", self.dotnet_path.trim()));
+        }
+
+        OutputLayout::parse(&self.output_layout)?;
+
+        if let Some(unknown) = self
+            .enabled_alternative_sources
+            .iter()
+            .find(|s| !ALTERNATIVE_SOURCES.contains(&s.to_lowercase().as_str()))
+        {
+            return Err(format!("Unknown alternative source: {}. Use 'printedwaste' or 'kernelos'.", unknown));
+        }
+
+        if !CANCEL_CLEANUP_MODES.contains(&self.cancel_cleanup_mode.as_str()) {
+            return Err(format!(
+                "Unknown cancel cleanup mode: {}. Use 'delete', 'trash' or 'keep'.",
+                self.cancel_cleanup_mode
+            ));
+        }
+
+        if !MANIFEST_FAILURE_POLICIES.contains(&self.manifest_failure_policy.as_str()) {
+            return Err(format!(
+                "Unknown manifest failure policy: {}. Use 'continue' or 'abort'.",
+                self.manifest_failure_policy
+            ));
+        }
+
+        validate_manifest_filename_pattern(&self.manifest_filename_pattern)?;
+
+        if let Some(bad) = self
+            .manifest_hub_hosts
+            .iter()
+            .find(|h| !(h.starts_with("https://") || h.starts_with("http://")) || h.trim() != h.as_str())
+        {
+            return Err(format!("ManifestHub host must be an http(s) URL: {}", bad));
+        }
+
+        if !self.target_os.is_empty() && !TARGET_OSES.contains(&self.target_os.as_str()) {
+            return Err(format!("Unknown target OS: {}. Use 'windows', 'macos' or 'linux'.", self.target_os));
+        }
+
+        if !self.target_arch.is_empty() && !TARGET_ARCHS.contains(&self.target_arch.as_str()) {
+            return Err(format!("Unknown target architecture: {}. Use '32' or '64'.", self.target_arch));
+        }
+
+        if let Some(bad) = self
+            .preferred_languages
+            .iter()
+            .find(|l| l.is_empty() || !l.chars().all(|c| c.is_ascii_lowercase() || c == '_'))
+        {
+            return Err(format!("Invalid language name: '{}'. Use Steam's names, e.g. 'english' or 'schinese'.", bad));
+        }
+
+        // DDM refuses to run with both
+        if !self.target_os.is_empty() && self.dd_extra_args.iter().any(|a| a == "-all-platforms") {
+            return Err("A target OS can't be combined with -all-platforms".to_string());
+        }
+        if !self.target_arch.is_empty() && self.dd_extra_args.iter().any(|a| a == "-all-archs") {
+            return Err("A target architecture can't be combined with -all-archs".to_string());
+        }
+
+        if self.output_throttle_ms > 5000 {
+            return Err(format!("Output throttle must be at most 5000 ms, got {}", self.output_throttle_ms));
+        }
+
+        if !(1..=1000).contains(&self.output_buffer_lines) {
+            return Err(format!("Output buffer must be between 1 and 1000 lines, got {}", self.output_buffer_lines));
+        }
+
+        if self.dd_extra_args.iter().any(|a| a.trim().is_empty()) {
+            return Err("DepotDownloader arguments cannot contain empty entries".to_string());
+        }
+
+        if let Some(pos) = self.dd_extra_args.iter().position(|a| a == "-max-downloads") {
+            let value = self
+                .dd_extra_args
+                .get(pos + 1)
+                .and_then(|v| v.parse::<u32>().ok())
+                .ok_or_else(|| "-max-downloads must be followed by a number".to_string())?;
+            if !(1..=64).contains(&value) {
+                return Err(format!("-max-downloads must be between 1 and 64, got {}", value));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Extra DDM arguments for a run. `-verify-all` follows `verify_all`, whether or not
+    /// it is also listed in `dd_extra_args` (older settings files still have it there).
+    pub fn dd_args(&self) -> Vec<String> {
+        let base = if self.dd_extra_args.is_empty() {
+            default_dd_extra_args()
+        } else {
+            self.dd_extra_args.clone()
+        };
+
+        let mut args: Vec<String> = base.into_iter().filter(|a| a != "-verify-all").collect();
+        if self.verify_all {
+            args.push("-verify-all".to_string());
+        }
+        args
+    }
+
+    /// Whether an alternative source (case-insensitive name) may be queried.
+    pub fn alternative_source_enabled(&self, source: &str) -> bool {
+        self.enabled_alternative_sources
+            .iter()
+            .any(|s| s.eq_ignore_ascii_case(source))
+    }
+
+    /// The configured dotnet executable, if any. A folder resolves to the dotnet binary inside it.
+    pub fn resolve_dotnet_path(&self) -> Option<PathBuf> {
+        let path = self.dotnet_path.trim();
+        if path.is_empty() {
+            return None;
+        }
+
+        let path = PathBuf::from(path);
+        if path.is_dir() {
+            #[cfg(target_os = "windows")]
+            return Some(path.join("dotnet.exe"));
+            #[cfg(not(target_os = "windows"))]
+            return Some(path.join("dotnet"));
+        }
+        Some(path)
+    }
+}
+
+/// A pattern must name every manifest distinctly and be readable back into its ids.
+fn validate_manifest_filename_pattern(pattern: &str) -> Result<(), String> {
+    if pattern.trim().is_empty() {
+        return Err("Manifest filename pattern cannot be empty".to_string());
+    }
+    for placeholder in ["{depot_id}", "{manifest_id}"] {
+        if pattern.matches(placeholder).count() != 1 {
+            return Err(format!("Manifest filename pattern must contain {} exactly once", placeholder));
+        }
+    }
+    // Two bare numbers in a row can't be told apart again
+    if pattern.contains("{depot_id}{manifest_id}") || pattern.contains("{manifest_id}{depot_id}") {
+        return Err("Manifest filename pattern needs a separator between {depot_id} and {manifest_id}".to_string());
+    }
+    // Also keeps the name safe to quote in exported batch and shell scripts
+    if let Some(bad) = pattern
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '{' | '}' | '_' | '-' | '.' | ' ')))
+    {
+        return Err(format!(
+            "Manifest filename pattern cannot contain '{}'; use letters, digits, spaces, '_', '-' and '.'",
+            bad
+        ));
+    }
+    Ok(())
+}
+
+/// Merge a JSON object of per-job overrides over the loaded settings.
+///
+/// Keys use the same names as `settings.json`. Unknown keys and global-only
+/// settings are rejected, and the merged result must pass `Settings::validate`.
+pub fn apply_overrides(settings: &Settings, overrides: &serde_json::Value) -> Result<Settings, String> {
+    let overrides = overrides
+        .as_object()
+        .ok_or_else(|| "Settings overrides must be an object".to_string())?;
+
+    let mut merged = serde_json::to_value(settings)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+    let fields = merged
+        .as_object_mut()
+        .ok_or_else(|| "Failed to serialize settings".to_string())?;
+
+    for (key, value) in overrides {
+        if !fields.contains_key(key) {
+            return Err(format!("Unknown setting override: {}", key));
         }
+        if NON_OVERRIDABLE.contains(&key.as_str()) {
+            return Err(format!("Setting '{}' cannot be overridden per download", key));
+        }
+        fields.insert(key.clone(), value.clone());
     }
+
+    let merged: Settings = serde_json::from_value(merged)
+        .map_err(|e| format!("Invalid settings override: {}", e))?;
+    merged.validate()?;
+    Ok(merged)
+}
+
+/// Settings as a download would use them, with where each field's value came from:
+/// "default", "file" (set in `settings.json`) or "override" (per-job override).
+pub async fn effective_settings(
+    app_data_dir: &Path,
+    overrides: Option<&serde_json::Value>,
+) -> Result<(Settings, BTreeMap<String, &'static str>), String> {
+    let settings = load_settings(app_data_dir).await;
+    let defaults = serde_json::to_value(Settings::default())
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+    // A file that doesn't parse is ignored entirely by load_settings, so nothing comes from it
+    let file_fields: Vec<String> = match fs::read_to_string(settings_path(app_data_dir)).await {
+        Ok(content) if serde_json::from_str::<Settings>(&content).is_ok() => {
+            serde_json::from_str::<serde_json::Value>(&content)
+                .ok()
+                .and_then(|v| v.as_object().map(|o| o.keys().cloned().collect()))
+                .unwrap_or_default()
+        }
+        _ => Vec::new(),
+    };
+
+    let mut sources: BTreeMap<String, &'static str> = defaults
+        .as_object()
+        .map(|o| o.keys().map(|k| (k.clone(), "default")).collect())
+        .unwrap_or_default();
+    for key in file_fields {
+        if let Some(source) = sources.get_mut(&key) {
+            *source = "file";
+        }
+    }
+
+    let settings = match overrides {
+        Some(overrides) => {
+            let merged = apply_overrides(&settings, overrides)?;
+            for key in overrides.as_object().into_iter().flat_map(|o| o.keys()) {
+                if let Some(source) = sources.get_mut(key) {
+                    *source = "override";
+                }
+            }
+            merged
+        }
+        None => settings,
+    };
+
+    Ok((settings, sources))
 }
 
 /// Get the settings file path within the app data directory.
@@ -80,7 +509,8 @@ pub async fn save_settings(app_data_dir: &Path, settings: &Settings) -> Result<(
     let content = serde_json::to_string_pretty(settings)
         .map_err(|e| format!("Failed to serialize settings: {}", e))?;
 
-    fs::write(&path, content)
+    // Never leave a half-written file behind; load_settings would fall back to defaults
+    atomic_file::write_atomic_async(&path, content.into_bytes())
         .await
         .map_err(|e| format!("Failed to write settings file: {}", e))?;
 
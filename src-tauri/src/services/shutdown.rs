@@ -0,0 +1,34 @@
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::services::atomic_file;
+use crate::services::depot_runner;
+use crate::services::embedded_tools;
+use crate::services::AppState;
+
+/// How long exit waits for in-flight file writes before giving up on them
+const SHUTDOWN_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Run once on the final exit, before the process ends: close the progress logs of
+/// jobs still running, stop any DDM extraction, and let pending settings/history/report writes land on disk.
+/// In-memory caches (Steam store, depot lists) aren't persisted, so there's nothing to flush for them.
+pub fn flush_on_exit(app: &AppHandle) {
+    // A half-written DDM extraction is discarded rather than marked complete
+    embedded_tools::cancel_extraction();
+
+    if let Some(state) = app.try_state::<AppState>() {
+        let closed = depot_runner::close_progress_logs(&state);
+        if closed > 0 {
+            eprintln!("[Shutdown] Marked {} running job(s) as interrupted", closed);
+        }
+    }
+
+    let pending = atomic_file::wait_for_pending_writes(SHUTDOWN_WRITE_TIMEOUT);
+    if pending > 0 {
+        eprintln!(
+            "[Shutdown] {} file write(s) still pending after {}s; exiting anyway",
+            pending,
+            SHUTDOWN_WRITE_TIMEOUT.as_secs()
+        );
+    }
+}
@@ -3,15 +3,52 @@ use std::io::Read;
 
 use crate::services::lua_parser::{parse_lua_file, LuaParseResult};
 
+/// Mask the header's key fields are stored XORed with
+const XOR_KEY_MASK: u32 = 0xFFFEA4C8;
+
 /// Parse a `.st` binary file buffer.
 ///
 /// Format:
 ///   Header: 12 bytes = [xorkey (u32 LE), size (u32 LE), xorkeyverify (u32 LE)]
-///   xorkey = (xorkey XOR 0xFFFEA4C8) AND 0xFF
+///   xorkey = (xorkey XOR 0xFFFEA4C8) AND 0xFF, and xorkeyverify must decrypt to the same key
 ///   Data: content[12 .. 12+size], XOR each byte with xorkey
 ///   Then zlib decompress
 ///   Then skip first 512 bytes, rest is lua-like content
 pub fn parse_st_file(buffer: &[u8]) -> Result<LuaParseResult, String> {
+    let xor_key = verify_st_header(buffer)?;
+    let size = u32::from_le_bytes(buffer[4..8].try_into().unwrap()) as usize;
+
+    // Extract and XOR decrypt data bytes
+    let encrypted_data = &buffer[12..12 + size];
+    let decrypted_data: Vec<u8> = encrypted_data.iter().map(|b| b ^ xor_key).collect();
+
+    // Zlib decompress
+    let mut decoder = ZlibDecoder::new(&decrypted_data[..]);
+    let mut decompressed = Vec::new();
+    decoder
+        .read_to_end(&mut decompressed)
+        .map_err(|e| format!("Failed to decompress .st data: {}", e))?;
+
+    // Skip first 512 bytes
+    if decompressed.len() <= 512 {
+        return Err(format!(
+            ".st decompressed data too small: {} bytes (need >512)",
+            decompressed.len()
+        ));
+    }
+
+    let lua_content = String::from_utf8_lossy(&decompressed[512..]).to_string();
+
+    // Parse with lua_parser
+    Ok(parse_lua_file(&lua_content))
+}
+
+/// Check a `.st` header and return the derived XOR key.
+///
+/// `xorkeyverify` (bytes 8..12) is stored with the same mask as the key, so decrypting it
+/// must give the key back. A mismatch means a damaged or foreign file, which is rejected
+/// before it is decompressed into garbage lua.
+pub fn verify_st_header(buffer: &[u8]) -> Result<u8, String> {
     if buffer.len() < 12 {
         return Err(format!(
             ".st file too small: {} bytes (need at least 12 for header)",
@@ -22,10 +59,17 @@ pub fn parse_st_file(buffer: &[u8]) -> Result<LuaParseResult, String> {
     // Read header (3x uint32 little-endian)
     let xor_key_raw = u32::from_le_bytes(buffer[0..4].try_into().unwrap());
     let size = u32::from_le_bytes(buffer[4..8].try_into().unwrap()) as usize;
-    // xor_key_verify at bytes 8..12 not used
+    let xor_key_verify = u32::from_le_bytes(buffer[8..12].try_into().unwrap());
 
-    // Derive XOR key
-    let xor_key = ((xor_key_raw ^ 0xFFFEA4C8) & 0xFF) as u8;
+    // Derive XOR key and check it against the verify field
+    let xor_key = derive_xor_key(xor_key_raw);
+    let expected = derive_xor_key(xor_key_verify);
+    if xor_key != expected {
+        return Err(format!(
+            "Corrupt or wrong-format .st file: XOR key {:#04x} doesn't match its verify value {:#04x}",
+            xor_key, expected
+        ));
+    }
 
     // Validate size
     if 12 + size > buffer.len() {
@@ -36,27 +80,61 @@ pub fn parse_st_file(buffer: &[u8]) -> Result<LuaParseResult, String> {
         ));
     }
 
-    // Extract and XOR decrypt data bytes
-    let encrypted_data = &buffer[12..12 + size];
-    let decrypted_data: Vec<u8> = encrypted_data.iter().map(|b| b ^ xor_key).collect();
+    Ok(xor_key)
+}
 
-    // Zlib decompress
-    let mut decoder = ZlibDecoder::new(&decrypted_data[..]);
-    let mut decompressed = Vec::new();
-    decoder
-        .read_to_end(&mut decompressed)
-        .map_err(|e| format!("Failed to decompress .st data: {}", e))?;
+/// The one-byte XOR key a masked header field decrypts to.
+fn derive_xor_key(raw: u32) -> u8 {
+    ((raw ^ XOR_KEY_MASK) & 0xFF) as u8
+}
 
-    // Skip first 512 bytes
-    if decompressed.len() <= 512 {
-        return Err(format!(
-            ".st decompressed data too small: {} bytes (need >512)",
-            decompressed.len()
-        ));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    /// A `.st` file holding `lua` after the 512 skipped bytes, with `verify` as its verify field.
+    fn build_st(lua: &str, raw_key: u32, verify: u32) -> Vec<u8> {
+        let mut payload = vec![0u8; 512];
+        payload.extend_from_slice(lua.as_bytes());
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&payload).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let key = derive_xor_key(raw_key);
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(&raw_key.to_le_bytes());
+        buffer.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        buffer.extend_from_slice(&verify.to_le_bytes());
+        buffer.extend(compressed.iter().map(|b| b ^ key));
+        buffer
     }
 
-    let lua_content = String::from_utf8_lossy(&decompressed[512..]).to_string();
+    #[test]
+    fn parses_valid_file() {
+        let lua = "addappid(440)\naddappid(441,1,\"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\")\n";
+        let buffer = build_st(lua, 0x1234_5678, 0x1234_5678);
 
-    // Parse with lua_parser
-    Ok(parse_lua_file(&lua_content))
+        assert_eq!(verify_st_header(&buffer), Ok(derive_xor_key(0x1234_5678)));
+        let result = parse_st_file(&buffer).unwrap();
+        assert_eq!(result.main_app_id, Some(440));
+        let depot = result.depots.iter().find(|d| d.depot_id == 441).unwrap();
+        assert_eq!(depot.depot_key, Some("a".repeat(64)));
+    }
+
+    #[test]
+    fn rejects_corrupted_header() {
+        let mut buffer = build_st("addappid(440)\n", 0x1234_5678, 0x1234_5678);
+        buffer[8] ^= 0x5A;
+
+        let err = parse_st_file(&buffer).unwrap_err();
+        assert!(err.starts_with("Corrupt or wrong-format .st file"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_truncated_header() {
+        assert!(verify_st_header(&[0u8; 11]).is_err());
+    }
 }
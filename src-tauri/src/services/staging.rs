@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+
+/// Hidden folder inside the download location that holds in-progress jobs.
+pub const STAGING_DIR_NAME: &str = ".staging";
+
+/// Get the staging directory for a job: `{base_dir}/.staging/{job_id}`.
+pub fn staging_dir(base_dir: &Path, job_id: &str) -> PathBuf {
+    base_dir.join(STAGING_DIR_NAME).join(job_id)
+}
+
+/// Move a finished staging directory into its final location.
+///
+/// If the final folder doesn't exist yet the whole directory is renamed in one step.
+/// Otherwise the staged entries are merged into it, replacing files with the same name.
+/// Falls back to copy + delete when a rename fails (e.g. the target is on another device).
+pub async fn promote(staging: &Path, final_dir: &Path) -> Result<(), String> {
+    let staging = staging.to_path_buf();
+    let final_dir = final_dir.to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        move_dir(&staging, &final_dir)?;
+
+        // Drop the `.staging` root once the last job has left it
+        if let Some(root) = staging.parent() {
+            let _ = std::fs::remove_dir(root);
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("Staging move task failed: {}", e))?
+}
+
+fn move_dir(src: &Path, dest: &Path) -> Result<(), String> {
+    if !dest.exists() {
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+        }
+        if std::fs::rename(src, dest).is_ok() {
+            return Ok(());
+        }
+        copy_dir(src, dest)?;
+        return std::fs::remove_dir_all(src)
+            .map_err(|e| format!("Failed to remove staging directory {:?}: {}", src, e));
+    }
+
+    let entries = std::fs::read_dir(src)
+        .map_err(|e| format!("Failed to read staging directory {:?}: {}", src, e))?;
+
+    for entry in entries.flatten() {
+        let from = entry.path();
+        let to = dest.join(entry.file_name());
+
+        if from.is_dir() {
+            if to.exists() && !to.is_dir() {
+                std::fs::remove_file(&to)
+                    .map_err(|e| format!("Failed to replace {:?}: {}", to, e))?;
+            }
+            move_dir(&from, &to)?;
+        } else {
+            if to.is_dir() {
+                std::fs::remove_dir_all(&to)
+                    .map_err(|e| format!("Failed to replace {:?}: {}", to, e))?;
+            }
+            if std::fs::rename(&from, &to).is_err() {
+                std::fs::copy(&from, &to)
+                    .map_err(|e| format!("Failed to copy {:?} to {:?}: {}", from, to, e))?;
+                std::fs::remove_file(&from)
+                    .map_err(|e| format!("Failed to remove staged file {:?}: {}", from, e))?;
+            }
+        }
+    }
+
+    std::fs::remove_dir(src)
+        .map_err(|e| format!("Failed to remove staging directory {:?}: {}", src, e))
+}
+
+fn copy_dir(src: &Path, dest: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(dest)
+        .map_err(|e| format!("Failed to create {:?}: {}", dest, e))?;
+
+    let entries = std::fs::read_dir(src)
+        .map_err(|e| format!("Failed to read {:?}: {}", src, e))?;
+
+    for entry in entries.flatten() {
+        let from = entry.path();
+        let to = dest.join(entry.file_name());
+        if from.is_dir() {
+            copy_dir(&from, &to)?;
+        } else {
+            std::fs::copy(&from, &to)
+                .map_err(|e| format!("Failed to copy {:?} to {:?}: {}", from, to, e))?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+use crate::services::vdf_parser;
+
+/// A Steam library folder configured in `libraryfolders.vdf`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SteamLibrary {
+    /// Library root (the folder containing `steamapps`)
+    pub path: String,
+    /// `{path}/steamapps/common`, where game folders live
+    #[serde(rename = "commonPath")]
+    pub common_path: String,
+    pub label: Option<String>,
+    /// Whether the folder is currently reachable (e.g. the drive is plugged in)
+    pub exists: bool,
+    #[serde(rename = "appCount")]
+    pub app_count: usize,
+    /// Whether this is the library inside the Steam install itself
+    #[serde(rename = "isMain")]
+    pub is_main: bool,
+}
+
+/// Find Steam libraries from every Steam install found in the standard locations.
+pub fn detect_libraries() -> Vec<SteamLibrary> {
+    let mut libraries: Vec<SteamLibrary> = Vec::new();
+
+    for root in steam_install_dirs() {
+        let vdf_path = root.join("steamapps").join("libraryfolders.vdf");
+        let mut paths: Vec<(PathBuf, Option<String>, usize)> = match std::fs::read_to_string(&vdf_path) {
+            Ok(content) => parse_library_folders(&content).unwrap_or_else(|e| {
+                eprintln!("[SteamLibrary] Failed to parse {:?}: {}", vdf_path, e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+
+        // The install folder is always a library, even if the file doesn't list it
+        if !paths.iter().any(|(p, _, _)| same_path(p, &root)) {
+            paths.insert(0, (root.clone(), None, 0));
+        }
+
+        for (path, label, app_count) in paths {
+            if libraries.iter().any(|l| same_path(Path::new(&l.path), &path)) {
+                continue;
+            }
+            libraries.push(SteamLibrary {
+                common_path: path.join("steamapps").join("common").to_string_lossy().to_string(),
+                exists: path.join("steamapps").is_dir(),
+                is_main: same_path(&path, &root),
+                path: path.to_string_lossy().to_string(),
+                label,
+                app_count,
+            });
+        }
+    }
+
+    libraries
+}
+
+/// Read `(path, label, installed app count)` for each library in `libraryfolders.vdf`.
+/// Supports both the current `{ "path" ... "apps" {...} }` entries and the old bare-path form.
+fn parse_library_folders(content: &str) -> Result<Vec<(PathBuf, Option<String>, usize)>, String> {
+    let root = vdf_parser::parse_vdf(content)?;
+    let folders = root
+        .get("libraryfolders")
+        .or_else(|| root.get("LibraryFolders"))
+        .ok_or("Missing libraryfolders section")?;
+
+    let mut result = Vec::new();
+    for (key, value) in folders.entries() {
+        // Only numbered entries are libraries ("contentstatsid" etc. are metadata)
+        if !key.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+
+        match value {
+            vdf_parser::VdfValue::String(path) => result.push((PathBuf::from(path), None, 0)),
+            obj => {
+                let Some(path) = obj.get("path").and_then(|p| p.as_str()) else {
+                    continue;
+                };
+                let label = obj
+                    .get("label")
+                    .and_then(|l| l.as_str())
+                    .filter(|l| !l.is_empty())
+                    .map(|l| l.to_string());
+                let app_count = obj.get("apps").map(|a| a.entries().len()).unwrap_or(0);
+                result.push((PathBuf::from(path), label, app_count));
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// Standard Steam install locations that exist on this machine.
+fn steam_install_dirs() -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+
+    #[cfg(target_os = "windows")]
+    {
+        if let Some(path) = registry_steam_path() {
+            candidates.push(path);
+        }
+        for var in ["ProgramFiles(x86)", "ProgramFiles"] {
+            if let Ok(dir) = std::env::var(var) {
+                candidates.push(PathBuf::from(dir).join("Steam"));
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        if let Ok(home) = std::env::var("HOME") {
+            let home = PathBuf::from(home);
+            candidates.push(home.join(".steam").join("steam"));
+            candidates.push(home.join(".local").join("share").join("Steam"));
+            // Flatpak and Snap installs
+            candidates.push(home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"));
+            candidates.push(home.join("snap/steam/common/.local/share/Steam"));
+        }
+    }
+
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for candidate in candidates {
+        if candidate.join("steamapps").is_dir() && !dirs.iter().any(|d| same_path(d, &candidate)) {
+            dirs.push(candidate);
+        }
+    }
+    dirs
+}
+
+/// Steam's own record of where it is installed.
+#[cfg(target_os = "windows")]
+fn registry_steam_path() -> Option<PathBuf> {
+    let mut cmd = std::process::Command::new("reg");
+    cmd.args(["query", r"HKCU\Software\Valve\Steam", "/v", "SteamPath"]);
+    cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    let output = cmd.output().ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    // "    SteamPath    REG_SZ    c:/program files (x86)/steam"
+    stdout.lines().find_map(|line| {
+        let (_, value) = line.split_once("REG_SZ")?;
+        let value = value.trim();
+        (!value.is_empty()).then(|| PathBuf::from(value.replace('/', "\\")))
+    })
+}
+
+/// Compare paths after resolving symlinks (`~/.steam/steam` usually points at another root).
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => {
+            let a = a.to_string_lossy().to_lowercase();
+            let b = b.to_string_lossy().to_lowercase();
+            a.trim_end_matches(['/', '\\']) == b.trim_end_matches(['/', '\\'])
+        }
+    }
+}
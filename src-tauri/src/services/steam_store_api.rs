@@ -13,11 +13,132 @@ pub struct GameInfo {
     pub short_description: Option<String>,
     #[serde(rename = "type")]
     pub app_type: Option<String>,
+    #[serde(rename = "isFree", default)]
+    pub is_free: Option<bool>,
+    /// Whether the store lists a price (`price_overview`) for this app
+    #[serde(rename = "hasPrice", default)]
+    pub has_price: bool,
+    /// The store hides this app in the user's region; details (if any) came from `FALLBACK_COUNTRY_CODE`
+    #[serde(rename = "regionBlocked", default)]
+    pub region_blocked: bool,
+    /// The store page sits behind an age check (`required_age` above 0)
+    #[serde(rename = "ageGated", default)]
+    pub age_gated: bool,
 }
 
 /// Maximum cache entries before clearing
 const MAX_CACHE_SIZE: usize = 500;
 
+/// Store country asked for when an app isn't available in the user's own region.
+const FALLBACK_COUNTRY_CODE: &str = "us";
+
+/// Attempts per appdetails request before a transient failure is given up on.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry; doubled for each further one.
+const RETRY_BASE_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Outcome of one appdetails request.
+enum AppDetails {
+    Found(serde_json::Value),
+    /// `success: false`: unknown app, or hidden in this region
+    NotFound,
+    /// HTTP 403, which Steam returns for region-locked store content
+    Blocked,
+    /// Any other failure status (rate limits, outages)
+    Unavailable,
+}
+
+async fn fetch_app_details(
+    client: &reqwest::Client,
+    id: &str,
+    country_code: Option<&str>,
+) -> Result<AppDetails, String> {
+    let mut url = format!(
+        "https://store.steampowered.com/api/appdetails?appids={}",
+        id
+    );
+    if let Some(cc) = country_code {
+        url.push_str(&format!("&cc={}", cc));
+    }
+
+    let mut attempt = 1;
+    loop {
+        match request_app_details(client, &url, id).await {
+            Err(e) if attempt < MAX_ATTEMPTS => {
+                let delay = RETRY_BASE_DELAY * 2u32.pow(attempt - 1);
+                eprintln!(
+                    "[SteamAPI] Attempt {}/{} for appId {} failed ({}), retrying in {}ms",
+                    attempt,
+                    MAX_ATTEMPTS,
+                    id,
+                    e.message,
+                    delay.as_millis()
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) if e.bad_status => return Ok(AppDetails::Unavailable),
+            Err(e) => return Err(format!("[SteamAPI] {} for appId {}", e.message, id)),
+            Ok(details) => return Ok(details),
+        }
+    }
+}
+
+/// A failed appdetails request that may succeed if sent again.
+struct TransientError {
+    message: String,
+    /// Failed on the HTTP status (5xx, 429) rather than the connection or body
+    bad_status: bool,
+}
+
+/// Send one appdetails request. Network errors, 5xx/429 responses and unreadable bodies
+/// are transient; a valid response with `success: false` is a definitive `NotFound`.
+async fn request_app_details(
+    client: &reqwest::Client,
+    url: &str,
+    id: &str,
+) -> Result<AppDetails, TransientError> {
+    let response = client.get(url).send().await.map_err(|e| TransientError {
+        message: format!("Request failed: {}", e),
+        bad_status: false,
+    })?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::FORBIDDEN {
+        return Ok(AppDetails::Blocked);
+    }
+    if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(TransientError {
+            message: format!("HTTP {}", status),
+            bad_status: true,
+        });
+    }
+    if !status.is_success() {
+        return Ok(AppDetails::Unavailable);
+    }
+
+    let mut data: serde_json::Value = response.json().await.map_err(|e| TransientError {
+        message: format!("Failed to parse JSON: {}", e),
+        bad_status: false,
+    })?;
+
+    // Check if data[id].success && data[id].data exists
+    let success = data[id]["success"].as_bool().unwrap_or(false);
+    match data.get_mut(id).and_then(|entry| entry.get_mut("data")) {
+        Some(d) if success => Ok(AppDetails::Found(d.take())),
+        _ => Ok(AppDetails::NotFound),
+    }
+}
+
+/// `required_age` is a number for most apps but a string for some.
+fn required_age(app_data: &serde_json::Value) -> u32 {
+    match &app_data["required_age"] {
+        serde_json::Value::String(s) => s.trim().parse().unwrap_or(0),
+        v => v.as_u64().unwrap_or(0) as u32,
+    }
+}
+
 /// Fetch game info from Steam Store API with caching.
 ///
 /// # Arguments
@@ -43,40 +164,24 @@ pub async fn get_game_info(
         }
     }
 
-    let url = format!(
-        "https://store.steampowered.com/api/appdetails?appids={}",
-        id
-    );
-
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("[SteamAPI] Request failed for appId {}: {}", id, e))?;
-
-    if !response.status().is_success() {
-        return Ok(None);
-    }
-
-    let data: serde_json::Value = response
-        .json()
-        .await
-        .map_err(|e| format!("[SteamAPI] Failed to parse JSON for appId {}: {}", id, e))?;
-
-    // Check if data[id].success && data[id].data exists
-    let app_data = match data.get(&id) {
-        Some(entry) => {
-            let success = entry.get("success").and_then(|v| v.as_bool()).unwrap_or(false);
-            if !success {
-                return Ok(None);
-            }
-            match entry.get("data") {
-                Some(d) => d,
-                None => return Ok(None),
+    // Apps Steam hides in the user's region come back as `success: false` (or a 403);
+    // asking for another store country often still returns their details
+    let first = fetch_app_details(client, &id, None).await?;
+    let (app_data, region_blocked) = match first {
+        AppDetails::Found(data) => (data, false),
+        AppDetails::Unavailable => return Ok(None),
+        AppDetails::NotFound | AppDetails::Blocked => {
+            match fetch_app_details(client, &id, Some(FALLBACK_COUNTRY_CODE)).await? {
+                AppDetails::Found(data) => (data, true),
+                _ if matches!(first, AppDetails::Blocked) => {
+                    // Known to be blocked, so worth explaining even without any details
+                    (serde_json::Value::Null, true)
+                }
+                _ => return Ok(None),
             }
         }
-        None => return Ok(None),
     };
+    let app_data = &app_data;
 
     let info = GameInfo {
         name: app_data
@@ -95,6 +200,10 @@ pub async fn get_game_info(
             .get("type")
             .and_then(|v| v.as_str())
             .map(|s| s.to_string()),
+        is_free: app_data.get("is_free").and_then(|v| v.as_bool()),
+        has_price: app_data.get("price_overview").is_some_and(|v| v.is_object()),
+        region_blocked,
+        age_gated: required_age(app_data) > 0,
     };
 
     // Cache the result
@@ -108,6 +217,28 @@ pub async fn get_game_info(
     Ok(Some(info))
 }
 
+/// Guess whether downloading an app needs depot keys.
+///
+/// Free apps usually download anonymously; anything with a price, or that the
+/// store doesn't know about (delisted, unreleased), is assumed to need keys.
+pub async fn app_requires_key(
+    client: &reqwest::Client,
+    cache: &Arc<Mutex<HashMap<String, serde_json::Value>>>,
+    app_id: &str,
+) -> Result<bool, String> {
+    let info = get_game_info(client, cache, app_id).await?;
+
+    Ok(match info {
+        Some(info) => info.has_price || info.is_free != Some(true),
+        None => true,
+    })
+}
+
+/// Whether `app_id` is a Steam App ID: one or more ASCII digits.
+pub fn is_valid_app_id(app_id: &str) -> bool {
+    !app_id.is_empty() && app_id.chars().all(|c| c.is_ascii_digit())
+}
+
 /// Sanitize a game name for use in folder names.
 /// Removes characters not allowed in Windows folder names: < > : " / \ | ? *
 /// Also trims whitespace and trailing dots/spaces.
@@ -0,0 +1,9 @@
+use std::path::Path;
+
+/// Move a file or folder to the OS recycle bin / trash instead of deleting it.
+/// On Linux this follows the freedesktop.org Trash spec, including the per-volume
+/// `.Trash-$uid` folders, so downloads on another filesystem can be trashed too.
+/// Fails (leaving the path in place) if the trash can't take it.
+pub fn move_to_trash(path: &Path) -> Result<(), String> {
+    ::trash::delete(path).map_err(|e| format!("Failed to move {} to the trash: {}", path.display(), e))
+}
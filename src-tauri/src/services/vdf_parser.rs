@@ -57,16 +57,223 @@ pub fn xor_decrypt_hex(hex_string: &str, xor_key: &[u8]) -> String {
     hex_encode(&result)
 }
 
-fn hex_decode(s: &str) -> Option<Vec<u8>> {
-    if s.len() % 2 != 0 {
+/// XOR encrypt a hex-encoded key the way `xor_decrypt_hex` decrypts it. XOR is its own
+/// inverse, so this is the same operation, named for the export direction.
+pub fn xor_encrypt_hex(hex_string: &str, xor_key: &[u8]) -> String {
+    xor_decrypt_hex(hex_string, xor_key)
+}
+
+/// Build Key.vdf content from a depot-key map, depots in numeric order. For a sean-who
+/// repo the keys are XOR encrypted, so `parse_key_vdf` with the same repo reads them back.
+pub fn build_key_vdf(keys: &HashMap<String, String>, repo: Option<&str>) -> String {
+    let encrypt = repo.is_some_and(|r| r.contains("sean-who"));
+
+    let mut depots: Vec<(&String, &String)> = keys.iter().collect();
+    depots.sort_by_key(|(depot_id, _)| depot_id.parse::<u64>().unwrap_or(u64::MAX));
+
+    let mut vdf = String::from("\"depots\"\n{\n");
+    for (depot_id, key) in depots {
+        let key = if encrypt {
+            xor_encrypt_hex(key, SEAN_WHO_XOR_KEY)
+        } else {
+            key.to_lowercase()
+        };
+        vdf.push_str(&format!(
+            "\t\"{}\"\n\t{{\n\t\t\"DecryptionKey\"\t\t\"{}\"\n\t}}\n",
+            depot_id, key
+        ));
+    }
+    vdf.push_str("}\n");
+    vdf
+}
+
+/// A node in a text VDF (KeyValues) document.
+#[derive(Debug, Clone)]
+pub enum VdfValue {
+    String(String),
+    /// Child entries in file order; keys may repeat.
+    Object(Vec<(String, VdfValue)>),
+}
+
+impl VdfValue {
+    /// First child with the given key (case-insensitive, like Steam).
+    pub fn get(&self, key: &str) -> Option<&VdfValue> {
+        match self {
+            VdfValue::Object(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            VdfValue::String(_) => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            VdfValue::String(s) => Some(s),
+            VdfValue::Object(_) => None,
+        }
+    }
+
+    pub fn entries(&self) -> &[(String, VdfValue)] {
+        match self {
+            VdfValue::Object(entries) => entries,
+            VdfValue::String(_) => &[],
+        }
+    }
+}
+
+/// Parse a text VDF document into a root object holding its top-level entries.
+/// Handles quoted and bare tokens, `\` escapes, `//` comments and skips `[$OS]` conditionals.
+pub fn parse_vdf(content: &str) -> Result<VdfValue, String> {
+    let tokens = tokenize_vdf(content)?;
+    let mut pos = 0;
+    let root = parse_vdf_entries(&tokens, &mut pos, false)?;
+    Ok(VdfValue::Object(root))
+}
+
+#[derive(Debug, PartialEq)]
+enum VdfToken {
+    Text(String),
+    Open,
+    Close,
+}
+
+fn tokenize_vdf(content: &str) -> Result<Vec<VdfToken>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => tokens.push(VdfToken::Open),
+            '}' => tokens.push(VdfToken::Close),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => text.push('\n'),
+                            Some('t') => text.push('\t'),
+                            Some(other) => text.push(other),
+                            None => return Err("Unterminated escape in VDF string".to_string()),
+                        },
+                        Some(other) => text.push(other),
+                        None => return Err("Unterminated VDF string".to_string()),
+                    }
+                }
+                tokens.push(VdfToken::Text(text));
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            '[' => {
+                // Platform conditional such as [$WIN32]; ignored
+                for next in chars.by_ref() {
+                    if next == ']' {
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut text = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || matches!(next, '{' | '}' | '"') {
+                        break;
+                    }
+                    text.push(next);
+                    chars.next();
+                }
+                tokens.push(VdfToken::Text(text));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_vdf_entries(tokens: &[VdfToken], pos: &mut usize, nested: bool) -> Result<Vec<(String, VdfValue)>, String> {
+    let mut entries = Vec::new();
+
+    while let Some(token) = tokens.get(*pos) {
+        *pos += 1;
+        let key = match token {
+            VdfToken::Text(key) => key.clone(),
+            VdfToken::Close if nested => return Ok(entries),
+            VdfToken::Close => return Err("Unexpected '}' in VDF".to_string()),
+            VdfToken::Open => return Err("Unexpected '{' in VDF".to_string()),
+        };
+
+        let value = match tokens.get(*pos) {
+            Some(VdfToken::Text(value)) => VdfValue::String(value.clone()),
+            Some(VdfToken::Open) => {
+                *pos += 1;
+                VdfValue::Object(parse_vdf_entries(tokens, pos, true)?)
+            }
+            _ => return Err(format!("Missing value for VDF key \"{}\"", key)),
+        };
+        if let VdfValue::String(_) = value {
+            *pos += 1;
+        }
+        entries.push((key, value));
+    }
+
+    if nested {
+        return Err("Unclosed '{' in VDF".to_string());
+    }
+    Ok(entries)
+}
+
+/// Decode a hex string; None for an odd length or a non-hex digit.
+pub(crate) fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
         return None;
     }
     (0..s.len())
         .step_by(2)
-        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
         .collect()
 }
 
 fn hex_encode(bytes: &[u8]) -> String {
     bytes.iter().map(|b| format!("{:02x}", b)).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_keys() -> HashMap<String, String> {
+        HashMap::from([
+            ("228988".to_string(), "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef".to_string()),
+            ("1245621".to_string(), "fedcba9876543210fedcba9876543210fedcba9876543210fedcba9876543210".to_string()),
+        ])
+    }
+
+    #[test]
+    fn key_vdf_round_trips_plain() {
+        let keys = sample_keys();
+        let vdf = build_key_vdf(&keys, Some("SteamAutoCracks/ManifestHub"));
+        assert_eq!(parse_key_vdf(&vdf, Some("SteamAutoCracks/ManifestHub")), keys);
+        assert!(parse_vdf(&vdf).is_ok());
+    }
+
+    #[test]
+    fn key_vdf_round_trips_sean_who() {
+        let keys = sample_keys();
+        let vdf = build_key_vdf(&keys, Some("sean-who/ManifestAutoUpdate"));
+        // Stored encrypted, read back decrypted
+        assert!(!vdf.contains(&keys["228988"]));
+        assert_eq!(parse_key_vdf(&vdf, Some("sean-who/ManifestAutoUpdate")), keys);
+    }
+
+    #[test]
+    fn key_vdf_lists_depots_in_numeric_order() {
+        let vdf = build_key_vdf(&sample_keys(), None);
+        assert!(vdf.find("\"228988\"").unwrap() < vdf.find("\"1245621\"").unwrap());
+    }
+}